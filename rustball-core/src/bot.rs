@@ -0,0 +1,189 @@
+use crate::rng::Rng;
+use crate::{MatchState, PlayerInput, Stadium, Team};
+use glam::{Mat2, Vec2};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// How far ahead the bot expects its player to slide, so it brakes before reaching the target.
+const BRAKING_TICKS: f32 = 12.0;
+// Distance from the target at which the bot starts to slow down.
+const ARRIVAL_DISTANCE: f32 = 30.0;
+// Cosine of the largest angle between the aim and the direction to the ball the bot kicks at.
+const KICK_ALIGNMENT: f32 = 0.8;
+// Extra space kept from the ball when going around it, so the bot doesn't push it backwards.
+const DETOUR_MARGIN: f32 = 15.0;
+// Part of the way from the own goal to the ball a defender stands at.
+const DEFENDER_POSITION: f32 = 0.35;
+// How far in front of the goal a goalkeeper stands.
+const GOALKEEPER_DISTANCE: f32 = 40.0;
+// A goalkeeper leaves the goal to clear the ball closer than this.
+const GOALKEEPER_CLEARING_DISTANCE: f32 = 220.0;
+
+// How well a bot plays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    // Ticks before the bot notices where the ball moved.
+    pub fn reaction_delay(self) -> usize {
+        match self {
+            Difficulty::Easy => 18,
+            Difficulty::Normal => 9,
+            Difficulty::Hard => 2,
+        }
+    }
+
+    // Largest angle in radians the bot's kicks miss the goal center by.
+    pub fn aim_noise(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.35,
+            Difficulty::Normal => 0.15,
+            Difficulty::Hard => 0.04,
+        }
+    }
+}
+
+// Where a bot positions itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BotRole {
+    // Always goes for the ball and shoots at the goal.
+    Attacker,
+    // Stands between the ball and the own goal, goes for the ball in the own half.
+    Defender,
+    // Stays in front of the own goal, goes for the ball only when it comes close.
+    Goalkeeper,
+}
+
+impl BotRole {
+    pub const ALL: [BotRole; 3] = [BotRole::Attacker, BotRole::Defender, BotRole::Goalkeeper];
+}
+
+// Computer-controlled player, deciding its input from the state of the match.
+// It's deterministic, bots created with the same seed play the same way.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bot {
+    // Index of the controlled player in the match.
+    pub player: usize,
+    pub difficulty: Difficulty,
+    pub role: BotRole,
+    // Positions of the ball in the last ticks, the oldest first.
+    seen_ball: VecDeque<Vec2>,
+    // Angle the next kick misses by, picked again after every kick.
+    aim_error: f32,
+    rng: Rng,
+}
+
+impl Bot {
+    pub fn new(player: usize, difficulty: Difficulty, role: BotRole, seed: u64) -> Self {
+        let mut bot = Bot {
+            player,
+            difficulty,
+            role,
+            seen_ball: VecDeque::new(),
+            aim_error: 0.,
+            // Xorshift gets stuck at zero.
+            rng: Rng::new(seed),
+        };
+        bot.aim_error = bot.next_aim_error();
+        bot
+    }
+
+    // Input of the bot's player for the next tick.
+    // Should be called once per tick, the bot remembers the ball's positions to react late.
+    pub fn input(&mut self, state: &MatchState) -> PlayerInput {
+        self.seen_ball.push_back(state.ball().position);
+        while self.seen_ball.len() > self.difficulty.reaction_delay() + 1 {
+            self.seen_ball.pop_front();
+        }
+        let ball = self.seen_ball[0];
+
+        let team = state.players[self.player].team;
+        let own_goal = goal_center(&state.stadium, team);
+        let ball_is_close = ball.distance(own_goal) < GOALKEEPER_CLEARING_DISTANCE;
+        let ball_in_own_half = ball.x * team.attack_direction() < 0.;
+        let guard_point = match self.role {
+            BotRole::Attacker => None,
+            BotRole::Defender if ball_in_own_half => None,
+            BotRole::Defender => Some(own_goal.lerp(ball, DEFENDER_POSITION)),
+            BotRole::Goalkeeper if ball_is_close => None,
+            BotRole::Goalkeeper => {
+                Some(own_goal + (ball - own_goal).normalize_or_zero() * GOALKEEPER_DISTANCE)
+            }
+        };
+
+        match guard_point {
+            Some(point) => PlayerInput {
+                direction: self.steer(state, point),
+                kick: false,
+            },
+            None => self.attack(state, ball),
+        }
+    }
+
+    // Runs behind the ball and shoots it towards the opponent's goal.
+    fn attack(&mut self, state: &MatchState, ball: Vec2) -> PlayerInput {
+        let player = &state.players[self.player];
+        let disc = state.player_disc(self.player);
+        let physics = &state.stadium.physics;
+        let contact_distance = disc.radius + physics.ball.radius;
+
+        let goal = goal_center(&state.stadium, player.team.opponent());
+        let aim = Mat2::from_angle(self.aim_error) * (goal - ball).normalize_or_zero();
+        let to_ball = ball - disc.position;
+        let aligned = to_ball.normalize_or_zero().dot(aim) > KICK_ALIGNMENT;
+
+        let target = if aligned {
+            // Run through the ball, pushing it forward.
+            ball + aim * contact_distance
+        } else if to_ball.dot(aim) < 0. {
+            // The player is in front of the ball, go around it on the closer side.
+            let mut side = aim.perp();
+            if side.dot(-to_ball) < 0. {
+                side = -side;
+            }
+            ball + (side - aim) * (contact_distance + DETOUR_MARGIN)
+        } else {
+            ball - aim * contact_distance
+        };
+
+        let in_reach = to_ball.length() <= contact_distance + physics.kick_reach;
+        // The kick button has to be released between kicks.
+        let kick = aligned && in_reach && player.kick_released;
+        if kick {
+            self.aim_error = self.next_aim_error();
+        }
+        PlayerInput {
+            direction: self.steer(state, target),
+            kick,
+        }
+    }
+
+    // Direction towards the target, slowing down before the player gets there.
+    fn steer(&self, state: &MatchState, target: Vec2) -> Vec2 {
+        let disc = state.player_disc(self.player);
+        let stopping_point = disc.position + disc.velocity * BRAKING_TICKS;
+        ((target - stopping_point) / ARRIVAL_DISTANCE).clamp_length_max(1.)
+    }
+
+    fn next_aim_error(&mut self) -> f32 {
+        (self.rng.next_f32() * 2. - 1.) * self.difficulty.aim_noise()
+    }
+}
+
+// Middle of the goal defended by the given team, or of its side of the pitch without a goal.
+fn goal_center(stadium: &Stadium, team: Team) -> Vec2 {
+    stadium
+        .goals
+        .iter()
+        .find(|goal| goal.team == team)
+        .map_or_else(
+            || Vec2::new(-team.attack_direction() * stadium.width / 2., 0.),
+            |goal| Vec2::new(goal.x, (goal.top + goal.bottom) / 2.),
+        )
+}
@@ -0,0 +1,171 @@
+use crate::protocol::{ClientMessage, DiscMotion, Snapshot};
+use crate::{MatchRules, MatchState, PlayerInput, Stadium, Team, TICKS_PER_SECOND};
+use std::collections::VecDeque;
+
+// How far behind the latest snapshot the other players and the ball are shown,
+// so there's usually a later snapshot to interpolate towards.
+pub const INTERPOLATION_TICKS: f64 = 6.;
+// Inputs kept for the server at most, older ones are assumed lost.
+const MAX_PENDING_INPUTS: usize = 2 * TICKS_PER_SECOND as usize;
+
+// Online match as a client sees it.
+// The client's own player is predicted by stepping the match with its inputs right away,
+// the rest follows the snapshots of the server, a bit in the past.
+#[derive(Clone, Debug)]
+pub struct ClientMatch {
+    // `None` for a spectator, who only watches the snapshots.
+    player: Option<usize>,
+    // The match in the latest snapshot.
+    server: MatchState,
+    // The match after the inputs the server hasn't applied yet.
+    predicted: MatchState,
+    // Inputs sent to the server and not applied yet, with their sequence numbers.
+    pending: VecDeque<(u64, PlayerInput)>,
+    next_sequence: u64,
+    // Received snapshots with their sequence numbers, from the oldest.
+    snapshots: VecDeque<(u64, Snapshot)>,
+    // Server tick the other discs are shown at.
+    render_tick: f64,
+}
+
+impl ClientMatch {
+    // Match of the start message, where the client controls the given player.
+    pub fn new(player: Option<usize>, stadium: Stadium, rules: MatchRules, teams: &[Team]) -> Self {
+        let server = MatchState::new(stadium, teams, rules);
+        ClientMatch {
+            player,
+            predicted: server.clone(),
+            server,
+            pending: VecDeque::new(),
+            next_sequence: 0,
+            snapshots: VecDeque::new(),
+            render_tick: 0.,
+        }
+    }
+
+    pub fn player(&self) -> Option<usize> {
+        self.player
+    }
+
+    pub fn server_state(&self) -> &MatchState {
+        &self.server
+    }
+
+    pub fn predicted(&self) -> &MatchState {
+        &self.predicted
+    }
+
+    // Number of inputs not applied by the server yet, about the round trip time in ticks.
+    pub fn pending_inputs(&self) -> usize {
+        self.pending.len()
+    }
+
+    // Predicts the next tick with the input of the client's player.
+    // Returns the message with the input to send to the server, spectators have none.
+    pub fn step(&mut self, input: PlayerInput) -> Option<ClientMessage> {
+        self.render_tick += 1.;
+        // Spectators have no input to send.
+        self.player?;
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.pending.push_back((sequence, input));
+        if self.pending.len() > MAX_PENDING_INPUTS {
+            self.pending.pop_front();
+        }
+        self.predicted.step(&self.inputs(input));
+        Some(ClientMessage::Input { sequence, input })
+    }
+
+    // Inputs of all players when the client's one has the given input,
+    // the others are predicted to do nothing.
+    fn inputs(&self, input: PlayerInput) -> Vec<PlayerInput> {
+        let mut inputs = vec![PlayerInput::default(); self.server.players.len()];
+        if let Some(own) = self.player.and_then(|player| inputs.get_mut(player)) {
+            *own = input;
+        }
+        inputs
+    }
+
+    // Takes the snapshot of the server as the truth and predicts the pending inputs again.
+    // Snapshots older than the latest one came late and are ignored,
+    // so are the ones which don't fit the match, only a broken server sends them.
+    pub fn receive_snapshot(&mut self, sequence: u64, snapshot: Snapshot, last_input: Option<u64>) {
+        let latest = self.snapshots.back().map(|(latest, _)| *latest);
+        if latest.is_some_and(|latest| sequence <= latest) || !self.server.apply_snapshot(&snapshot)
+        {
+            return;
+        }
+        if let Some(last_input) = last_input {
+            self.pending.retain(|(pending, _)| *pending > last_input);
+        }
+        self.predicted = self.server.clone();
+        let inputs: Vec<Vec<PlayerInput>> = self
+            .pending
+            .iter()
+            .map(|(_, input)| self.inputs(*input))
+            .collect();
+        for inputs in inputs {
+            self.predicted.step(&inputs);
+        }
+
+        // The shown tick follows the snapshots, jumping only when it's way off.
+        let target = sequence as f64 - INTERPOLATION_TICKS;
+        if latest.is_none() || (self.render_tick - target).abs() > INTERPOLATION_TICKS {
+            self.render_tick = target;
+        } else {
+            self.render_tick += (target - self.render_tick) * 0.1;
+        }
+        self.snapshots.push_back((sequence, snapshot));
+        while self.snapshots.len() > 2 && (self.snapshots[1].0 as f64) < self.render_tick {
+            self.snapshots.pop_front();
+        }
+    }
+
+    // Positions of all discs at the shown tick, between the snapshots around it.
+    // A goal teleports the discs, so they're not interpolated across it.
+    fn interpolated(&self) -> Option<Vec<DiscMotion>> {
+        let next = self
+            .snapshots
+            .iter()
+            .position(|(sequence, _)| *sequence as f64 >= self.render_tick);
+        let (from, to) = match next {
+            Some(0) => return self.snapshots.front().map(|(_, s)| s.discs.clone()),
+            Some(next) => (&self.snapshots[next - 1], &self.snapshots[next]),
+            None => return self.snapshots.back().map(|(_, s)| s.discs.clone()),
+        };
+        if from.1.score != to.1.score || from.1.tick > to.1.tick {
+            return Some(to.1.discs.clone());
+        }
+        let alpha = ((self.render_tick - from.0 as f64) / (to.0 - from.0) as f64) as f32;
+        let discs = from
+            .1
+            .discs
+            .iter()
+            .zip(to.1.discs.iter())
+            .map(|(from, to)| DiscMotion {
+                position: from.position.lerp(to.position, alpha),
+                velocity: from.velocity.lerp(to.velocity, alpha),
+            })
+            .collect();
+        Some(discs)
+    }
+
+    // The match to show: the score and the clock of the server,
+    // the client's player as predicted and the other discs interpolated.
+    pub fn display_state(&self) -> MatchState {
+        let mut state = self.server.clone();
+        if let Some(discs) = self.interpolated() {
+            for (disc, motion) in state.discs.iter_mut().zip(discs) {
+                disc.position = motion.position;
+                disc.velocity = motion.velocity;
+            }
+        }
+        if let Some(index) = self.player {
+            if let Some(player) = self.predicted.players.get(index) {
+                state.discs[player.disc] = self.predicted.discs[player.disc].clone();
+                state.players[index] = player.clone();
+            }
+        }
+        state
+    }
+}
@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use std::ops::{BitAnd, BitOr};
+
+// Set of collision groups stored as bit flags, named and numbered as in HaxBall.
+// Stadium files list the groups by their names, e.g. `["ball", "wall"]`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct CollisionGroups(u32);
+
+impl CollisionGroups {
+    pub const NONE: CollisionGroups = CollisionGroups(0);
+    pub const BALL: CollisionGroups = CollisionGroups(1);
+    pub const RED: CollisionGroups = CollisionGroups(2);
+    pub const BLUE: CollisionGroups = CollisionGroups(4);
+    pub const RED_KICKOFF: CollisionGroups = CollisionGroups(8);
+    pub const BLUE_KICKOFF: CollisionGroups = CollisionGroups(16);
+    pub const WALL: CollisionGroups = CollisionGroups(32);
+    // Every group above, but not the ones below.
+    pub const ALL: CollisionGroups = CollisionGroups(63);
+    pub const KICK: CollisionGroups = CollisionGroups(64);
+    pub const SCORE: CollisionGroups = CollisionGroups(128);
+    pub const C0: CollisionGroups = CollisionGroups(1 << 28);
+    pub const C1: CollisionGroups = CollisionGroups(1 << 29);
+    pub const C2: CollisionGroups = CollisionGroups(1 << 30);
+    pub const C3: CollisionGroups = CollisionGroups(1 << 31);
+
+    const NAMES: [(&'static str, CollisionGroups); 13] = [
+        ("ball", CollisionGroups::BALL),
+        ("red", CollisionGroups::RED),
+        ("blue", CollisionGroups::BLUE),
+        ("redKO", CollisionGroups::RED_KICKOFF),
+        ("blueKO", CollisionGroups::BLUE_KICKOFF),
+        ("wall", CollisionGroups::WALL),
+        ("all", CollisionGroups::ALL),
+        ("kick", CollisionGroups::KICK),
+        ("score", CollisionGroups::SCORE),
+        ("c0", CollisionGroups::C0),
+        ("c1", CollisionGroups::C1),
+        ("c2", CollisionGroups::C2),
+        ("c3", CollisionGroups::C3),
+    ];
+
+    pub fn from_name(name: &str) -> Option<CollisionGroups> {
+        CollisionGroups::NAMES
+            .iter()
+            .find(|(group_name, _)| *group_name == name)
+            .map(|&(_, group)| group)
+    }
+
+    pub fn contains(&self, other: CollisionGroups) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersects(&self, other: CollisionGroups) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    // Same as `|`, but usable in constants.
+    pub const fn union(self, other: CollisionGroups) -> CollisionGroups {
+        CollisionGroups(self.0 | other.0)
+    }
+}
+
+// Whether two objects collide, each of them has to have a group of the other one in its mask.
+pub fn can_collide(
+    group1: CollisionGroups,
+    mask1: CollisionGroups,
+    group2: CollisionGroups,
+    mask2: CollisionGroups,
+) -> bool {
+    mask1.intersects(group2) && mask2.intersects(group1)
+}
+
+impl BitOr for CollisionGroups {
+    type Output = CollisionGroups;
+
+    fn bitor(self, other: CollisionGroups) -> CollisionGroups {
+        CollisionGroups(self.0 | other.0)
+    }
+}
+
+impl BitAnd for CollisionGroups {
+    type Output = CollisionGroups;
+
+    fn bitand(self, other: CollisionGroups) -> CollisionGroups {
+        CollisionGroups(self.0 & other.0)
+    }
+}
+
+impl TryFrom<Vec<String>> for CollisionGroups {
+    type Error = String;
+
+    fn try_from(names: Vec<String>) -> Result<Self, Self::Error> {
+        names
+            .iter()
+            .try_fold(CollisionGroups::NONE, |groups, name| {
+                CollisionGroups::from_name(name)
+                    .map(|group| groups | group)
+                    .ok_or_else(|| format!("unknown collision group `{}`", name))
+            })
+    }
+}
+
+impl From<CollisionGroups> for Vec<String> {
+    // Names of the groups, using "all" where possible.
+    fn from(groups: CollisionGroups) -> Vec<String> {
+        let mut remaining = groups;
+        let mut names = Vec::new();
+        for (name, group) in CollisionGroups::NAMES.iter().rev() {
+            if remaining.contains(*group) && remaining.intersects(*group) {
+                names.push(name.to_string());
+                remaining = CollisionGroups(remaining.0 & !group.0);
+            }
+        }
+        names.reverse();
+        names
+    }
+}
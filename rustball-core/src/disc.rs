@@ -0,0 +1,59 @@
+use crate::collision::{self, CollisionGroups};
+use crate::{BALL_INV_MASS, BALL_RADIUS, BALL_RESTITUTION};
+use crate::{PLAYER_INV_MASS, PLAYER_RADIUS, PLAYER_RESTITUTION};
+use glam::Vec2;
+
+// Players collide with everything but the kickoff barriers,
+// which stop them only until the kickoff is taken.
+pub const PLAYER_MASK: CollisionGroups = CollisionGroups::BALL
+    .union(CollisionGroups::RED)
+    .union(CollisionGroups::BLUE)
+    .union(CollisionGroups::WALL);
+
+// A circle taking part in the simulation (a player or the ball).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Disc {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub radius: f32,
+    // Inverse of the mass, zero means the disc can't be moved by collisions.
+    pub inv_mass: f32,
+    // Bounciness, multiplied with the restitution of the other side of a collision.
+    pub restitution: f32,
+    // Groups the disc belongs to and the groups of the objects it collides with.
+    pub group: CollisionGroups,
+    pub mask: CollisionGroups,
+}
+
+impl Disc {
+    pub fn new(position: Vec2, radius: f32, inv_mass: f32, restitution: f32) -> Self {
+        Disc {
+            position,
+            velocity: Vec2::ZERO,
+            radius,
+            inv_mass,
+            restitution,
+            group: CollisionGroups::ALL,
+            mask: CollisionGroups::ALL,
+        }
+    }
+
+    pub fn ball(position: Vec2) -> Self {
+        Disc {
+            group: CollisionGroups::BALL,
+            ..Disc::new(position, BALL_RADIUS, BALL_INV_MASS, BALL_RESTITUTION)
+        }
+    }
+
+    pub fn player(position: Vec2) -> Self {
+        Disc {
+            mask: PLAYER_MASK,
+            ..Disc::new(position, PLAYER_RADIUS, PLAYER_INV_MASS, PLAYER_RESTITUTION)
+        }
+    }
+
+    pub fn collides_with(&self, other: &Disc) -> bool {
+        collision::can_collide(self.group, self.mask, other.group, other.mask)
+            && self.position.distance(other.position) < self.radius + other.radius
+    }
+}
@@ -0,0 +1,533 @@
+// Import of HaxBall stadiums (.hbs files).
+// HaxBall's y axis points down, so every position is mirrored vertically on the way.
+
+use crate::{Background, DiscProperties, Goal, PhysicsConstants, Plane, Post, Segment};
+use crate::{CollisionGroups, Stadium, StadiumError, Team, MAX_SPEED};
+use glam::Vec2;
+use serde_json::{Map, Value};
+
+type Result<T> = std::result::Result<T, StadiumError>;
+
+// Defaults HaxBall uses for the missing properties.
+const DEFAULT_SPAWN_DISTANCE: f32 = 200.0;
+const DEFAULT_BALL: DiscProperties = DiscProperties {
+    radius: 10.0,
+    inv_mass: 1.0,
+    restitution: 0.5,
+    damping: 0.99,
+};
+const DEFAULT_PLAYER: DiscProperties = DiscProperties {
+    radius: 15.0,
+    inv_mass: 0.5,
+    restitution: 0.5,
+    damping: 0.96,
+};
+const DEFAULT_ACCELERATION: f32 = 0.1;
+const DEFAULT_KICKING_ACCELERATION: f32 = 0.07;
+const DEFAULT_KICKING_DAMPING: f32 = 0.96;
+const DEFAULT_KICK_STRENGTH: f32 = 5.0;
+const DEFAULT_KICK_REACH: f32 = 4.0;
+const GRASS_COLOR: u32 = 0x718C5A;
+const HOCKEY_COLOR: u32 = 0x555555;
+// Vertical distance between the generated spawn points.
+const SPAWN_SPACING: f32 = 55.0;
+
+// Fields each kind of objects may have, anything else is reported as an error.
+const STADIUM_FIELDS: [&str; 22] = [
+    "name",
+    "width",
+    "height",
+    "maxViewWidth",
+    "cameraWidth",
+    "cameraHeight",
+    "cameraFollow",
+    "spawnDistance",
+    "canBeStored",
+    "kickOffReset",
+    "bg",
+    "traits",
+    "vertexes",
+    "segments",
+    "goals",
+    "discs",
+    "planes",
+    "joints",
+    "redSpawnPoints",
+    "blueSpawnPoints",
+    "playerPhysics",
+    "ballPhysics",
+];
+const BACKGROUND_FIELDS: [&str; 7] = [
+    "type",
+    "width",
+    "height",
+    "kickOffRadius",
+    "cornerRadius",
+    "goalLine",
+    "color",
+];
+const VERTEX_FIELDS: [&str; 5] = ["x", "y", "bCoef", "cMask", "cGroup"];
+const SEGMENT_FIELDS: [&str; 10] = [
+    "v0", "v1", "bCoef", "curve", "curveF", "bias", "cMask", "cGroup", "vis", "color",
+];
+const GOAL_FIELDS: [&str; 3] = ["p0", "p1", "team"];
+const PLANE_FIELDS: [&str; 5] = ["normal", "dist", "bCoef", "cMask", "cGroup"];
+const DISC_FIELDS: [&str; 10] = [
+    "pos", "speed", "gravity", "radius", "invMass", "damping", "color", "bCoef", "cMask", "cGroup",
+];
+const PLAYER_FIELDS: [&str; 11] = [
+    "radius",
+    "bCoef",
+    "invMass",
+    "damping",
+    "cGroup",
+    "acceleration",
+    "kickingAcceleration",
+    "kickingDamping",
+    "kickStrength",
+    "kickback",
+    "gravity",
+];
+
+fn parse_error(message: String) -> StadiumError {
+    StadiumError::Parse(message)
+}
+
+// Object of the file, with the properties of its trait used for the missing fields.
+struct Object<'a> {
+    // Where the object is in the file, for the error messages.
+    path: String,
+    fields: &'a Map<String, Value>,
+    base: Option<&'a Map<String, Value>>,
+}
+
+impl<'a> Object<'a> {
+    fn new(
+        path: String,
+        value: &'a Value,
+        traits: &'a Map<String, Value>,
+        known_fields: &[&str],
+    ) -> Result<Self> {
+        let fields = value
+            .as_object()
+            .ok_or_else(|| parse_error(format!("{} must be an object", path)))?;
+        for key in fields.keys() {
+            if key != "trait" && !known_fields.contains(&key.as_str()) {
+                return Err(parse_error(format!("{}: unknown field `{}`", path, key)));
+            }
+        }
+
+        let base = match fields.get("trait") {
+            None => None,
+            Some(Value::String(name)) => Some(
+                traits
+                    .get(name)
+                    .and_then(Value::as_object)
+                    .ok_or_else(|| parse_error(format!("{}: unknown trait `{}`", path, name)))?,
+            ),
+            Some(_) => return Err(parse_error(format!("{}: trait must be a name", path))),
+        };
+
+        Ok(Object { path, fields, base })
+    }
+
+    fn get(&self, key: &str) -> Option<&'a Value> {
+        self.fields
+            .get(key)
+            .or_else(|| self.base.and_then(|base| base.get(key)))
+    }
+
+    fn error(&self, key: &str, expected: &str) -> StadiumError {
+        parse_error(format!("{}: `{}` must be {}", self.path, key, expected))
+    }
+
+    fn unsupported(&self, key: &str) -> StadiumError {
+        StadiumError::Unsupported(format!(
+            "{}: `{}` other than the default isn't supported",
+            self.path, key
+        ))
+    }
+
+    fn number(&self, key: &str, default: f32) -> Result<f32> {
+        match self.get(key) {
+            None => Ok(default),
+            Some(value) => value
+                .as_f64()
+                .map(|value| value as f32)
+                .ok_or_else(|| self.error(key, "a number")),
+        }
+    }
+
+    fn required_number(&self, key: &str) -> Result<f32> {
+        match self.get(key) {
+            None => Err(self.error(key, "given")),
+            Some(_) => self.number(key, 0.),
+        }
+    }
+
+    fn index(&self, key: &str) -> Result<usize> {
+        self.get(key)
+            .and_then(Value::as_u64)
+            .map(|index| index as usize)
+            .ok_or_else(|| self.error(key, "an index"))
+    }
+
+    fn string(&self, key: &str) -> Result<Option<&'a str>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(value) => value
+                .as_str()
+                .map(Some)
+                .ok_or_else(|| self.error(key, "a string")),
+        }
+    }
+
+    fn bool(&self, key: &str, default: bool) -> Result<bool> {
+        match self.get(key) {
+            None => Ok(default),
+            Some(value) => value.as_bool().ok_or_else(|| self.error(key, "a boolean")),
+        }
+    }
+
+    fn point(&self, key: &str) -> Result<Option<Vec2>> {
+        let value = match self.get(key) {
+            None => return Ok(None),
+            Some(value) => value,
+        };
+        coordinates(value)
+            .map(Some)
+            .ok_or_else(|| self.error(key, "a point [x, y]"))
+    }
+
+    fn required_point(&self, key: &str) -> Result<Vec2> {
+        self.point(key)?.ok_or_else(|| self.error(key, "given"))
+    }
+
+    fn groups(&self, key: &str, default: CollisionGroups) -> Result<CollisionGroups> {
+        let names = match self.get(key) {
+            None => return Ok(default),
+            Some(value) => value
+                .as_array()
+                .ok_or_else(|| self.error(key, "a list of collision groups"))?,
+        };
+        names
+            .iter()
+            .try_fold(CollisionGroups::NONE, |groups, name| {
+                name.as_str()
+                    .and_then(CollisionGroups::from_name)
+                    .map(|group| groups | group)
+                    .ok_or_else(|| self.error(key, "a list of collision groups"))
+            })
+    }
+
+    // Fails on the fields which change the physics in a way RustBall doesn't simulate.
+    fn require_default_number(&self, key: &str, default: f32) -> Result<()> {
+        if self.number(key, default)? != default {
+            return Err(self.unsupported(key));
+        }
+        Ok(())
+    }
+
+    fn require_zero_point(&self, key: &str) -> Result<()> {
+        if self.point(key)?.is_some_and(|point| point != Vec2::ZERO) {
+            return Err(self.unsupported(key));
+        }
+        Ok(())
+    }
+
+    fn disc_properties(&self, default: DiscProperties) -> Result<DiscProperties> {
+        Ok(DiscProperties {
+            radius: self.number("radius", default.radius)?,
+            inv_mass: self.number("invMass", default.inv_mass)?,
+            restitution: self.number("bCoef", default.restitution)?,
+            damping: self.number("damping", default.damping)?,
+        })
+    }
+}
+
+// Elements of the given list field, each with its path for the error messages.
+fn list<'a>(
+    object: &Object<'a>,
+    key: &str,
+) -> Result<impl Iterator<Item = (String, &'a Value)> + 'a> {
+    let values = match object.get(key) {
+        None => &[][..],
+        Some(value) => value
+            .as_array()
+            .map(Vec::as_slice)
+            .ok_or_else(|| object.error(key, "a list"))?,
+    };
+    let key = key.to_string();
+    Ok(values
+        .iter()
+        .enumerate()
+        .map(move |(i, value)| (format!("{}[{}]", key, i), value)))
+}
+
+// Point given as `[x, y]`, mirrored to the RustBall's axes.
+fn coordinates(value: &Value) -> Option<Vec2> {
+    let coordinates: Option<Vec<f32>> = value.as_array().and_then(|array| {
+        array
+            .iter()
+            .map(|value| value.as_f64().map(|value| value as f32))
+            .collect()
+    });
+    match coordinates.as_deref() {
+        Some(&[x, y]) => Some(Vec2::new(x, -y)),
+        _ => None,
+    }
+}
+
+fn rgb(color: u32) -> Background {
+    let channel = |shift: u32| ((color >> shift) & 0xFF) as f32 / 255.;
+    Background::Color(channel(16), channel(8), channel(0))
+}
+
+fn background(object: &Object) -> Result<Background> {
+    let default = match object.string("type")? {
+        None | Some("none") | Some("grass") => GRASS_COLOR,
+        Some("hockey") => HOCKEY_COLOR,
+        Some(_) => return Err(object.error("type", "\"grass\", \"hockey\" or \"none\"")),
+    };
+
+    let color = match object.get("color") {
+        None => default,
+        Some(Value::String(color)) if color == "transparent" => default,
+        Some(Value::String(color)) => {
+            u32::from_str_radix(color, 16).map_err(|_| object.error("color", "a hex color"))?
+        }
+        Some(Value::Array(channels)) => {
+            let channels: Option<Vec<u64>> = channels.iter().map(Value::as_u64).collect();
+            match channels.as_deref() {
+                Some(&[r, g, b]) => ((r as u32) << 16) | ((g as u32) << 8) | b as u32,
+                _ => return Err(object.error("color", "[r, g, b]")),
+            }
+        }
+        Some(_) => return Err(object.error("color", "a color")),
+    };
+    Ok(rgb(color))
+}
+
+fn spawn_points(object: &Object, key: &str) -> Result<Vec<Vec2>> {
+    list(object, key)?
+        .map(|(_, value)| {
+            coordinates(value).ok_or_else(|| object.error(key, "a list of points [x, y]"))
+        })
+        .collect()
+}
+
+impl Stadium {
+    // Converts a HaxBall stadium, failing on the features RustBall doesn't support.
+    pub fn from_hbs(source: &str) -> Result<Stadium> {
+        let root: Value =
+            serde_json::from_str(source).map_err(|err| parse_error(err.to_string()))?;
+        let empty = Map::new();
+        let traits = match root.get("traits") {
+            None => &empty,
+            Some(traits) => traits
+                .as_object()
+                .ok_or_else(|| parse_error("`traits` must be an object".to_string()))?,
+        };
+        let stadium = Object::new("stadium".to_string(), &root, traits, &STADIUM_FIELDS)?;
+
+        let name = stadium
+            .string("name")?
+            .ok_or_else(|| stadium.error("name", "given"))?
+            .to_string();
+        let width = 2. * stadium.required_number("width")?;
+        let height = 2. * stadium.required_number("height")?;
+
+        if list(&stadium, "joints")?.next().is_some() {
+            return Err(StadiumError::Unsupported(
+                "joints aren't supported".to_string(),
+            ));
+        }
+
+        let background = match stadium.get("bg") {
+            None => rgb(GRASS_COLOR),
+            Some(value) => {
+                let bg = Object::new("bg".to_string(), value, traits, &BACKGROUND_FIELDS)?;
+                background(&bg)?
+            }
+        };
+
+        let vertexes = list(&stadium, "vertexes")?
+            .map(|(path, value)| {
+                let vertex = Object::new(path, value, traits, &VERTEX_FIELDS)?;
+                Ok(Vec2::new(
+                    vertex.required_number("x")?,
+                    -vertex.required_number("y")?,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let walls = list(&stadium, "segments")?
+            .map(|(path, value)| {
+                let segment = Object::new(path, value, traits, &SEGMENT_FIELDS)?;
+                let vertex = |key| {
+                    vertexes
+                        .get(segment.index(key)?)
+                        .copied()
+                        .ok_or_else(|| segment.error(key, "an index of a vertex"))
+                };
+                segment.require_default_number("bias", 0.)?;
+
+                // `curveF` is the cotangent of half the curve, it takes precedence.
+                let curve = match segment.get("curveF") {
+                    Some(_) => 2. * (1. / segment.number("curveF", 0.)?).atan().to_degrees(),
+                    None => segment.number("curve", 0.)?,
+                };
+                Ok(Segment {
+                    a: vertex("v0")?,
+                    b: vertex("v1")?,
+                    // Mirroring the axes swaps the side the segment bulges to.
+                    curve: -curve,
+                    restitution: segment.number("bCoef", 1.)?,
+                    group: segment.groups("cGroup", CollisionGroups::WALL)?,
+                    mask: segment.groups("cMask", CollisionGroups::ALL)?,
+                    visible: segment.bool("vis", true)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let goals = list(&stadium, "goals")?
+            .map(|(path, value)| {
+                let goal = Object::new(path, value, traits, &GOAL_FIELDS)?;
+                let (p0, p1) = (goal.required_point("p0")?, goal.required_point("p1")?);
+                if p0.x != p1.x {
+                    return Err(StadiumError::Unsupported(format!(
+                        "{}: only vertical goal lines are supported",
+                        goal.path
+                    )));
+                }
+                let team = match goal.string("team")? {
+                    Some("red") => Team::Red,
+                    Some("blue") => Team::Blue,
+                    _ => return Err(goal.error("team", "\"red\" or \"blue\"")),
+                };
+                Ok(Goal {
+                    team,
+                    x: p0.x,
+                    top: p0.y.max(p1.y),
+                    bottom: p0.y.min(p1.y),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let planes = list(&stadium, "planes")?
+            .map(|(path, value)| {
+                let plane = Object::new(path, value, traits, &PLANE_FIELDS)?;
+                let normal = plane.required_point("normal")?.normalize_or_zero();
+                if normal == Vec2::ZERO {
+                    return Err(plane.error("normal", "a non-zero vector"));
+                }
+                Ok(Plane {
+                    normal,
+                    distance: plane.required_number("dist")?,
+                    restitution: plane.number("bCoef", 1.)?,
+                    group: plane.groups("cGroup", CollisionGroups::WALL)?,
+                    mask: plane.groups("cMask", CollisionGroups::ALL)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // The first disc may describe the ball, the others have to be static.
+        let ball_is_first_disc = stadium.get("ballPhysics") == Some(&Value::from("disc0"));
+        let mut ball = DEFAULT_BALL;
+        let mut posts = Vec::new();
+        for (i, (path, value)) in list(&stadium, "discs")?.enumerate() {
+            let disc = Object::new(path, value, traits, &DISC_FIELDS)?;
+            disc.require_zero_point("speed")?;
+            disc.require_zero_point("gravity")?;
+
+            if ball_is_first_disc && i == 0 {
+                ball = ball_properties(&disc)?;
+                continue;
+            }
+            let properties = disc.disc_properties(DEFAULT_BALL)?;
+            if properties.inv_mass != 0. {
+                return Err(StadiumError::Unsupported(format!(
+                    "{}: only static discs (`invMass` 0) are supported",
+                    disc.path
+                )));
+            }
+            posts.push(Post {
+                position: disc.required_point("pos")?,
+                radius: properties.radius,
+                restitution: properties.restitution,
+                group: disc.groups("cGroup", CollisionGroups::ALL)?,
+                mask: disc.groups("cMask", CollisionGroups::ALL)?,
+            });
+        }
+        if let Some(value) = stadium.get("ballPhysics").filter(|_| !ball_is_first_disc) {
+            let disc = Object::new("ballPhysics".to_string(), value, traits, &DISC_FIELDS)?;
+            ball = ball_properties(&disc)?;
+        }
+
+        let mut physics = PhysicsConstants {
+            max_speed: MAX_SPEED,
+            acceleration: DEFAULT_ACCELERATION,
+            kicking_acceleration: DEFAULT_KICKING_ACCELERATION,
+            kick_strength: DEFAULT_KICK_STRENGTH,
+            kick_reach: DEFAULT_KICK_REACH,
+            player: DEFAULT_PLAYER,
+            ball,
+            ..PhysicsConstants::default()
+        };
+        if let Some(value) = stadium.get("playerPhysics") {
+            let player = Object::new("playerPhysics".to_string(), value, traits, &PLAYER_FIELDS)?;
+            if player.get("cGroup").is_some() {
+                return Err(player.unsupported("cGroup"));
+            }
+            player.require_default_number("kickingDamping", DEFAULT_KICKING_DAMPING)?;
+            player.require_default_number("kickback", 0.)?;
+            player.require_zero_point("gravity")?;
+
+            physics.player = player.disc_properties(DEFAULT_PLAYER)?;
+            physics.acceleration = player.number("acceleration", DEFAULT_ACCELERATION)?;
+            physics.kicking_acceleration =
+                player.number("kickingAcceleration", DEFAULT_KICKING_ACCELERATION)?;
+            physics.kick_strength = player.number("kickStrength", DEFAULT_KICK_STRENGTH)?;
+        }
+
+        // Without the spawn points, HaxBall lines the players up at the spawn distance.
+        let mut red_spawn_points = spawn_points(&stadium, "redSpawnPoints")?;
+        if red_spawn_points.is_empty() {
+            let distance = stadium.number("spawnDistance", DEFAULT_SPAWN_DISTANCE)?;
+            red_spawn_points = [0., 1., -1., 2., -2.]
+                .iter()
+                .map(|row| Vec2::new(-distance, row * SPAWN_SPACING))
+                .collect();
+        }
+        let blue_spawn_points = spawn_points(&stadium, "blueSpawnPoints")?;
+
+        let stadium = Stadium {
+            name,
+            width,
+            height,
+            goals,
+            posts,
+            walls,
+            planes,
+            red_spawn_points,
+            blue_spawn_points,
+            background,
+            physics,
+        };
+        stadium.validate()?;
+        Ok(stadium)
+    }
+}
+
+// Physics of the ball, which always collides like a HaxBall ball.
+fn ball_properties(disc: &Object) -> Result<DiscProperties> {
+    let default_group = CollisionGroups::BALL | CollisionGroups::KICK | CollisionGroups::SCORE;
+    if !default_group.contains(disc.groups("cGroup", default_group)?) {
+        return Err(disc.unsupported("cGroup"));
+    }
+    if disc.groups("cMask", CollisionGroups::ALL)? != CollisionGroups::ALL {
+        return Err(disc.unsupported("cMask"));
+    }
+    disc.require_zero_point("pos")?;
+    disc.disc_properties(DEFAULT_BALL)
+}
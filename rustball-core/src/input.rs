@@ -0,0 +1,32 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+// Actions a player wants to perform during a single tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerInput {
+    // Direction the player runs in, at most one unit long.
+    // Shorter vectors, e.g. from a slightly tilted stick, accelerate the player less.
+    pub direction: Vec2,
+    pub kick: bool,
+}
+
+impl PlayerInput {
+    // Input given by held direction keys, diagonal directions are normalized as well.
+    // Opposite keys cancel each other out.
+    pub fn from_keys(up: bool, down: bool, left: bool, right: bool, kick: bool) -> PlayerInput {
+        let axis = |positive: bool, negative: bool| positive as i32 as f32 - negative as i32 as f32;
+        let direction = Vec2::new(axis(right, left), axis(up, down)).normalize_or_zero();
+        PlayerInput { direction, kick }
+    }
+
+    // Input which can't break the simulation, for inputs received from other machines.
+    // Directions which aren't numbers are dropped and longer ones shortened to a unit.
+    pub fn sanitized(self) -> PlayerInput {
+        let direction = if self.direction.is_finite() {
+            self.direction.clamp_length_max(1.)
+        } else {
+            Vec2::ZERO
+        };
+        PlayerInput { direction, ..self }
+    }
+}
@@ -0,0 +1,83 @@
+// Headless simulation of a RustBall match.
+// It owns positions, velocities and radii of all discs and knows nothing about rendering,
+// so it can be tested without a window and reused by a server.
+
+mod bot;
+mod client;
+mod collision;
+mod disc;
+mod hbs;
+mod input;
+mod lobby;
+mod netsim;
+pub mod physics;
+pub mod protocol;
+mod replay;
+mod rng;
+mod rollback;
+mod rooms;
+mod rules;
+mod stadium;
+mod state;
+
+pub use bot::{Bot, BotRole, Difficulty};
+pub use client::{ClientMatch, INTERPOLATION_TICKS};
+pub use collision::{can_collide, CollisionGroups};
+pub use disc::{Disc, PLAYER_MASK};
+pub use glam::Vec2;
+pub use input::PlayerInput;
+pub use lobby::{Lobby, LobbyMember};
+pub use netsim::{LinkSimulator, NetworkConditions};
+pub use replay::{Replay, ReplayError, ReplayPlayer};
+pub use rollback::{RollbackSession, MAX_ROLLBACK_TICKS, PEER_TEAMS};
+pub use rooms::{RoomFilter, RoomSort};
+pub use rules::MatchRules;
+pub use stadium::{Background, DiscProperties, Goal, PhysicsConstants, Plane, Post};
+pub use stadium::{Segment, SegmentArc};
+pub use stadium::{Stadium, StadiumError};
+pub use state::{MatchEvent, MatchState, Player, PlayerStats, Score, Team, BALL};
+
+// Constants
+// Physics and geometry of the default stadium, others may use different ones.
+// Top speed of the players, who'd otherwise reach a bit more with their acceleration and damping.
+pub const MAX_SPEED: f32 = 3.0;
+pub const ACCELERATION: f32 = 0.1;
+// Players holding the kick button run slower.
+pub const KICKING_ACCELERATION: f32 = 0.07;
+pub const KICK_STRENGTH: f32 = 5.0;
+// How far from the edge of a player the ball can still be kicked.
+pub const KICK_REACH: f32 = 5.0;
+// Ticks after a kick before the player can kick again.
+pub const KICK_COOLDOWN: u32 = 15;
+pub const PLAYER_RADIUS: f32 = 25.0;
+pub const BALL_RADIUS: f32 = 10.0;
+// Players are twice as heavy as the ball.
+pub const PLAYER_INV_MASS: f32 = 0.5;
+pub const BALL_INV_MASS: f32 = 1.0;
+pub const PLAYER_RESTITUTION: f32 = 0.5;
+pub const BALL_RESTITUTION: f32 = 1.0;
+// Part of the velocity kept after every tick.
+pub const PLAYER_DAMPING: f32 = 0.97;
+pub const BALL_DAMPING: f32 = 0.99;
+pub const CORNER_RADIUS: f32 = 10.0;
+// Kickoff positions of the red players by their slot, blue players stand mirrored.
+// Further players are placed behind them.
+pub const SPAWN_POSITIONS: [(f32, f32); 6] = [
+    (-200.0, 0.0),
+    (-300.0, 120.0),
+    (-300.0, -120.0),
+    (-420.0, 0.0),
+    (-150.0, 220.0),
+    (-150.0, -220.0),
+];
+pub const CENTER_CIRCLE_RADIUS: f32 = 180.0;
+pub const PITCH_WIDTH: f32 = 1024.0;
+pub const PITCH_HEIGHT: f32 = 768.0;
+pub const CORNER_UP_HEIGHT: f32 = 100.0;
+pub const CORNER_DOWN_HEIGHT: f32 = -100.0;
+// How far the goal nets are behind the goal lines.
+pub const GOAL_DEPTH: f32 = 40.0;
+pub const GOAL_NET_RESTITUTION: f32 = 0.1;
+// Ticks after a goal before everybody is moved back to the kickoff positions.
+pub const GOAL_RESET_DELAY: u64 = 2 * TICKS_PER_SECOND as u64;
+pub const TICKS_PER_SECOND: u32 = 60;
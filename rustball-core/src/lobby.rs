@@ -0,0 +1,104 @@
+use crate::{MatchRules, MatchState, Stadium, Team};
+use serde::{Deserialize, Serialize};
+
+// Somebody in the lobby, playing for a team or watching.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LobbyMember {
+    // Unique within the lobby, also after the member left.
+    pub id: u32,
+    pub name: String,
+    // `None` for a spectator.
+    pub team: Option<Team>,
+}
+
+// Room gathering everybody before a match.
+// Its admin moves the members between the teams and the spectators,
+// picks the stadium and the rules and starts the match.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lobby {
+    // In the order they joined, which is also the order of their players in the match.
+    pub members: Vec<LobbyMember>,
+    // Member allowed to change the lobby, `None` while it's empty.
+    pub admin: Option<u32>,
+    pub stadium: Stadium,
+    pub rules: MatchRules,
+}
+
+impl Lobby {
+    pub fn new(stadium: Stadium, rules: MatchRules) -> Self {
+        Lobby {
+            members: Vec::new(),
+            admin: None,
+            stadium,
+            rules,
+        }
+    }
+
+    // Adds a member, the first one becomes the admin.
+    pub fn join(&mut self, id: u32, name: impl Into<String>, team: Option<Team>) {
+        self.members.push(LobbyMember {
+            id,
+            name: name.into(),
+            team,
+        });
+        self.admin.get_or_insert(id);
+    }
+
+    // Removes a member, the admin passes to the one who joined next.
+    pub fn leave(&mut self, id: u32) {
+        self.members.retain(|member| member.id != id);
+        if self.admin == Some(id) {
+            self.admin = self.members.first().map(|member| member.id);
+        }
+    }
+
+    pub fn member(&self, id: u32) -> Option<&LobbyMember> {
+        self.members.iter().find(|member| member.id == id)
+    }
+
+    pub fn is_admin(&self, id: u32) -> bool {
+        self.admin == Some(id)
+    }
+
+    // Moves the member to the team, or to the spectators with `None`.
+    // Returns whether there's such a member.
+    pub fn move_member(&mut self, id: u32, team: Option<Team>) -> bool {
+        match self.members.iter_mut().find(|member| member.id == id) {
+            Some(member) => {
+                member.team = team;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Members in the team, or the spectators with `None`.
+    pub fn team_members(&self, team: Option<Team>) -> impl Iterator<Item = &LobbyMember> {
+        self.members
+            .iter()
+            .filter(move |member| member.team == team)
+    }
+
+    // Members playing the match, by the index of their player.
+    pub fn players(&self) -> Vec<&LobbyMember> {
+        self.members
+            .iter()
+            .filter(|member| member.team.is_some())
+            .collect()
+    }
+
+    // A match needs somebody to play it.
+    pub fn can_start(&self) -> bool {
+        self.members.iter().any(|member| member.team.is_some())
+    }
+
+    // The match of the players in their teams, spectators get no disc.
+    pub fn new_match(&self) -> MatchState {
+        let teams: Vec<Team> = self
+            .players()
+            .iter()
+            .filter_map(|member| member.team)
+            .collect();
+        MatchState::new(self.stadium.clone(), &teams, self.rules)
+    }
+}
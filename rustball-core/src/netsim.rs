@@ -0,0 +1,56 @@
+use crate::rng::Rng;
+use std::time::{Duration, Instant};
+
+// Delay and loss of the datagrams going one way, simulated to test online play on localhost.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NetworkConditions {
+    pub latency: Duration,
+    // Largest random change of the latency, which may also reorder the datagrams.
+    pub jitter: Duration,
+    // Part of the datagrams which never arrive, from 0 to 1.
+    pub packet_loss: f32,
+}
+
+// Holds datagrams back until the simulated network delivers them.
+// Datagrams may carry more than their bytes, e.g. who sent them.
+#[derive(Clone, Debug)]
+pub struct LinkSimulator<T = Vec<u8>> {
+    conditions: NetworkConditions,
+    // Datagrams on their way with the time they arrive.
+    queue: Vec<(Instant, T)>,
+    rng: Rng,
+}
+
+impl<T> LinkSimulator<T> {
+    pub fn new(conditions: NetworkConditions, seed: u64) -> Self {
+        LinkSimulator {
+            conditions,
+            queue: Vec::new(),
+            rng: Rng::new(seed),
+        }
+    }
+
+    // Sends the datagram at the given time, unless it gets lost.
+    pub fn push(&mut self, now: Instant, datagram: T) {
+        if self.rng.next_f32() < self.conditions.packet_loss {
+            return;
+        }
+        let jitter =
+            self.conditions.jitter.as_nanos() as f64 * (2. * self.rng.next_f32() as f64 - 1.);
+        let latency = self.conditions.latency.as_nanos() as i128 + jitter as i128;
+        let arrival = now + Duration::from_nanos(latency.max(0) as u64);
+        self.queue.push((arrival, datagram));
+    }
+
+    // The datagram which arrived first by the given time, if any.
+    // Datagrams arriving at the same time keep their order.
+    pub fn pop(&mut self, now: Instant) -> Option<T> {
+        let (index, _) = self
+            .queue
+            .iter()
+            .enumerate()
+            .filter(|(_, (arrival, _))| *arrival <= now)
+            .min_by_key(|(_, (arrival, _))| *arrival)?;
+        Some(self.queue.remove(index).1)
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{Disc, PhysicsConstants, Plane, PlayerInput, Post, Segment};
+use glam::Vec2;
+
+// Discs this close to each other or to a wall are still in contact,
+// so the ones stopped at the time of impact bounce off.
+const CONTACT_DISTANCE: f32 = 1e-3;
+
+// Changes velocity of a player according to its input.
+// Both the acceleration and the speed are limited as vectors, so running diagonally isn't any faster.
+pub fn accelerate(velocity: &mut Vec2, input: &PlayerInput, constants: &PhysicsConstants) {
+    // Longer vectors would make the player accelerate faster than the others.
+    let direction = input.direction.clamp_length_max(1.);
+
+    // Holding the kick button slows the player down, as in HaxBall.
+    let acceleration = if input.kick {
+        constants.kicking_acceleration
+    } else {
+        constants.acceleration
+    };
+    *velocity += direction * acceleration;
+    *velocity = velocity.clamp_length_max(constants.max_speed);
+}
+
+// Shoots the ball away from the player.
+pub fn kick(player: &Disc, ball: &mut Disc, strength: f32) {
+    let diff = player.position - ball.position;
+    let angle = diff.y.atan2(diff.x);
+    ball.velocity.y += -strength * angle.sin();
+    ball.velocity.x += -strength * angle.cos();
+}
+
+// Calculates new velocity vectors after collision.
+// Using some math formulas from the internet.
+// Inspired with: https://stackoverflow.com/questions/345838/ball-to-ball-collision-detection-and-handling
+pub fn collide(disc1: &mut Disc, disc2: &mut Disc) {
+    let delta = disc1.position - disc2.position;
+    let d = delta.length();
+    if d == 0. {
+        return;
+    }
+    let normal = delta / d;
+    // Discs which only touch each other aren't moved.
+    let mtd = normal * (disc1.radius + disc2.radius - d).max(0.);
+
+    let im1 = disc1.inv_mass;
+    let im2 = disc2.inv_mass;
+    if im1 + im2 == 0. {
+        return;
+    }
+
+    disc1.position += mtd * (im1 / (im1 + im2));
+    disc2.position -= mtd * (im2 / (im1 + im2));
+
+    let v = disc1.velocity - disc2.velocity;
+    let vn = v.dot(normal);
+
+    if vn > 0.0 {
+        return;
+    }
+
+    let restitution = disc1.restitution * disc2.restitution;
+    let i = (-(1. + restitution) * vn) / (im1 + im2);
+    let impulse = normal * i;
+
+    disc1.velocity += impulse * im1;
+    disc2.velocity -= impulse * im2;
+}
+
+// Handles collision between a disc and a goal post.
+pub fn post_collision(disc: &mut Disc, post: &Post) {
+    let delta = disc.position - post.position;
+    let distance = delta.length();
+    let min_distance = disc.radius + post.radius;
+    if distance >= min_distance + CONTACT_DISTANCE || distance == 0. {
+        return;
+    }
+
+    let normal = delta / distance;
+    disc.position += normal * (min_distance - distance).max(0.);
+    bounce(disc, normal, post.restitution);
+}
+
+// Handles collision between a disc and a wall of the stadium.
+pub fn wall_collision(disc: &mut Disc, wall: &Segment) {
+    let delta = disc.position - wall.closest_point(disc.position);
+    let distance = delta.length();
+    if distance >= disc.radius + CONTACT_DISTANCE || distance == 0. {
+        return;
+    }
+
+    let normal = delta / distance;
+    disc.position += normal * (disc.radius - distance).max(0.);
+    bounce(disc, normal, wall.restitution);
+}
+
+// Handles collision between a disc and an infinite wall of the stadium.
+pub fn plane_collision(disc: &mut Disc, plane: &Plane) {
+    let depth = disc.radius - (plane.normal.dot(disc.position) - plane.distance);
+    if depth <= -CONTACT_DISTANCE {
+        return;
+    }
+
+    disc.position += plane.normal * depth.max(0.);
+    bounce(disc, plane.normal, plane.restitution);
+}
+
+// Time within the next `time` ticks at which the discs start touching, if they run into each other.
+// Discs already overlapping are left to `collide`.
+pub fn sweep_discs(disc1: &Disc, disc2: &Disc, time: f32) -> Option<f32> {
+    let motion = (disc1.velocity - disc2.velocity) * time;
+    let radius = disc1.radius + disc2.radius;
+    sweep_into_circle(disc1.position - disc2.position, motion, radius).map(|t| t * time)
+}
+
+// Time within the next `time` ticks at which the disc runs into the post.
+pub fn sweep_post(disc: &Disc, post: &Post, time: f32) -> Option<f32> {
+    let motion = disc.velocity * time;
+    let radius = disc.radius + post.radius;
+    sweep_into_circle(disc.position - post.position, motion, radius).map(|t| t * time)
+}
+
+// Time within the next `time` ticks at which the disc runs into the wall, from either side.
+pub fn sweep_wall(disc: &Disc, wall: &Segment, time: f32) -> Option<f32> {
+    let motion = disc.velocity * time;
+    let body = match wall.arc() {
+        Some(arc) => {
+            let position = disc.position - arc.center;
+            let outside = sweep_into_circle(position, motion, arc.radius + disc.radius);
+            let inside = if arc.radius > disc.radius {
+                sweep_out_of_circle(position, motion, arc.radius - disc.radius)
+            } else {
+                None
+            };
+            [outside, inside]
+                .into_iter()
+                .flatten()
+                .filter(|&t| arc.covers(disc.position + motion * t))
+                .reduce(f32::min)
+        }
+        None => sweep_line(disc, wall, motion),
+    };
+    let ends = [wall.a, wall.b]
+        .into_iter()
+        .filter_map(|end| sweep_into_circle(disc.position - end, motion, disc.radius));
+
+    body.into_iter()
+        .chain(ends)
+        .reduce(f32::min)
+        .map(|t| t * time)
+}
+
+// Time within the next `time` ticks at which the disc runs into the plane.
+pub fn sweep_plane(disc: &Disc, plane: &Plane, time: f32) -> Option<f32> {
+    let motion = disc.velocity * time;
+    let distance = plane.normal.dot(disc.position) - plane.distance - disc.radius;
+    let approach = -plane.normal.dot(motion);
+    if distance < 0. || approach <= 0. {
+        return None;
+    }
+    let t = distance / approach;
+    (t <= 1.).then_some(t * time)
+}
+
+// Part of the motion after which the disc touches the straight wall between its ends.
+fn sweep_line(disc: &Disc, wall: &Segment, motion: Vec2) -> Option<f32> {
+    let along = wall.b - wall.a;
+    let mut normal = along.perp().normalize_or_zero();
+    let mut distance = normal.dot(disc.position - wall.a);
+    // Turn the normal towards the disc.
+    if distance < 0. {
+        normal = -normal;
+        distance = -distance;
+    }
+    let approach = -normal.dot(motion);
+    if distance < disc.radius || approach <= 0. {
+        return None;
+    }
+
+    let t = (distance - disc.radius) / approach;
+    let along_t = (disc.position + motion * t - wall.a).dot(along) / along.length_squared();
+    (t <= 1. && (0. ..=1.).contains(&along_t)).then_some(t)
+}
+
+// Part of the motion after which a point moving from the given position (relative to the center)
+// enters the circle of the given radius. Points already in the circle are ignored.
+fn sweep_into_circle(position: Vec2, motion: Vec2, radius: f32) -> Option<f32> {
+    let a = motion.length_squared();
+    let b = 2. * position.dot(motion);
+    let c = position.length_squared() - radius * radius;
+    if c <= 0. || b >= 0. {
+        return None;
+    }
+    let discriminant = b * b - 4. * a * c;
+    if discriminant < 0. {
+        return None;
+    }
+    let t = (-b - discriminant.sqrt()) / (2. * a);
+    (t <= 1.).then_some(t)
+}
+
+// Part of the motion after which a point moving from the given position (relative to the center)
+// leaves the circle of the given radius, it may enter the circle first.
+fn sweep_out_of_circle(position: Vec2, motion: Vec2, radius: f32) -> Option<f32> {
+    let a = motion.length_squared();
+    let b = 2. * position.dot(motion);
+    let c = position.length_squared() - radius * radius;
+    let discriminant = b * b - 4. * a * c;
+    if a == 0. || discriminant < 0. {
+        return None;
+    }
+    let t = (-b + discriminant.sqrt()) / (2. * a);
+    (0. ..=1.).contains(&t).then_some(t)
+}
+
+// Reflects the velocity of a disc moving against a static wall with the given normal.
+fn bounce(disc: &mut Disc, normal: Vec2, restitution: f32) {
+    let inward = disc.velocity.dot(normal);
+    if inward < 0. {
+        disc.velocity -= normal * ((1. + disc.restitution * restitution) * inward);
+    }
+}
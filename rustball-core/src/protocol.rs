@@ -0,0 +1,240 @@
+// Messages exchanged by the server and the clients of an online match, one per UDP datagram.
+use crate::{Lobby, MatchRules, MatchState, Player, PlayerInput, Score, Stadium, Team};
+use bincode::Options;
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+pub const DEFAULT_PORT: u16 = 5195;
+// Port a peer hosting a peer-to-peer match listens on.
+pub const DEFAULT_PEER_PORT: u16 = 5196;
+// Port of the master server listing the rooms.
+pub const DEFAULT_MASTER_PORT: u16 = 5197;
+// Clients and servers of different versions can't play together.
+pub const PROTOCOL_VERSION: u32 = 4;
+// Size of the largest datagram, big enough for the lobby with a stadium.
+pub const MAX_DATAGRAM_SIZE: usize = 65_507;
+// Rooms sent in a room list at most, so that it fits a datagram.
+pub const MAX_LISTED_ROOMS: usize = 200;
+// Bytes of the names in a room list at most, the list has to fit a datagram whatever the characters.
+pub const MAX_ROOM_NAME: usize = 40;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ClientMessage {
+    // Asks to enter the lobby, sent again until the server welcomes the client.
+    // Rooms with a password let in only the clients who know it.
+    Join {
+        version: u32,
+        password: Option<String>,
+    },
+    // Input of the client's player, numbered so late datagrams can be dropped.
+    Input {
+        sequence: u64,
+        input: PlayerInput,
+    },
+    Leave,
+    // Changes of the lobby only its admin is allowed to make.
+    // Moves the member to the team, or to the spectators with `None`.
+    MoveMember {
+        member: u32,
+        team: Option<Team>,
+    },
+    ChangeMatch {
+        stadium: Box<Stadium>,
+        rules: MatchRules,
+    },
+    StartMatch,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ServerMessage {
+    // The client is the given member of the lobby.
+    Welcome {
+        member: u32,
+        lobby: Lobby,
+    },
+    // The client can't join, e.g. because the lobby is full.
+    Rejected(String),
+    // The lobby changed or the match ended, also sent every now and then in case it was lost.
+    Lobby(Lobby),
+    // The match started, the client controls the given player or watches with `None`.
+    Start {
+        player: Option<usize>,
+        stadium: Stadium,
+        rules: MatchRules,
+        teams: Vec<Team>,
+    },
+    // State of the match after a tick, with the last input of the client applied before it.
+    // Snapshots are numbered by the ticks of the server, which go on when the next match starts.
+    Snapshot {
+        sequence: u64,
+        snapshot: Snapshot,
+        last_input: Option<u64>,
+    },
+}
+
+// Messages exchanged by the peers of a peer-to-peer match, see `RollbackSession`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PeerMessage {
+    // Asks the hosting peer for a match, sent again until it starts.
+    Join {
+        version: u32,
+    },
+    // Answer to every join, the joining peer plays blue on the stadium.
+    // The stadium is boxed, it's far bigger than the inputs of every tick.
+    Start {
+        stadium: Box<Stadium>,
+        rules: MatchRules,
+    },
+    Rejected(String),
+    // Inputs of the sender from the `start` tick on, sent until the other peer acknowledges them,
+    // with the number of the other peer's inputs received and the checksum of a confirmed tick.
+    Inputs {
+        start: u64,
+        inputs: Vec<PlayerInput>,
+        acknowledged: u64,
+        checksum: Option<(u64, u64)>,
+    },
+    Leave,
+}
+
+// Messages of the game servers and the clients to the master server.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MasterMessage {
+    // Sent by a game server every now and then, the room is listed until they stop coming.
+    // The room is reached at the address the heartbeat came from.
+    Heartbeat { version: u32, room: RoomInfo },
+    // The game server is shutting down.
+    Unregister,
+    // Asks for the rooms of the same protocol version.
+    // Only a cookie the master server gave to the same address gets the rooms, anything else gets a cookie.
+    // Otherwise a forged address would get the whole list, many times bigger than the request.
+    ListRooms { version: u32, cookie: [u8; 8] },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MasterReply {
+    Rooms(Vec<RoomListing>),
+    Rejected(String),
+    // Cookie to ask for the rooms with, the reply is no bigger than the request.
+    Cookie([u8; 8]),
+}
+
+// What the room list shows about a room.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RoomInfo {
+    pub name: String,
+    pub players: u32,
+    pub max_players: u32,
+    pub password: bool,
+    // Name of the stadium in the lobby.
+    pub stadium: String,
+    pub location: Location,
+}
+
+// Where a room is, the list is sorted by the distance to it as the best guess of the ping.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    // Country code shown in the list, e.g. `PL`.
+    pub code: String,
+    pub latitude: f32,
+    pub longitude: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RoomListing {
+    pub address: SocketAddr,
+    pub room: RoomInfo,
+}
+
+// Position and velocity of a disc, the rest of it doesn't change during a match.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DiscMotion {
+    pub position: Vec2,
+    pub velocity: Vec2,
+}
+
+// Everything about a match that changes from tick to tick,
+// the stadium and the rules are sent only once, when the client joins.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub tick: u64,
+    pub discs: Vec<DiscMotion>,
+    pub players: Vec<Player>,
+    pub score: Score,
+    pub overtime: bool,
+    pub winner: Option<Team>,
+    pub kickoff: Option<Team>,
+    pub last_touch: Option<usize>,
+    pub restart: Option<(u64, Team)>,
+}
+
+impl MatchState {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            tick: self.tick,
+            discs: self
+                .discs
+                .iter()
+                .map(|disc| DiscMotion {
+                    position: disc.position,
+                    velocity: disc.velocity,
+                })
+                .collect(),
+            players: self.players.clone(),
+            score: self.score,
+            overtime: self.overtime,
+            winner: self.winner,
+            kickoff: self.kickoff,
+            last_touch: self.last_touch,
+            restart: self.restart,
+        }
+    }
+
+    // Brings the match to the state of the snapshot taken on the same stadium with the same players.
+    // A snapshot of any other match is left out and gives `false`, its indices would be out of range.
+    pub fn apply_snapshot(&mut self, snapshot: &Snapshot) -> bool {
+        let fits = snapshot.discs.len() == self.discs.len()
+            && snapshot.players.len() == self.players.len()
+            && snapshot
+                .players
+                .iter()
+                .zip(&self.players)
+                .all(|(player, own)| player.disc == own.disc)
+            && snapshot
+                .last_touch
+                .is_none_or(|player| player < self.players.len());
+        if !fits {
+            return false;
+        }
+        for (disc, motion) in self.discs.iter_mut().zip(snapshot.discs.iter()) {
+            disc.position = motion.position;
+            disc.velocity = motion.velocity;
+        }
+        self.players.clone_from(&snapshot.players);
+        self.tick = snapshot.tick;
+        self.score = snapshot.score;
+        self.overtime = snapshot.overtime;
+        self.winner = snapshot.winner;
+        self.kickoff = snapshot.kickoff;
+        self.last_touch = snapshot.last_touch;
+        self.restart = snapshot.restart;
+        true
+    }
+}
+
+// Message as the bytes of a datagram.
+pub fn encode<T: Serialize>(message: &T) -> Vec<u8> {
+    bincode::DefaultOptions::new()
+        .serialize(message)
+        .expect("Messages are always serializable")
+}
+
+// Message from the bytes of a datagram, `None` if they aren't a valid message.
+// The limit keeps forged lengths from allocating more than a datagram could hold.
+pub fn decode<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Option<T> {
+    bincode::DefaultOptions::new()
+        .with_limit(MAX_DATAGRAM_SIZE as u64)
+        .deserialize(bytes)
+        .ok()
+}
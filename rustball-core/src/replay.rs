@@ -0,0 +1,263 @@
+use crate::{MatchEvent, MatchRules, MatchState, PlayerInput, Stadium, Team};
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::{error, fmt};
+
+// First bytes of every replay file, followed by the version of the format.
+const MAGIC: &[u8; 4] = b"RBRP";
+const VERSION: u8 = 1;
+// Flags of the byte stored for every player in every tick.
+const KICK_FLAG: u8 = 1;
+// The direction differs from the previous tick and follows the flags as two floats.
+const DIRECTION_FLAG: u8 = 2;
+// Ticks between the states a `ReplayPlayer` keeps to seek faster.
+const CHECKPOINT_TICKS: usize = 10 * crate::TICKS_PER_SECOND as usize;
+
+// Recorded match: how it started and the inputs of the players in every tick.
+// The simulation is deterministic, so a new match stepped with the same inputs plays the same.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub stadium: Stadium,
+    pub rules: MatchRules,
+    // Teams of the players, in the order of players in the match.
+    pub teams: Vec<Team>,
+    // Inputs of all the players, by the tick.
+    pub inputs: Vec<Vec<PlayerInput>>,
+}
+
+// Everything needed to start the match again, stored as RON in front of the inputs.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    stadium: Stadium,
+    rules: MatchRules,
+    teams: Vec<Team>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    // The file isn't a replay, or one of a newer version.
+    Format(String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "can't read replay: {}", err),
+            ReplayError::Format(message) => write!(f, "invalid replay: {}", message),
+        }
+    }
+}
+
+impl error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+impl Replay {
+    // Starts recording the match, which must not have been stepped yet.
+    pub fn new(state: &MatchState) -> Self {
+        Replay {
+            stadium: state.stadium.clone(),
+            rules: state.rules,
+            teams: state.players.iter().map(|player| player.team).collect(),
+            inputs: Vec::new(),
+        }
+    }
+
+    // Adds inputs of the next tick, the same ones the match is stepped with.
+    pub fn record(&mut self, inputs: &[PlayerInput]) {
+        let mut inputs = inputs.to_vec();
+        inputs.resize(self.teams.len(), PlayerInput::default());
+        self.inputs.push(inputs);
+    }
+
+    // Number of recorded ticks.
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    // The match as it was before the first recorded tick.
+    pub fn initial_state(&self) -> MatchState {
+        MatchState::new(self.stadium.clone(), &self.teams, self.rules)
+    }
+
+    // Writes the replay in the compact binary format of `.rbreplay` files.
+    // Every player takes a byte per tick, directions are stored only when they change.
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let header = Header {
+            stadium: self.stadium.clone(),
+            rules: self.rules,
+            teams: self.teams.clone(),
+        };
+        let header = ron::ser::to_string(&header)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&(header.len() as u32).to_le_bytes())?;
+        writer.write_all(header.as_bytes())?;
+        writer.write_all(&(self.inputs.len() as u32).to_le_bytes())?;
+
+        let mut directions = vec![Vec2::ZERO; self.teams.len()];
+        for inputs in self.inputs.iter() {
+            for (input, direction) in inputs.iter().zip(directions.iter_mut()) {
+                let changed = input.direction != *direction;
+                let flags = if input.kick { KICK_FLAG } else { 0 }
+                    | if changed { DIRECTION_FLAG } else { 0 };
+                writer.write_all(&[flags])?;
+                if changed {
+                    writer.write_all(&input.direction.x.to_le_bytes())?;
+                    writer.write_all(&input.direction.y.to_le_bytes())?;
+                    *direction = input.direction;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Reads a replay written by `write_to`.
+    pub fn read_from(reader: &mut impl Read) -> Result<Replay, ReplayError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(ReplayError::Format("not a RustBall replay".to_string()));
+        }
+        let version = read_u8(reader)?;
+        if version != VERSION {
+            return Err(ReplayError::Format(format!("unknown version {}", version)));
+        }
+
+        let mut header = vec![0; read_u32(reader)? as usize];
+        reader.read_exact(&mut header)?;
+        let header: Header = std::str::from_utf8(&header)
+            .map_err(|err| err.to_string())
+            .and_then(|source| ron::de::from_str(source).map_err(|err| err.to_string()))
+            .map_err(ReplayError::Format)?;
+        header
+            .stadium
+            .validate()
+            .map_err(|err| ReplayError::Format(err.to_string()))?;
+
+        let ticks = read_u32(reader)? as usize;
+        let mut directions = vec![Vec2::ZERO; header.teams.len()];
+        let mut inputs = Vec::new();
+        for _ in 0..ticks {
+            let mut tick_inputs = Vec::with_capacity(directions.len());
+            for direction in directions.iter_mut() {
+                let flags = read_u8(reader)?;
+                if flags & DIRECTION_FLAG != 0 {
+                    *direction = Vec2::new(read_f32(reader)?, read_f32(reader)?);
+                }
+                tick_inputs.push(PlayerInput {
+                    direction: *direction,
+                    kick: flags & KICK_FLAG != 0,
+                });
+            }
+            inputs.push(tick_inputs);
+        }
+
+        Ok(Replay {
+            stadium: header.stadium,
+            rules: header.rules,
+            teams: header.teams,
+            inputs,
+        })
+    }
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f32(reader: &mut impl Read) -> io::Result<f32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+// Plays a replay tick by tick, able to jump to any of its ticks.
+#[derive(Clone, Debug)]
+pub struct ReplayPlayer {
+    replay: Replay,
+    state: MatchState,
+    // Number of ticks played so far.
+    tick: usize,
+    // States at every `CHECKPOINT_TICKS`-th tick played so far,
+    // so seeking back doesn't simulate the match from the start.
+    checkpoints: Vec<MatchState>,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        let state = replay.initial_state();
+        ReplayPlayer {
+            checkpoints: vec![state.clone()],
+            replay,
+            state,
+            tick: 0,
+        }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    // The match after the played ticks.
+    pub fn state(&self) -> &MatchState {
+        &self.state
+    }
+
+    pub fn tick(&self) -> usize {
+        self.tick
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.tick >= self.replay.len()
+    }
+
+    // Plays the next tick, there's nothing to play at the end of the replay.
+    pub fn step(&mut self) -> Vec<MatchEvent> {
+        if self.is_finished() {
+            return Vec::new();
+        }
+        let events = self.state.step(&self.replay.inputs[self.tick]);
+        self.tick += 1;
+        if self.tick.is_multiple_of(CHECKPOINT_TICKS)
+            && self.checkpoints.len() == self.tick / CHECKPOINT_TICKS
+        {
+            self.checkpoints.push(self.state.clone());
+        }
+        events
+    }
+
+    // Moves to the state after the given number of ticks, or to the end of the replay.
+    pub fn seek(&mut self, tick: usize) {
+        let tick = tick.min(self.replay.len());
+        let checkpoint = (tick / CHECKPOINT_TICKS).min(self.checkpoints.len() - 1);
+        let checkpoint_tick = checkpoint * CHECKPOINT_TICKS;
+        if tick < self.tick || checkpoint_tick > self.tick {
+            self.state = self.checkpoints[checkpoint].clone();
+            self.tick = checkpoint_tick;
+        }
+        while self.tick < tick {
+            self.step();
+        }
+    }
+}
@@ -0,0 +1,19 @@
+// Xorshift64, small and good enough to spread the kicks of the bots and the simulated datagrams.
+// It's deterministic, the same seed gives the same numbers on every machine.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Xorshift would give only zeros from zero.
+        Rng(seed | 1)
+    }
+
+    // Number from 0 to 1.
+    pub fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
@@ -0,0 +1,259 @@
+use crate::protocol::PeerMessage;
+use crate::{MatchEvent, MatchRules, MatchState, PlayerInput, Stadium, Team, TICKS_PER_SECOND};
+use std::collections::VecDeque;
+
+// How far the predicted match may get ahead of the inputs of the other peer.
+// Further ticks wait for them, so a rollback never has to simulate more than this.
+pub const MAX_ROLLBACK_TICKS: u64 = 8;
+// Checksums of the confirmed ticks kept to compare with the ones of the other peer.
+const CHECKSUM_HISTORY: usize = 2 * TICKS_PER_SECOND as usize;
+// Inputs sent in a single message at most, the rest follows in the next ones.
+const MAX_INPUTS_PER_MESSAGE: usize = 64;
+
+// Teams of the peers, the hosting peer plays red.
+pub const PEER_TEAMS: [Team; 2] = [Team::Red, Team::Blue];
+
+// Peer-to-peer match of two players, both simulating it and sending only their inputs.
+// Missing inputs of the other peer are predicted to stay the same as the last known one,
+// a wrong prediction rolls the match back to the last confirmed tick and simulates it again.
+#[derive(Clone, Debug)]
+pub struct RollbackSession {
+    // Index of the local player, the other peer controls the other one.
+    local: usize,
+    // The match after the ticks both inputs are known for.
+    confirmed: MatchState,
+    // Number of ticks the confirmed match was simulated for,
+    // its own tick stops when the match is won.
+    confirmed_ticks: usize,
+    // The match after all the ticks, some of them with predicted remote inputs.
+    predicted: MatchState,
+    local_inputs: Vec<PlayerInput>,
+    remote_inputs: Vec<PlayerInput>,
+    // Remote inputs the predicted match was simulated with, by the tick.
+    used_remote_inputs: Vec<PlayerInput>,
+    // Number of local inputs the other peer acknowledged.
+    acknowledged: usize,
+    // Checksums of the confirmed match after its ticks, of both peers.
+    checksums: VecDeque<(u64, u64)>,
+    remote_checksums: VecDeque<(u64, u64)>,
+    // First tick the peers' matches differed after, if they did.
+    desync: Option<u64>,
+    rollbacks: usize,
+}
+
+impl RollbackSession {
+    pub fn new(local: usize, stadium: Stadium, rules: MatchRules) -> Self {
+        let state = MatchState::new(stadium, &PEER_TEAMS, rules);
+        RollbackSession {
+            local,
+            predicted: state.clone(),
+            confirmed: state,
+            confirmed_ticks: 0,
+            local_inputs: Vec::new(),
+            remote_inputs: Vec::new(),
+            used_remote_inputs: Vec::new(),
+            acknowledged: 0,
+            checksums: VecDeque::new(),
+            remote_checksums: VecDeque::new(),
+            desync: None,
+            rollbacks: 0,
+        }
+    }
+
+    pub fn local(&self) -> usize {
+        self.local
+    }
+
+    // The match to show, including the predicted ticks.
+    pub fn state(&self) -> &MatchState {
+        &self.predicted
+    }
+
+    // The match both peers agree on.
+    pub fn confirmed(&self) -> &MatchState {
+        &self.confirmed
+    }
+
+    // Number of ticks simulated.
+    pub fn tick(&self) -> u64 {
+        self.local_inputs.len() as u64
+    }
+
+    // Number of ticks both inputs are known for.
+    pub fn confirmed_tick(&self) -> u64 {
+        self.local_inputs.len().min(self.remote_inputs.len()) as u64
+    }
+
+    // Whether the next tick has to wait for the inputs of the other peer.
+    pub fn is_waiting(&self) -> bool {
+        self.tick() >= self.confirmed_tick() + MAX_ROLLBACK_TICKS
+    }
+
+    // Tick after which the matches of the peers differed, which should never happen.
+    pub fn desync(&self) -> Option<u64> {
+        self.desync
+    }
+
+    // Number of wrong predictions of the remote inputs so far.
+    pub fn rollbacks(&self) -> usize {
+        self.rollbacks
+    }
+
+    // Simulates the next tick with the local input, unless it has to wait for the other peer.
+    // Events of predicted ticks may be undone by a rollback.
+    pub fn advance(&mut self, input: PlayerInput) -> Option<Vec<MatchEvent>> {
+        if self.is_waiting() {
+            return None;
+        }
+        // Both peers have to simulate with exactly the same inputs.
+        let input = input.sanitized();
+        let tick = self.local_inputs.len();
+        let remote = self.remote_input(tick);
+        self.local_inputs.push(input);
+        self.used_remote_inputs.push(remote);
+        let events = self.predicted.step(&self.inputs(input, remote));
+        self.confirm();
+        Some(events)
+    }
+
+    // Remote input of the tick, the last known one if it didn't come yet.
+    fn remote_input(&self, tick: usize) -> PlayerInput {
+        self.remote_inputs
+            .get(tick)
+            .or_else(|| self.remote_inputs.last())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // Inputs of both players in their order in the match.
+    fn inputs(&self, local: PlayerInput, remote: PlayerInput) -> [PlayerInput; 2] {
+        if self.local == 0 {
+            [local, remote]
+        } else {
+            [remote, local]
+        }
+    }
+
+    // Message with the local inputs the other peer may not have yet.
+    pub fn message(&self) -> PeerMessage {
+        let start = self.acknowledged.max(
+            self.local_inputs
+                .len()
+                .saturating_sub(MAX_INPUTS_PER_MESSAGE),
+        );
+        PeerMessage::Inputs {
+            start: start as u64,
+            inputs: self.local_inputs[start..].to_vec(),
+            acknowledged: self.remote_inputs.len() as u64,
+            checksum: self.checksums.back().copied(),
+        }
+    }
+
+    // Takes the inputs and the checksum of the other peer, rolling back if they weren't predicted.
+    pub fn receive(&mut self, message: &PeerMessage) {
+        if let PeerMessage::Inputs {
+            start,
+            inputs,
+            acknowledged,
+            checksum,
+        } = message
+        {
+            let acknowledged = (*acknowledged as usize).min(self.local_inputs.len());
+            self.acknowledged = self.acknowledged.max(acknowledged);
+            // Inputs already received are skipped, ones after a gap can't be used yet.
+            let known = self.remote_inputs.len();
+            if let Some(new) = known
+                .checked_sub(*start as usize)
+                .and_then(|skip| inputs.get(skip..))
+            {
+                self.remote_inputs
+                    .extend(new.iter().map(|input| input.sanitized()));
+            }
+            if let Some(checksum) = checksum {
+                if self
+                    .remote_checksums
+                    .back()
+                    .is_none_or(|last| last.0 < checksum.0)
+                {
+                    self.remote_checksums.push_back(*checksum);
+                }
+            }
+            self.confirm();
+        }
+    }
+
+    // Simulates the confirmed match up to the inputs known from both peers.
+    // If the predicted remote inputs were wrong, the predicted match is simulated again from it.
+    fn confirm(&mut self) {
+        let confirmed_tick = self.confirmed_tick() as usize;
+        let mut mispredicted = false;
+        for tick in self.confirmed_ticks..confirmed_tick {
+            let remote = self.remote_inputs[tick];
+            mispredicted |= self.used_remote_inputs[tick] != remote;
+            self.confirmed
+                .step(&self.inputs(self.local_inputs[tick], remote));
+            self.checksums
+                .push_back((tick as u64 + 1, self.confirmed.checksum()));
+            if self.checksums.len() > CHECKSUM_HISTORY {
+                self.checksums.pop_front();
+            }
+        }
+        self.confirmed_ticks = confirmed_tick;
+
+        if mispredicted {
+            self.rollbacks += 1;
+            self.predicted = self.confirmed.clone();
+            for tick in confirmed_tick..self.local_inputs.len() {
+                let remote = self.remote_input(tick);
+                self.used_remote_inputs[tick] = remote;
+                self.predicted
+                    .step(&self.inputs(self.local_inputs[tick], remote));
+            }
+        }
+        self.check_desync();
+    }
+
+    // Compares the checksums of the other peer with the local ones of the same ticks.
+    fn check_desync(&mut self) {
+        while let Some(&(tick, remote)) = self.remote_checksums.front() {
+            let local = match self.checksums.iter().find(|(local, _)| *local == tick) {
+                Some((_, local)) => *local,
+                // The tick isn't confirmed here yet.
+                None if tick > self.confirmed_tick() => break,
+                // The tick is too old to compare.
+                None => {
+                    self.remote_checksums.pop_front();
+                    continue;
+                }
+            };
+            if local != remote && self.desync.is_none() {
+                self.desync = Some(tick);
+            }
+            self.remote_checksums.pop_front();
+        }
+    }
+}
+
+impl MatchState {
+    // Hash of the positions and velocities of all discs and of the score,
+    // equal on both peers as long as their simulations didn't diverge.
+    pub fn checksum(&self) -> u64 {
+        // FNV-1a, which gives the same hash on every platform, unlike the one of the standard library.
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut add = |value: u32| {
+            for byte in value.to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        };
+        for disc in &self.discs {
+            for value in [disc.position, disc.velocity] {
+                add(value.x.to_bits());
+                add(value.y.to_bits());
+            }
+        }
+        add(self.score.red);
+        add(self.score.blue);
+        hash
+    }
+}
@@ -0,0 +1,127 @@
+use crate::protocol::{Location, RoomInfo, RoomListing, MAX_ROOM_NAME};
+use std::cmp::Ordering;
+
+// Mean radius of the Earth in kilometres.
+const EARTH_RADIUS: f32 = 6371.0;
+
+impl Location {
+    // Great-circle distance in kilometres.
+    pub fn distance(&self, other: &Location) -> f32 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.longitude - self.longitude).to_radians();
+        let a = (dlat / 2.).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.).sin().powi(2);
+        2. * EARTH_RADIUS * a.sqrt().min(1.).asin()
+    }
+}
+
+impl RoomInfo {
+    pub fn is_full(&self) -> bool {
+        self.players >= self.max_players
+    }
+
+    // Room which fits the room list, for rooms received from other machines.
+    // The names are cut to `MAX_ROOM_NAME` bytes and coordinates which aren't numbers zeroed.
+    pub fn sanitized(self) -> RoomInfo {
+        let cut = |mut name: String| {
+            // Not in the middle of a character.
+            let mut end = name.len().min(MAX_ROOM_NAME);
+            while !name.is_char_boundary(end) {
+                end -= 1;
+            }
+            name.truncate(end);
+            name
+        };
+        let coordinate = |value: f32, max: f32| {
+            if value.is_finite() {
+                value.clamp(-max, max)
+            } else {
+                0.
+            }
+        };
+        RoomInfo {
+            name: cut(self.name),
+            stadium: cut(self.stadium),
+            location: Location {
+                code: cut(self.location.code),
+                latitude: coordinate(self.location.latitude, 90.),
+                longitude: coordinate(self.location.longitude, 180.),
+            },
+            ..self
+        }
+    }
+}
+
+// Order of the room list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoomSort {
+    // The fullest rooms first.
+    Players,
+    Name,
+    // The nearest rooms first.
+    Distance,
+}
+
+impl RoomSort {
+    // The next order, to cycle through them with a single button.
+    pub fn next(self) -> RoomSort {
+        match self {
+            RoomSort::Players => RoomSort::Name,
+            RoomSort::Name => RoomSort::Distance,
+            RoomSort::Distance => RoomSort::Players,
+        }
+    }
+}
+
+// How the room list is shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoomFilter {
+    pub sort: RoomSort,
+    pub hide_full: bool,
+    // Hides the rooms with a password.
+    pub hide_locked: bool,
+}
+
+impl Default for RoomFilter {
+    fn default() -> Self {
+        RoomFilter {
+            sort: RoomSort::Players,
+            hide_full: false,
+            hide_locked: false,
+        }
+    }
+}
+
+impl RoomFilter {
+    // The rooms left by the filter in its order, distances are measured from `origin`.
+    // Ties are broken by the name and then the address, so the list doesn't shuffle on a refresh.
+    pub fn apply<'a>(&self, rooms: &'a [RoomListing], origin: &Location) -> Vec<&'a RoomListing> {
+        let mut rooms: Vec<&RoomListing> = rooms
+            .iter()
+            .filter(|listing| !(self.hide_full && listing.room.is_full()))
+            .filter(|listing| !(self.hide_locked && listing.room.password))
+            .collect();
+        let by_name = |a: &RoomListing, b: &RoomListing| {
+            a.room
+                .name
+                .to_lowercase()
+                .cmp(&b.room.name.to_lowercase())
+                .then(a.address.cmp(&b.address))
+        };
+        rooms.sort_by(|a, b| {
+            let order = match self.sort {
+                RoomSort::Players => b.room.players.cmp(&a.room.players),
+                RoomSort::Name => Ordering::Equal,
+                RoomSort::Distance => {
+                    let (a, b) = (
+                        origin.distance(&a.room.location),
+                        origin.distance(&b.room.location),
+                    );
+                    a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+                }
+            };
+            order.then_with(|| by_name(a, b))
+        });
+        rooms
+    }
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+// Rules deciding when a match ends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchRules {
+    // Goals needed to win the match, `None` means unlimited.
+    pub score_limit: Option<u32>,
+    // Length of the match in minutes, `None` means unlimited.
+    // When the time is up and the score is tied, the next goal wins (sudden death).
+    pub time_limit: Option<u32>,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        MatchRules {
+            score_limit: Some(3),
+            time_limit: Some(3),
+        }
+    }
+}
@@ -0,0 +1,444 @@
+use crate::{CollisionGroups, Team};
+use crate::{ACCELERATION, KICKING_ACCELERATION, MAX_SPEED};
+use crate::{BALL_DAMPING, BALL_INV_MASS, BALL_RADIUS, BALL_RESTITUTION};
+use crate::{CENTER_CIRCLE_RADIUS, CORNER_DOWN_HEIGHT, CORNER_RADIUS, CORNER_UP_HEIGHT};
+use crate::{GOAL_DEPTH, GOAL_NET_RESTITUTION};
+use crate::{KICK_COOLDOWN, KICK_REACH, KICK_STRENGTH};
+use crate::{PITCH_HEIGHT, PITCH_WIDTH, SPAWN_POSITIONS};
+use crate::{PLAYER_DAMPING, PLAYER_INV_MASS, PLAYER_RADIUS, PLAYER_RESTITUTION};
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use std::{error, fmt};
+
+// Physical properties shared by a kind of discs.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DiscProperties {
+    pub radius: f32,
+    pub inv_mass: f32,
+    pub restitution: f32,
+    // Part of the velocity kept after every tick, slowing the disc down.
+    pub damping: f32,
+}
+
+fn full_restitution() -> f32 {
+    1.0
+}
+
+fn wall_group() -> CollisionGroups {
+    CollisionGroups::WALL
+}
+
+fn all_groups() -> CollisionGroups {
+    CollisionGroups::ALL
+}
+
+fn visible() -> bool {
+    true
+}
+
+// Physics constants of a stadium, missing ones take the default values.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PhysicsConstants {
+    // Limit of the speed the players may run with.
+    pub max_speed: f32,
+    pub acceleration: f32,
+    // Acceleration of the players holding the kick button.
+    pub kicking_acceleration: f32,
+    pub kick_strength: f32,
+    // How far from the edge of a player the ball can still be kicked.
+    pub kick_reach: f32,
+    // Ticks after a kick before the player can kick again.
+    pub kick_cooldown: u32,
+    pub player: DiscProperties,
+    pub ball: DiscProperties,
+}
+
+impl Default for PhysicsConstants {
+    fn default() -> Self {
+        PhysicsConstants {
+            max_speed: MAX_SPEED,
+            acceleration: ACCELERATION,
+            kicking_acceleration: KICKING_ACCELERATION,
+            kick_strength: KICK_STRENGTH,
+            kick_reach: KICK_REACH,
+            kick_cooldown: KICK_COOLDOWN,
+            player: DiscProperties {
+                radius: PLAYER_RADIUS,
+                inv_mass: PLAYER_INV_MASS,
+                restitution: PLAYER_RESTITUTION,
+                damping: PLAYER_DAMPING,
+            },
+            ball: DiscProperties {
+                radius: BALL_RADIUS,
+                inv_mass: BALL_INV_MASS,
+                restitution: BALL_RESTITUTION,
+                damping: BALL_DAMPING,
+            },
+        }
+    }
+}
+
+// Goal line defended by the given team.
+// The ball crossing it between the posts is a goal for the other team.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Goal {
+    pub team: Team,
+    pub x: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl Goal {
+    // Whether the given point is behind the goal line, between the posts.
+    pub fn contains(&self, point: Vec2) -> bool {
+        let outward = self.x.signum();
+        (point.x - self.x) * outward >= 0. && point.y <= self.top && point.y >= self.bottom
+    }
+}
+
+// Static circle the discs bounce off, like a goal post.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Post {
+    pub position: Vec2,
+    pub radius: f32,
+    #[serde(default = "full_restitution")]
+    pub restitution: f32,
+    #[serde(default = "wall_group")]
+    pub group: CollisionGroups,
+    #[serde(default = "all_groups")]
+    pub mask: CollisionGroups,
+}
+
+// Wall the discs bounce off, going from `a` to `b`.
+// A non-zero curve makes it an arc spanning that many degrees,
+// bulging to the right of the way from `a` to `b` for positive values.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    pub a: Vec2,
+    pub b: Vec2,
+    #[serde(default)]
+    pub curve: f32,
+    #[serde(default = "full_restitution")]
+    pub restitution: f32,
+    #[serde(default = "wall_group")]
+    pub group: CollisionGroups,
+    #[serde(default = "all_groups")]
+    pub mask: CollisionGroups,
+    #[serde(default = "visible")]
+    pub visible: bool,
+}
+
+// Arc of a curved segment, going counterclockwise from `start`.
+pub struct SegmentArc {
+    pub center: Vec2,
+    pub radius: f32,
+    pub start: Vec2,
+    // Angle spanned by the arc in radians.
+    pub angle: f32,
+}
+
+impl SegmentArc {
+    // Whether the direction from the center towards the point goes through the arc.
+    pub fn covers(&self, point: Vec2) -> bool {
+        let from = self.start - self.center;
+        let to = point - self.center;
+        let mut angle = from.perp_dot(to).atan2(from.dot(to));
+        if angle < 0. {
+            angle += TAU;
+        }
+        angle <= self.angle
+    }
+}
+
+impl Segment {
+    // A straight wall with the default properties.
+    pub fn new(a: Vec2, b: Vec2) -> Self {
+        Segment {
+            a,
+            b,
+            curve: 0.,
+            restitution: full_restitution(),
+            group: wall_group(),
+            mask: all_groups(),
+            visible: visible(),
+        }
+    }
+
+    // The arc of the segment, None if it's straight.
+    pub fn arc(&self) -> Option<SegmentArc> {
+        let curve = self.curve.to_radians();
+        if curve == 0. || curve.abs() >= TAU || self.a == self.b {
+            return None;
+        }
+        let (start, end, angle) = if curve > 0. {
+            (self.a, self.b, curve)
+        } else {
+            (self.b, self.a, -curve)
+        };
+
+        let half = (end - start) / 2.;
+        let center = start + half + half.perp() / (angle / 2.).tan();
+        Some(SegmentArc {
+            center,
+            radius: center.distance(start),
+            start,
+            angle,
+        })
+    }
+
+    // Point of the segment nearest to the given one.
+    pub fn closest_point(&self, point: Vec2) -> Vec2 {
+        if let Some(arc) = self.arc() {
+            let direction = (point - arc.center).normalize_or_zero();
+            if direction != Vec2::ZERO && arc.covers(point) {
+                return arc.center + direction * arc.radius;
+            }
+            return if point.distance(self.a) < point.distance(self.b) {
+                self.a
+            } else {
+                self.b
+            };
+        }
+
+        let ab = self.b - self.a;
+        let length_squared = ab.length_squared();
+        if length_squared == 0. {
+            return self.a;
+        }
+        let t = ((point - self.a).dot(ab) / length_squared).clamp(0., 1.);
+        self.a + ab * t
+    }
+}
+
+// Infinite wall, discs are kept on the side the normal points to.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Plane {
+    pub normal: Vec2,
+    // Distance of the wall from the origin along the normal.
+    pub distance: f32,
+    #[serde(default = "full_restitution")]
+    pub restitution: f32,
+    #[serde(default = "wall_group")]
+    pub group: CollisionGroups,
+    #[serde(default = "all_groups")]
+    pub mask: CollisionGroups,
+}
+
+// What is drawn under the discs, an image from the assets or a plain color.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Background {
+    Image(String),
+    Color(f32, f32, f32),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Stadium {
+    pub name: String,
+    // Size of the pitch drawn with the background, centered at the origin.
+    pub width: f32,
+    pub height: f32,
+    pub goals: Vec<Goal>,
+    pub posts: Vec<Post>,
+    // Walls keeping the discs on the pitch, goal nets included.
+    #[serde(default)]
+    pub walls: Vec<Segment>,
+    #[serde(default)]
+    pub planes: Vec<Plane>,
+    // Kickoff positions of the players by their slot.
+    // Blue players stand mirrored to the red ones, unless their positions are given.
+    pub red_spawn_points: Vec<Vec2>,
+    #[serde(default)]
+    pub blue_spawn_points: Vec<Vec2>,
+    pub background: Background,
+    #[serde(default)]
+    pub physics: PhysicsConstants,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StadiumError {
+    // The file isn't a valid stadium description.
+    Parse(String),
+    // The file uses a feature RustBall doesn't simulate.
+    Unsupported(String),
+    // The stadium is well-formed, but can't be played on.
+    Invalid(String),
+}
+
+impl fmt::Display for StadiumError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StadiumError::Parse(message) => write!(f, "can't parse stadium: {}", message),
+            StadiumError::Unsupported(message) => write!(f, "unsupported stadium: {}", message),
+            StadiumError::Invalid(message) => write!(f, "invalid stadium: {}", message),
+        }
+    }
+}
+
+impl error::Error for StadiumError {}
+
+impl Default for Stadium {
+    // The pitch RustBall was originally played on.
+    fn default() -> Self {
+        let half_width = PITCH_WIDTH / 2.;
+        let half_height = PITCH_HEIGHT / 2.;
+        let goal = |team, x| Goal {
+            team,
+            x,
+            top: CORNER_UP_HEIGHT,
+            bottom: CORNER_DOWN_HEIGHT,
+        };
+        let post = |x, y| Post {
+            position: Vec2::new(x, y),
+            radius: CORNER_RADIUS,
+            restitution: full_restitution(),
+            group: wall_group(),
+            mask: all_groups(),
+        };
+
+        Stadium {
+            name: "Classic".to_string(),
+            width: PITCH_WIDTH,
+            height: PITCH_HEIGHT,
+            goals: vec![goal(Team::Red, -half_width), goal(Team::Blue, half_width)],
+            posts: vec![
+                post(-half_width, CORNER_UP_HEIGHT),
+                post(half_width, CORNER_UP_HEIGHT),
+                post(half_width, CORNER_DOWN_HEIGHT),
+                post(-half_width, CORNER_DOWN_HEIGHT),
+            ],
+            walls: [-half_width, half_width]
+                .into_iter()
+                .flat_map(|x| {
+                    let net = x + GOAL_DEPTH * x.signum();
+                    let corner_up = Vec2::new(x, CORNER_UP_HEIGHT);
+                    let corner_down = Vec2::new(x, CORNER_DOWN_HEIGHT);
+                    let net_wall = |a, b| Segment {
+                        restitution: GOAL_NET_RESTITUTION,
+                        ..Segment::new(a, b)
+                    };
+                    let edge = |a, b| Segment {
+                        visible: false,
+                        ..Segment::new(a, b)
+                    };
+                    [
+                        // Each side adds one of the top and bottom edges.
+                        edge(
+                            Vec2::new(x, half_height * x.signum()),
+                            Vec2::new(-x, half_height * x.signum()),
+                        ),
+                        edge(Vec2::new(x, half_height), corner_up),
+                        edge(corner_down, Vec2::new(x, -half_height)),
+                        // Players can't enter the goal.
+                        Segment {
+                            mask: CollisionGroups::RED | CollisionGroups::BLUE,
+                            ..edge(corner_up, corner_down)
+                        },
+                        net_wall(corner_up, Vec2::new(net, CORNER_UP_HEIGHT)),
+                        net_wall(
+                            Vec2::new(net, CORNER_UP_HEIGHT),
+                            Vec2::new(net, CORNER_DOWN_HEIGHT),
+                        ),
+                        net_wall(Vec2::new(net, CORNER_DOWN_HEIGHT), corner_down),
+                    ]
+                })
+                .chain(kickoff_barriers())
+                .collect(),
+            planes: Vec::new(),
+            red_spawn_points: SPAWN_POSITIONS
+                .iter()
+                .map(|&(x, y)| Vec2::new(x, y))
+                .collect(),
+            blue_spawn_points: Vec::new(),
+            background: Background::Image("pitch1.png".to_string()),
+            physics: PhysicsConstants::default(),
+        }
+    }
+}
+
+// Walls stopping only the players until the kickoff is taken.
+// They keep everybody in their halves and the team which doesn't kick off out of the center circle.
+fn kickoff_barriers() -> [Segment; 4] {
+    let half_height = PITCH_HEIGHT / 2.;
+    let circle_top = Vec2::new(0., CENTER_CIRCLE_RADIUS);
+    let circle_bottom = Vec2::new(0., -CENTER_CIRCLE_RADIUS);
+    let barrier = |a, b, curve, group| Segment {
+        curve,
+        restitution: 0.,
+        group,
+        mask: CollisionGroups::RED | CollisionGroups::BLUE,
+        visible: false,
+        ..Segment::new(a, b)
+    };
+    let both = CollisionGroups::RED_KICKOFF | CollisionGroups::BLUE_KICKOFF;
+    [
+        // The center line above and below the center circle.
+        barrier(Vec2::new(0., half_height), circle_top, 0., both),
+        barrier(circle_bottom, Vec2::new(0., -half_height), 0., both),
+        // Half of the center circle on the side of the defending team.
+        barrier(
+            circle_top,
+            circle_bottom,
+            -180.,
+            CollisionGroups::RED_KICKOFF,
+        ),
+        barrier(
+            circle_top,
+            circle_bottom,
+            180.,
+            CollisionGroups::BLUE_KICKOFF,
+        ),
+    ]
+}
+
+impl Stadium {
+    // Reads a stadium from its RON description.
+    pub fn from_ron(source: &str) -> Result<Stadium, StadiumError> {
+        let stadium: Stadium =
+            ron::de::from_str(source).map_err(|err| StadiumError::Parse(err.to_string()))?;
+        stadium.validate()?;
+        Ok(stadium)
+    }
+
+    // Checks whether a match can be played on the stadium.
+    pub fn validate(&self) -> Result<(), StadiumError> {
+        if self.width <= 0. || self.height <= 0. {
+            return Err(StadiumError::Invalid("size must be positive".to_string()));
+        }
+        if self.red_spawn_points.is_empty() {
+            return Err(StadiumError::Invalid("no spawn points".to_string()));
+        }
+        for team in [Team::Red, Team::Blue] {
+            if !self.goals.iter().any(|goal| goal.team == team) {
+                return Err(StadiumError::Invalid(format!("no goal of {:?} team", team)));
+            }
+        }
+        Ok(())
+    }
+
+    // Half of the size of the area covering the pitch and everything built around it.
+    pub fn half_extent(&self) -> Vec2 {
+        let mut extent = Vec2::new(self.width, self.height) / 2.;
+        for wall in &self.walls {
+            extent = extent.max(wall.a.abs()).max(wall.b.abs());
+        }
+        for post in &self.posts {
+            extent = extent.max(post.position.abs() + Vec2::splat(post.radius));
+        }
+        extent
+    }
+
+    // Kickoff position of the player in the given slot of the team.
+    // Players beyond the given spawn points are placed behind them.
+    pub fn spawn_position(&self, team: Team, slot: usize) -> Vec2 {
+        let (points, mirror) = match team {
+            Team::Blue if !self.blue_spawn_points.is_empty() => (&self.blue_spawn_points, 1.),
+            Team::Blue => (&self.red_spawn_points, -1.),
+            Team::Red => (&self.red_spawn_points, 1.),
+        };
+        let point = points[slot % points.len()] * Vec2::new(mirror, 1.);
+        let row = (slot / points.len()) as f32;
+        point - Vec2::new(row * 60. * team.attack_direction(), 0.)
+    }
+}
@@ -0,0 +1,507 @@
+use crate::physics;
+use crate::{can_collide, CollisionGroups, Disc, MatchRules, PlayerInput, Stadium};
+use crate::{GOAL_RESET_DELAY, PLAYER_MASK, TICKS_PER_SECOND};
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+// Index of the ball in the discs of the match.
+pub const BALL: usize = 0;
+
+// Limit of the contacts resolved one by one within a tick,
+// in case discs get stuck bouncing between each other.
+const MAX_CONTACTS_PER_TICK: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Team {
+    Red,
+    Blue,
+}
+
+impl Team {
+    pub fn opponent(&self) -> Team {
+        match self {
+            Team::Red => Team::Blue,
+            Team::Blue => Team::Red,
+        }
+    }
+
+    // Direction from the team's own goal towards the opponent's goal.
+    pub fn attack_direction(&self) -> f32 {
+        match self {
+            Team::Red => 1.,
+            Team::Blue => -1.,
+        }
+    }
+
+    // Collision group of the team's players.
+    pub fn collision_group(&self) -> CollisionGroups {
+        match self {
+            Team::Red => CollisionGroups::RED,
+            Team::Blue => CollisionGroups::BLUE,
+        }
+    }
+
+    // Collision group of the barriers keeping the players in place while the team kicks off.
+    pub fn kickoff_collision_group(&self) -> CollisionGroups {
+        match self {
+            Team::Red => CollisionGroups::RED_KICKOFF,
+            Team::Blue => CollisionGroups::BLUE_KICKOFF,
+        }
+    }
+}
+
+// What the player did during the match.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub goals: u32,
+    pub own_goals: u32,
+    pub kicks: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Player {
+    pub team: Team,
+    // Index of the player within its team.
+    pub slot: usize,
+    // Index of the disc controlled by the player.
+    pub disc: usize,
+    pub stats: PlayerStats,
+    // Whether the player holds the kick button and is able to kick.
+    pub kick_armed: bool,
+    // Holding the kick button kicks only once, it has to be released before the next kick.
+    pub kick_released: bool,
+    // Ticks left until the player can kick again.
+    pub kick_cooldown: u32,
+}
+
+impl Player {
+    // Position of the player at the kickoff.
+    pub fn initial_position(&self, stadium: &Stadium) -> Vec2 {
+        stadium.spawn_position(self.team, self.slot)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Score {
+    pub red: u32,
+    pub blue: u32,
+}
+
+// Things that happened during a tick, which the caller may want to react to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchEvent {
+    // The given team scored a goal.
+    Goal(Team),
+    // Everybody was moved back to the kickoff positions, the given team kicks off.
+    Kickoff(Team),
+    // The time is up with a tied score, the next goal wins.
+    Overtime,
+    // The given team won the match, the simulation stops.
+    Won(Team),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchState {
+    // The ball comes first, then one disc per player.
+    pub discs: Vec<Disc>,
+    pub players: Vec<Player>,
+    pub score: Score,
+    pub rules: MatchRules,
+    pub stadium: Stadium,
+    // Ticks since the start of the match, used as the match clock.
+    pub tick: u64,
+    pub overtime: bool,
+    pub winner: Option<Team>,
+    // Team taking the kickoff, until somebody touches the ball.
+    pub kickoff: Option<Team>,
+    // Player who touched the ball last, credited with the next goal.
+    pub last_touch: Option<usize>,
+    // After a goal, the tick at which everybody lines up again and the team kicking off.
+    pub restart: Option<(u64, Team)>,
+}
+
+impl MatchState {
+    // Creates a match on the given stadium with one player per given team, in the given order.
+    pub fn new(stadium: Stadium, teams: &[Team], rules: MatchRules) -> Self {
+        let ball = stadium.physics.ball;
+        let mut discs = vec![Disc {
+            group: CollisionGroups::BALL,
+            ..Disc::new(Vec2::ZERO, ball.radius, ball.inv_mass, ball.restitution)
+        }];
+        let player_properties = stadium.physics.player;
+        let mut players = Vec::new();
+
+        for &team in teams {
+            let slot = players.iter().filter(|p: &&Player| p.team == team).count();
+            let player = Player {
+                team,
+                slot,
+                disc: discs.len(),
+                stats: PlayerStats::default(),
+                kick_armed: false,
+                kick_released: true,
+                kick_cooldown: 0,
+            };
+            discs.push(Disc {
+                group: team.collision_group(),
+                mask: PLAYER_MASK,
+                ..Disc::new(
+                    player.initial_position(&stadium),
+                    player_properties.radius,
+                    player_properties.inv_mass,
+                    player_properties.restitution,
+                )
+            });
+            players.push(player);
+        }
+
+        MatchState {
+            discs,
+            players,
+            score: Score::default(),
+            rules,
+            stadium,
+            tick: 0,
+            overtime: false,
+            winner: None,
+            kickoff: Some(Team::Red),
+            last_touch: None,
+            restart: None,
+        }
+    }
+
+    pub fn ball(&self) -> &Disc {
+        &self.discs[BALL]
+    }
+
+    pub fn player_disc(&self, player: usize) -> &Disc {
+        &self.discs[self.players[player].disc]
+    }
+
+    // Time since the start of the match in seconds.
+    pub fn elapsed_seconds(&self) -> u64 {
+        self.tick / TICKS_PER_SECOND as u64
+    }
+
+    // Advances the simulation by a single tick.
+    // Inputs are given in the order of players, missing inputs mean no action.
+    // Once the match is over, nothing happens anymore.
+    pub fn step(&mut self, inputs: &[PlayerInput]) -> Vec<MatchEvent> {
+        let mut events = Vec::new();
+        if self.winner.is_some() {
+            return events;
+        }
+        self.tick += 1;
+
+        // Discs slow down before the players speed up, so they can keep running at the top speed.
+        let constants = self.stadium.physics;
+        for (i, disc) in self.discs.iter_mut().enumerate() {
+            let properties = if i == BALL {
+                constants.ball
+            } else {
+                constants.player
+            };
+            disc.velocity *= properties.damping;
+        }
+        for (i, player) in self.players.iter().enumerate() {
+            let input = inputs.get(i).copied().unwrap_or_default();
+            physics::accelerate(&mut self.discs[player.disc].velocity, &input, &constants);
+        }
+
+        // A press of the kick button shoots the ball if it's within the reach of the player.
+        for (i, player) in self.players.iter_mut().enumerate() {
+            let kick = inputs.get(i).is_some_and(|input| input.kick);
+            player.kick_cooldown = player.kick_cooldown.saturating_sub(1);
+            player.kick_released |= !kick;
+            player.kick_armed = kick && player.kick_released && player.kick_cooldown == 0;
+            if !player.kick_armed {
+                continue;
+            }
+
+            let (ball, player_disc) = pair_mut(&mut self.discs, BALL, player.disc);
+            let reach = player_disc.radius + ball.radius + constants.kick_reach;
+            if player_disc.position.distance(ball.position) <= reach {
+                physics::kick(player_disc, ball, constants.kick_strength);
+                player.stats.kicks += 1;
+                player.kick_armed = false;
+                player.kick_released = false;
+                player.kick_cooldown = constants.kick_cooldown;
+                self.last_touch = Some(i);
+                self.kickoff = None;
+            }
+        }
+
+        // Until the kickoff is taken, the players are also stopped by the barriers
+        // of the kicking team, keeping them in their halves and out of the center circle.
+        let kickoff_group = self
+            .kickoff
+            .map_or(CollisionGroups::NONE, |team| team.kickoff_collision_group());
+        let mut masks: Vec<CollisionGroups> = self.discs.iter().map(|disc| disc.mask).collect();
+        for player in &self.players {
+            masks[player.disc] = masks[player.disc] | kickoff_group;
+        }
+
+        // Discs move until the first contact, which is resolved before they move on,
+        // so fast discs can't pass through each other or the walls within a tick.
+        let mut remaining = 1.;
+        let mut contacts = 0;
+        while remaining > 0. {
+            let contact = if contacts < MAX_CONTACTS_PER_TICK {
+                self.first_contact(remaining, &masks)
+            } else {
+                None
+            };
+            let time = contact.map_or(remaining, |(time, _, _)| time);
+            for disc in self.discs.iter_mut() {
+                disc.position += disc.velocity * time;
+            }
+            remaining -= time;
+            if let Some((_, disc, obstacle)) = contact {
+                self.resolve_contact(disc, obstacle);
+                contacts += 1;
+            }
+        }
+
+        // Discs overlapping anyway, e.g. squeezed between others, are pushed apart.
+        for i in 0..self.players.len() {
+            if self.player_disc(i).collides_with(self.ball()) {
+                self.touch_ball(i);
+            }
+        }
+        for i in 0..self.players.len() {
+            for j in i + 1..self.players.len() {
+                let (disc1, disc2) =
+                    pair_mut(&mut self.discs, self.players[i].disc, self.players[j].disc);
+                if disc1.collides_with(disc2) {
+                    physics::collide(disc1, disc2);
+                }
+            }
+        }
+        for (disc, mask) in self.discs.iter_mut().zip(masks) {
+            let group = disc.group;
+            let collides = |wall_group, wall_mask| can_collide(wall_group, wall_mask, group, mask);
+            for wall in self
+                .stadium
+                .walls
+                .iter()
+                .filter(|w| collides(w.group, w.mask))
+            {
+                physics::wall_collision(disc, wall);
+            }
+            for plane in self
+                .stadium
+                .planes
+                .iter()
+                .filter(|p| collides(p.group, p.mask))
+            {
+                physics::plane_collision(disc, plane);
+            }
+            for post in self
+                .stadium
+                .posts
+                .iter()
+                .filter(|p| collides(p.group, p.mask))
+            {
+                physics::post_collision(disc, post);
+            }
+        }
+
+        self.check_goal(&mut events);
+        self.check_restart(&mut events);
+        self.check_time(&mut events);
+        events
+    }
+
+    // The earliest contact within the given time: when it happens, which disc moves into what.
+    fn first_contact(
+        &self,
+        time: f32,
+        masks: &[CollisionGroups],
+    ) -> Option<(f32, usize, Obstacle)> {
+        let mut first: Option<(f32, usize, Obstacle)> = None;
+        let mut consider = |contact_time: Option<f32>, disc: usize, obstacle: Obstacle| {
+            if let Some(contact_time) = contact_time {
+                if first.is_none_or(|(first_time, _, _)| contact_time < first_time) {
+                    first = Some((contact_time, disc, obstacle));
+                }
+            }
+        };
+
+        for (i, disc) in self.discs.iter().enumerate() {
+            let collides = |group, mask| can_collide(group, mask, disc.group, masks[i]);
+            for (j, other) in self.discs.iter().enumerate().skip(i + 1) {
+                if collides(other.group, other.mask) {
+                    consider(
+                        physics::sweep_discs(disc, other, time),
+                        i,
+                        Obstacle::Disc(j),
+                    );
+                }
+            }
+            for (j, wall) in self.stadium.walls.iter().enumerate() {
+                if collides(wall.group, wall.mask) {
+                    consider(physics::sweep_wall(disc, wall, time), i, Obstacle::Wall(j));
+                }
+            }
+            for (j, plane) in self.stadium.planes.iter().enumerate() {
+                if collides(plane.group, plane.mask) {
+                    consider(
+                        physics::sweep_plane(disc, plane, time),
+                        i,
+                        Obstacle::Plane(j),
+                    );
+                }
+            }
+            for (j, post) in self.stadium.posts.iter().enumerate() {
+                if collides(post.group, post.mask) {
+                    consider(physics::sweep_post(disc, post, time), i, Obstacle::Post(j));
+                }
+            }
+        }
+        first
+    }
+
+    // Bounces the disc off what it ran into.
+    fn resolve_contact(&mut self, disc: usize, obstacle: Obstacle) {
+        match obstacle {
+            // The ball comes first, so it's never the other disc.
+            Obstacle::Disc(other) if disc == BALL => {
+                if let Some(player) = self.players.iter().position(|p| p.disc == other) {
+                    self.touch_ball(player);
+                }
+            }
+            Obstacle::Disc(other) => {
+                let (disc1, disc2) = pair_mut(&mut self.discs, disc, other);
+                physics::collide(disc1, disc2);
+            }
+            Obstacle::Wall(wall) => {
+                physics::wall_collision(&mut self.discs[disc], &self.stadium.walls[wall]);
+            }
+            Obstacle::Plane(plane) => {
+                physics::plane_collision(&mut self.discs[disc], &self.stadium.planes[plane]);
+            }
+            Obstacle::Post(post) => {
+                physics::post_collision(&mut self.discs[disc], &self.stadium.posts[post]);
+            }
+        }
+    }
+
+    // Collision of the given player with the ball.
+    fn touch_ball(&mut self, i: usize) {
+        let (ball, player_disc) = pair_mut(&mut self.discs, BALL, self.players[i].disc);
+        physics::collide(player_disc, ball);
+        self.last_touch = Some(i);
+        self.kickoff = None;
+    }
+
+    // Check if the ball crossed a goal line.
+    // If it did, update the score and plan the restart, the ball stays in the net until then.
+    fn check_goal(&mut self, events: &mut Vec<MatchEvent>) {
+        if self.restart.is_some() {
+            return;
+        }
+        let ball = self.discs[BALL].position;
+        let scorer = match self.stadium.goals.iter().find(|goal| goal.contains(ball)) {
+            Some(goal) => goal.team.opponent(),
+            None => return,
+        };
+
+        match scorer {
+            Team::Red => self.score.red += 1,
+            Team::Blue => self.score.blue += 1,
+        }
+        if let Some(player) = self.last_touch {
+            let player = &mut self.players[player];
+            if player.team == scorer {
+                player.stats.goals += 1;
+            } else {
+                player.stats.own_goals += 1;
+            }
+        }
+        events.push(MatchEvent::Goal(scorer));
+        self.restart = Some((self.tick + GOAL_RESET_DELAY, scorer.opponent()));
+
+        let limit_reached = self
+            .rules
+            .score_limit
+            .is_some_and(|limit| self.score.red >= limit || self.score.blue >= limit);
+        if limit_reached || self.overtime {
+            self.finish(scorer, events);
+        }
+    }
+
+    // Check if it's time to line up for the kickoff after a goal.
+    fn check_restart(&mut self, events: &mut Vec<MatchEvent>) {
+        if let Some((tick, kicking_team)) = self.restart {
+            if self.tick >= tick {
+                self.reset_positions();
+                self.kickoff = Some(kicking_team);
+                self.restart = None;
+                events.push(MatchEvent::Kickoff(kicking_team));
+            }
+        }
+    }
+
+    // Check if the time is up.
+    // If it is, the leading team wins, a tie leads to the overtime.
+    fn check_time(&mut self, events: &mut Vec<MatchEvent>) {
+        let time_limit = match self.rules.time_limit {
+            Some(minutes) => minutes as u64 * 60 * TICKS_PER_SECOND as u64,
+            None => return,
+        };
+        if self.overtime || self.tick < time_limit {
+            return;
+        }
+
+        match self.score.red.cmp(&self.score.blue) {
+            Ordering::Greater => self.finish(Team::Red, events),
+            Ordering::Less => self.finish(Team::Blue, events),
+            Ordering::Equal => {
+                self.overtime = true;
+                events.push(MatchEvent::Overtime);
+            }
+        }
+    }
+
+    // Announces the winner and stops the match.
+    fn finish(&mut self, winner: Team, events: &mut Vec<MatchEvent>) {
+        events.push(MatchEvent::Won(winner));
+        self.winner = Some(winner);
+    }
+
+    // Moves the ball and the players to the kickoff positions.
+    pub fn reset_positions(&mut self) {
+        let ball = &mut self.discs[BALL];
+        ball.position = Vec2::ZERO;
+        ball.velocity = Vec2::ZERO;
+        self.last_touch = None;
+
+        for player in &self.players {
+            let disc = &mut self.discs[player.disc];
+            disc.position = player.initial_position(&self.stadium);
+            disc.velocity = Vec2::ZERO;
+        }
+    }
+}
+
+// Something a moving disc may run into, given by its index.
+#[derive(Clone, Copy, Debug)]
+enum Obstacle {
+    Disc(usize),
+    Wall(usize),
+    Plane(usize),
+    Post(usize),
+}
+
+// Borrows two different discs mutably at once.
+fn pair_mut(discs: &mut [Disc], i: usize, j: usize) -> (&mut Disc, &mut Disc) {
+    assert_ne!(i, j);
+    if i < j {
+        let (left, right) = discs.split_at_mut(j);
+        (&mut left[i], &mut right[0])
+    } else {
+        let (left, right) = discs.split_at_mut(i);
+        (&mut right[0], &mut left[j])
+    }
+}
@@ -0,0 +1,103 @@
+use rustball_core::{Bot, BotRole, Difficulty, MatchEvent, MatchRules, MatchState, PlayerInput};
+use rustball_core::{Stadium, Team, Vec2, BALL, PITCH_WIDTH, TICKS_PER_SECOND};
+
+fn one_on_one() -> MatchState {
+    MatchState::new(
+        Stadium::default(),
+        &[Team::Red, Team::Blue],
+        MatchRules::default(),
+    )
+}
+
+// Plays with the bot controlling the red player until somebody scores.
+fn play(state: &mut MatchState, bot: &mut Bot, seconds: u32) -> Option<Team> {
+    for _ in 0..seconds * TICKS_PER_SECOND {
+        let input = bot.input(state);
+        let events = state.step(&[input, PlayerInput::default()]);
+        for event in events {
+            if let MatchEvent::Goal(team) = event {
+                return Some(team);
+            }
+        }
+    }
+    None
+}
+
+#[test]
+fn attacker_scores() {
+    for difficulty in Difficulty::ALL {
+        let mut state = one_on_one();
+        // The blue player stands out of the way.
+        state.discs[state.players[1].disc].position = Vec2::new(0., 300.);
+        let mut bot = Bot::new(0, difficulty, BotRole::Attacker, 7);
+
+        assert_eq!(play(&mut state, &mut bot, 30), Some(Team::Red));
+    }
+}
+
+#[test]
+fn goalkeeper_stays_in_front_of_the_goal() {
+    let mut state = one_on_one();
+    let mut bot = Bot::new(0, Difficulty::Hard, BotRole::Goalkeeper, 7);
+
+    play(&mut state, &mut bot, 5);
+
+    let own_goal = Vec2::new(-PITCH_WIDTH / 2., 0.);
+    assert!(state.player_disc(0).position.distance(own_goal) < 80.);
+}
+
+#[test]
+fn goalkeeper_clears_a_close_ball() {
+    let mut state = one_on_one();
+    state.discs[BALL].position = Vec2::new(-PITCH_WIDTH / 2. + 150., 100.);
+    let mut bot = Bot::new(0, Difficulty::Hard, BotRole::Goalkeeper, 7);
+
+    play(&mut state, &mut bot, 5);
+
+    assert!(state.ball().position.x > -PITCH_WIDTH / 2. + 150.);
+}
+
+#[test]
+fn defender_stands_between_the_ball_and_the_goal() {
+    let mut state = one_on_one();
+    state.discs[BALL].position = Vec2::new(300., 0.);
+    state.kickoff = None;
+    let mut bot = Bot::new(0, Difficulty::Hard, BotRole::Defender, 7);
+
+    play(&mut state, &mut bot, 5);
+
+    let position = state.player_disc(0).position;
+    assert!(position.x < 0. && position.x > -PITCH_WIDTH / 2.);
+    assert!(position.y.abs() < 20.);
+}
+
+#[test]
+fn bot_reacts_after_its_delay() {
+    let mut state = one_on_one();
+    state.discs[BALL].position = Vec2::new(0., 200.);
+    let difficulty = Difficulty::Normal;
+    let mut bot = Bot::new(0, difficulty, BotRole::Attacker, 7);
+    for _ in 0..=difficulty.reaction_delay() {
+        bot.input(&state);
+    }
+    let before = bot.input(&state);
+
+    state.discs[BALL].position = Vec2::new(0., -200.);
+    for _ in 0..difficulty.reaction_delay() {
+        assert_eq!(bot.input(&state), before);
+    }
+    assert_ne!(bot.input(&state), before);
+}
+
+#[test]
+fn bots_with_the_same_seed_play_the_same() {
+    let mut first = one_on_one();
+    let mut second = one_on_one();
+    let mut first_bot = Bot::new(0, Difficulty::Easy, BotRole::Attacker, 42);
+    let mut second_bot = first_bot.clone();
+
+    play(&mut first, &mut first_bot, 10);
+    play(&mut second, &mut second_bot, 10);
+
+    assert_eq!(first, second);
+}
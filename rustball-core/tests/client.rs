@@ -0,0 +1,224 @@
+use rustball_core::protocol::ClientMessage;
+use rustball_core::{ClientMatch, LinkSimulator, MatchRules, MatchState, NetworkConditions};
+use rustball_core::{PlayerInput, Stadium, Team, Vec2, BALL};
+use std::time::{Duration, Instant};
+
+const TEAMS: [Team; 2] = [Team::Red, Team::Blue];
+
+fn server() -> MatchState {
+    MatchState::new(Stadium::default(), &TEAMS, MatchRules::default())
+}
+
+fn client() -> ClientMatch {
+    ClientMatch::new(Some(0), Stadium::default(), MatchRules::default(), &TEAMS)
+}
+
+fn right() -> PlayerInput {
+    PlayerInput {
+        direction: Vec2::X,
+        kick: false,
+    }
+}
+
+fn sequence(message: Option<ClientMessage>) -> u64 {
+    match message {
+        Some(ClientMessage::Input { sequence, .. }) => sequence,
+        message => panic!("expected an input, got {:?}", message),
+    }
+}
+
+#[test]
+fn own_player_moves_before_the_server_answers() {
+    let mut client = client();
+    let start = client.predicted().player_disc(0).position;
+    for _ in 0..10 {
+        client.step(right());
+    }
+    assert!(client.predicted().player_disc(0).position.x > start.x);
+    assert!(client.display_state().player_disc(0).position.x > start.x);
+    assert_eq!(client.server_state().player_disc(0).position, start);
+    assert_eq!(client.pending_inputs(), 10);
+}
+
+#[test]
+fn inputs_are_numbered() {
+    let mut client = client();
+    let sequences: Vec<u64> = (0..3).map(|_| sequence(client.step(right()))).collect();
+    assert_eq!(sequences, [0, 1, 2]);
+}
+
+#[test]
+fn acknowledged_inputs_are_not_predicted_again() {
+    let mut server = server();
+    let mut client = client();
+    // The server applies every input 4 ticks after the client sent it.
+    let latency = 4;
+    let mut sent = Vec::new();
+    for tick in 0..40 {
+        let input = if tick % 10 < 6 {
+            right()
+        } else {
+            PlayerInput::default()
+        };
+        sent.push((sequence(client.step(input)), input));
+        if tick >= latency {
+            let (sequence, input) = sent[tick - latency];
+            server.step(&[input, PlayerInput::default()]);
+            client.receive_snapshot(tick as u64, server.snapshot(), Some(sequence));
+        }
+    }
+
+    // Stepping the server with the inputs still on their way gives the prediction.
+    assert_eq!(client.pending_inputs(), latency);
+    let mut expected = server.clone();
+    for (_, input) in &sent[sent.len() - latency..] {
+        expected.step(&[*input, PlayerInput::default()]);
+    }
+    assert_eq!(client.predicted().snapshot(), expected.snapshot());
+}
+
+#[test]
+fn server_corrects_wrong_prediction() {
+    let mut client = client();
+    for _ in 0..20 {
+        client.step(right());
+    }
+    // The server didn't get any of the inputs and the player stayed.
+    let server = server();
+    client.receive_snapshot(20, server.snapshot(), Some(19));
+    assert_eq!(client.pending_inputs(), 0);
+    assert_eq!(client.predicted().snapshot(), server.snapshot());
+}
+
+#[test]
+fn late_snapshots_are_ignored() {
+    let mut server = server();
+    let mut client = client();
+    let old = server.snapshot();
+    for _ in 0..30 {
+        server.step(&[right(), right()]);
+    }
+    client.receive_snapshot(30, server.snapshot(), None);
+    client.receive_snapshot(1, old, None);
+    assert_eq!(client.server_state().snapshot(), server.snapshot());
+}
+
+#[test]
+fn snapshots_of_another_match_are_dropped() {
+    let mut server = server();
+    let mut client = client();
+    let fresh = client.server_state().snapshot();
+    for _ in 0..10 {
+        server.step(&[right(), right()]);
+    }
+    let mut forged = server.snapshot();
+    forged.players[0].disc = forged.discs.len();
+    client.receive_snapshot(10, forged, None);
+    assert_eq!(client.server_state().snapshot(), fresh);
+    client.step(right());
+
+    client.receive_snapshot(11, server.snapshot(), None);
+    assert_eq!(client.server_state().snapshot(), server.snapshot());
+}
+
+#[test]
+fn other_discs_are_shown_between_snapshots() {
+    let mut server = server();
+    let mut client = client();
+    server.discs[BALL].velocity = Vec2::new(2., 0.);
+    let mut positions = Vec::new();
+    for tick in 1..=30 {
+        server.step(&[PlayerInput::default(), PlayerInput::default()]);
+        positions.push(server.ball().position.x);
+        client.step(PlayerInput::default());
+        client.receive_snapshot(tick, server.snapshot(), None);
+    }
+
+    // The ball is shown where it was a few ticks ago, so it moves smoothly.
+    let shown = client.display_state().ball().position.x;
+    assert!(shown < server.ball().position.x);
+    assert!(shown > positions[positions.len() - 10]);
+}
+
+#[test]
+fn spectators_send_nothing_and_follow_the_server() {
+    let mut server = server();
+    let mut client = ClientMatch::new(None, Stadium::default(), MatchRules::default(), &TEAMS);
+    for tick in 1..=30 {
+        server.step(&[right(), right()]);
+        assert_eq!(client.step(right()), None);
+        client.receive_snapshot(tick, server.snapshot(), None);
+    }
+    assert_eq!(client.pending_inputs(), 0);
+    assert_eq!(client.predicted().snapshot(), server.snapshot());
+    // Every disc is shown a bit in the past, none of them is predicted.
+    let shown = client.display_state();
+    for player in 0..2 {
+        let position = shown.player_disc(player).position.x;
+        assert!(position < server.player_disc(player).position.x);
+    }
+}
+
+#[test]
+fn perfect_link_delivers_right_away() {
+    let now = Instant::now();
+    let mut link = LinkSimulator::new(NetworkConditions::default(), 1);
+    for i in 0..5 {
+        link.push(now, vec![i]);
+    }
+    for i in 0..5 {
+        assert_eq!(link.pop(now), Some(vec![i]));
+    }
+    assert_eq!(link.pop(now), None);
+}
+
+#[test]
+fn latency_holds_datagrams_back() {
+    let now = Instant::now();
+    let conditions = NetworkConditions {
+        latency: Duration::from_millis(100),
+        ..Default::default()
+    };
+    let mut link = LinkSimulator::new(conditions, 1);
+    link.push(now, vec![1]);
+    assert_eq!(link.pop(now + Duration::from_millis(99)), None);
+    assert_eq!(link.pop(now + Duration::from_millis(100)), Some(vec![1]));
+}
+
+#[test]
+fn jitter_reorders_but_delivers_everything() {
+    let now = Instant::now();
+    let conditions = NetworkConditions {
+        latency: Duration::from_millis(50),
+        jitter: Duration::from_millis(40),
+        packet_loss: 0.,
+    };
+    let mut link = LinkSimulator::new(conditions, 7);
+    for i in 0..100u8 {
+        link.push(now + Duration::from_millis(i as u64), vec![i]);
+    }
+    let mut delivered = Vec::new();
+    while let Some(datagram) = link.pop(now + Duration::from_secs(1)) {
+        delivered.push(datagram[0]);
+    }
+    assert_eq!(delivered.len(), 100);
+    assert!(delivered.windows(2).any(|pair| pair[0] > pair[1]));
+}
+
+#[test]
+fn packet_loss_drops_a_part_of_datagrams() {
+    let now = Instant::now();
+    let conditions = NetworkConditions {
+        packet_loss: 0.25,
+        ..Default::default()
+    };
+    let mut link = LinkSimulator::new(conditions, 3);
+    for i in 0..1000u32 {
+        link.push(now, i.to_le_bytes().to_vec());
+    }
+    let mut delivered = 0;
+    while link.pop(now).is_some() {
+        delivered += 1;
+    }
+    assert!((650..850).contains(&delivered), "{}", delivered);
+}
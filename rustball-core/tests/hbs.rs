@@ -0,0 +1,187 @@
+use rustball_core::{Background, CollisionGroups, Stadium, StadiumError, Team, Vec2};
+use rustball_core::{MatchRules, MatchState, BALL};
+use std::fs;
+
+const CLASSIC: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../assets/stadiums/haxball-classic.hbs"
+);
+
+fn classic() -> Stadium {
+    Stadium::from_hbs(&fs::read_to_string(CLASSIC).unwrap()).unwrap()
+}
+
+// The smallest stadium that can be played on, with the given fields added.
+fn stadium_with(extra: &str) -> Result<Stadium, StadiumError> {
+    Stadium::from_hbs(&format!(
+        r#"{{
+            "name": "Test",
+            "width": 300,
+            "height": 150,
+            "goals": [
+                {{ "p0": [-250, 50], "p1": [-250, -50], "team": "red" }},
+                {{ "p0": [250, 50], "p1": [250, -50], "team": "blue" }}
+            ]
+            {}
+        }}"#,
+        extra
+    ))
+}
+
+#[test]
+fn geometry_is_mirrored_vertically() {
+    let stadium = classic();
+
+    assert_eq!(stadium.name, "HaxBall Classic");
+    assert_eq!((stadium.width, stadium.height), (840., 400.));
+    assert_eq!(stadium.walls[0].a, Vec2::new(-370., -170.));
+    assert_eq!(stadium.planes[0].normal, Vec2::new(0., -1.));
+    assert_eq!(stadium.planes[0].distance, -200.);
+    assert_eq!(stadium.goals[0].team, Team::Red);
+    assert_eq!((stadium.goals[0].top, stadium.goals[0].bottom), (64., -64.));
+}
+
+#[test]
+fn traits_provide_missing_properties() {
+    let stadium = classic();
+
+    let ball_area = stadium.walls[0];
+    assert_eq!(ball_area.mask, CollisionGroups::BALL);
+    assert_eq!(ball_area.group, CollisionGroups::WALL);
+    assert!(!ball_area.visible);
+
+    let net = stadium.walls[6];
+    assert_eq!(net.restitution, 0.1);
+    assert!(net.visible);
+
+    assert_eq!(stadium.posts.len(), 4);
+    assert!(stadium.posts.iter().all(|post| post.radius == 8.));
+    assert_eq!(
+        stadium.background,
+        Background::Color(0x71 as f32 / 255., 0x8C as f32 / 255., 0x5A as f32 / 255.)
+    );
+}
+
+#[test]
+fn goal_nets_bulge_out_of_the_pitch() {
+    let stadium = classic();
+    let (left, right) = (stadium.walls[6], stadium.walls[7]);
+
+    let back = left.closest_point(Vec2::new(-450., 0.));
+    assert!(back.x < -390. && back.x > -400.);
+    let back = right.closest_point(Vec2::new(450., 0.));
+    assert!(back.x > 390. && back.x < 400.);
+}
+
+#[test]
+fn fast_ball_stays_in_the_curved_goal_net() {
+    let mut state = MatchState::new(classic(), &[Team::Red], MatchRules::default());
+    state.kickoff = None;
+    state.discs[BALL].position = Vec2::new(340., 0.);
+    state.discs[BALL].velocity = Vec2::new(150., 0.);
+
+    state.step(&[]);
+
+    let back = state.stadium.walls[7].closest_point(Vec2::new(450., 0.));
+    assert!(state.ball().position.x <= back.x - state.ball().radius + 1e-3);
+    assert!(state.ball().velocity.x < 0.);
+}
+
+#[test]
+fn kickoff_barriers_stop_only_the_players() {
+    let stadium = classic();
+    let line = stadium.walls[8];
+    assert_eq!(
+        line.group,
+        CollisionGroups::RED_KICKOFF | CollisionGroups::BLUE_KICKOFF
+    );
+    assert_eq!(line.mask, CollisionGroups::RED | CollisionGroups::BLUE);
+
+    // The circle half stopping blue players while red kicks off is on the blue side.
+    let (blue_half, red_half) = (stadium.walls[10], stadium.walls[11]);
+    assert_eq!(red_half.group, CollisionGroups::RED_KICKOFF);
+    assert!((red_half.closest_point(Vec2::new(100., 0.)) - Vec2::new(75., 0.)).length() < 1e-3);
+    assert_eq!(blue_half.group, CollisionGroups::BLUE_KICKOFF);
+    assert!((blue_half.closest_point(Vec2::new(-100., 0.)) - Vec2::new(-75., 0.)).length() < 1e-3);
+}
+
+#[test]
+fn players_are_lined_up_at_spawn_distance() {
+    let stadium = classic();
+
+    assert_eq!(stadium.spawn_position(Team::Red, 0), Vec2::new(-170., 0.));
+    assert_eq!(stadium.spawn_position(Team::Blue, 0), Vec2::new(170., 0.));
+    assert_eq!(stadium.spawn_position(Team::Red, 1).x, -170.);
+}
+
+#[test]
+fn disc_physics_are_converted() {
+    let stadium = stadium_with(
+        r#",
+        "ballPhysics": { "radius": 6.4, "bCoef": 0.4, "damping": 0.99 },
+        "playerPhysics": {
+            "acceleration": 0.11, "kickingAcceleration": 0.08, "kickStrength": 5.5, "damping": 0.95
+        },
+        "redSpawnPoints": [[-100, 20]]"#,
+    )
+    .unwrap();
+
+    assert_eq!(stadium.physics.ball.radius, 6.4);
+    assert_eq!(stadium.physics.ball.restitution, 0.4);
+    assert_eq!(stadium.physics.ball.inv_mass, 1.);
+    assert_eq!(stadium.physics.player.damping, 0.95);
+    assert_eq!(stadium.physics.player.radius, 15.);
+    assert_eq!(stadium.physics.acceleration, 0.11);
+    assert_eq!(stadium.physics.kicking_acceleration, 0.08);
+    assert_eq!(stadium.physics.kick_strength, 5.5);
+    assert_eq!(stadium.red_spawn_points, vec![Vec2::new(-100., -20.)]);
+}
+
+#[test]
+fn first_disc_can_be_the_ball() {
+    let stadium = stadium_with(
+        r#",
+        "ballPhysics": "disc0",
+        "discs": [
+            { "radius": 9, "invMass": 1.5 },
+            { "pos": [0, 100], "radius": 4, "invMass": 0 }
+        ]"#,
+    )
+    .unwrap();
+
+    assert_eq!(stadium.physics.ball.radius, 9.);
+    assert_eq!(stadium.physics.ball.inv_mass, 1.5);
+    assert_eq!(stadium.posts.len(), 1);
+    assert_eq!(stadium.posts[0].position, Vec2::new(0., -100.));
+}
+
+#[test]
+fn unknown_fields_are_reported() {
+    let error = stadium_with(r#", "planes": [{ "normal": [0, 1], "dist": 0, "foo": 1 }]"#);
+    assert_eq!(
+        error,
+        Err(StadiumError::Parse(
+            "planes[0]: unknown field `foo`".to_string()
+        ))
+    );
+
+    let error = stadium_with(r#", "discs": [{ "pos": [0, 0], "trait": "missing" }]"#);
+    assert!(matches!(error, Err(StadiumError::Parse(_))));
+}
+
+#[test]
+fn unsupported_features_are_reported() {
+    let movable_disc = r#", "discs": [{ "pos": [0, 0], "invMass": 1 }]"#;
+    let joint = r#", "discs": [], "joints": [{ "d0": 0, "d1": 1 }]"#;
+    let bias = r#", "vertexes": [{ "x": 0, "y": 0 }, { "x": 1, "y": 0 }],
+        "segments": [{ "v0": 0, "v1": 1, "bias": 10 }]"#;
+    let gravity = r#", "playerPhysics": { "gravity": [0, 0.1] }"#;
+
+    for extra in [movable_disc, joint, bias, gravity] {
+        assert!(
+            matches!(stadium_with(extra), Err(StadiumError::Unsupported(_))),
+            "{}",
+            extra
+        );
+    }
+}
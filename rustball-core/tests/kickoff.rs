@@ -0,0 +1,134 @@
+use rustball_core::{MatchEvent, MatchRules, MatchState, PlayerInput, Stadium, Team, Vec2, BALL};
+use rustball_core::{CENTER_CIRCLE_RADIUS, GOAL_RESET_DELAY, PITCH_WIDTH, PLAYER_RADIUS};
+
+fn one_on_one() -> MatchState {
+    MatchState::new(
+        Stadium::default(),
+        &[Team::Red, Team::Blue],
+        MatchRules::default(),
+    )
+}
+
+fn run(state: &mut MatchState, inputs: &[PlayerInput], ticks: usize) {
+    for _ in 0..ticks {
+        state.step(inputs);
+    }
+}
+
+const LEFT: PlayerInput = PlayerInput {
+    direction: glam::const_vec2!([-1.0, 0.0]),
+    kick: false,
+};
+
+const RIGHT: PlayerInput = PlayerInput {
+    direction: glam::const_vec2!([1.0, 0.0]),
+    kick: false,
+};
+
+#[test]
+fn spawn_positions_are_mirrored() {
+    let stadium = Stadium::default();
+    for slot in 0..10 {
+        let red = stadium.spawn_position(Team::Red, slot);
+        let blue = stadium.spawn_position(Team::Blue, slot);
+        assert_eq!(red, blue * Vec2::new(-1., 1.));
+        assert!(red.x < 0.);
+    }
+}
+
+#[test]
+fn players_are_placed_by_their_slot() {
+    let state = MatchState::new(
+        Stadium::default(),
+        &[Team::Blue, Team::Red, Team::Blue],
+        MatchRules::default(),
+    );
+
+    assert_eq!(
+        state.player_disc(0).position,
+        state.stadium.spawn_position(Team::Blue, 0)
+    );
+    assert_eq!(
+        state.player_disc(1).position,
+        state.stadium.spawn_position(Team::Red, 0)
+    );
+    assert_eq!(
+        state.player_disc(2).position,
+        state.stadium.spawn_position(Team::Blue, 1)
+    );
+}
+
+#[test]
+fn defending_team_stays_out_of_center_circle() {
+    let mut state = one_on_one();
+    assert_eq!(state.kickoff, Some(Team::Red));
+
+    // Blue player runs towards the ball.
+    run(&mut state, &[PlayerInput::default(), LEFT], 300);
+
+    let blue = state.player_disc(1).position;
+    assert!(blue.length() >= CENTER_CIRCLE_RADIUS + PLAYER_RADIUS - 1e-3);
+    assert_eq!(state.kickoff, Some(Team::Red));
+}
+
+#[test]
+fn kicking_team_stays_in_center_circle_on_opponents_side() {
+    let mut state = one_on_one();
+    let red = state.players[0].disc;
+    // Skip the ball, so the kickoff isn't taken.
+    state.discs[red].position = Vec2::new(-200., 100.);
+
+    run(&mut state, &[RIGHT, PlayerInput::default()], 600);
+
+    let red = state.player_disc(0).position;
+    assert!(red.x > 0.);
+    assert!(red.length() <= CENTER_CIRCLE_RADIUS + 1e-3);
+}
+
+#[test]
+fn kickoff_barriers_dont_stop_the_ball() {
+    let mut state = one_on_one();
+    state.discs[BALL].position = Vec2::new(-100., 300.);
+    state.discs[BALL].velocity = Vec2::new(5., 0.);
+
+    run(&mut state, &[], 60);
+
+    assert!(state.ball().position.x > 0.);
+    assert_eq!(state.kickoff, Some(Team::Red));
+}
+
+#[test]
+fn players_cross_the_center_line_after_the_kickoff() {
+    let mut state = one_on_one();
+    state.kickoff = None;
+    let red = state.players[0].disc;
+    state.discs[red].position = Vec2::new(-100., 300.);
+
+    run(&mut state, &[RIGHT, PlayerInput::default()], 120);
+
+    assert!(state.player_disc(0).position.x > 0.);
+}
+
+#[test]
+fn touching_the_ball_takes_the_kickoff() {
+    let mut state = one_on_one();
+
+    run(&mut state, &[RIGHT, PlayerInput::default()], 120);
+
+    assert_eq!(state.kickoff, None);
+    assert_eq!(state.last_touch, Some(0));
+}
+
+#[test]
+fn conceding_team_kicks_off() {
+    let mut state = one_on_one();
+    state.kickoff = None;
+    state.discs[BALL].position = Vec2::new(PITCH_WIDTH / 2. - 1., 0.);
+    state.discs[BALL].velocity = Vec2::new(3., 0.);
+
+    assert_eq!(state.step(&[]), vec![MatchEvent::Goal(Team::Red)]);
+    assert_eq!(state.kickoff, None);
+
+    run(&mut state, &[], GOAL_RESET_DELAY as usize);
+    assert_eq!(state.kickoff, Some(Team::Blue));
+}
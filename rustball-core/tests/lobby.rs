@@ -0,0 +1,70 @@
+use rustball_core::{Lobby, MatchRules, Stadium, Team};
+
+// Lobby with a red player, a spectator and a blue player, joined in this order.
+fn lobby() -> Lobby {
+    let mut lobby = Lobby::new(Stadium::default(), MatchRules::default());
+    lobby.join(1, "Player 1", Some(Team::Red));
+    lobby.join(2, "Player 2", None);
+    lobby.join(3, "Player 3", Some(Team::Blue));
+    lobby
+}
+
+fn names(lobby: &Lobby, team: Option<Team>) -> Vec<&str> {
+    lobby
+        .team_members(team)
+        .map(|member| member.name.as_str())
+        .collect()
+}
+
+#[test]
+fn first_member_is_the_admin() {
+    let lobby = lobby();
+    assert_eq!(lobby.admin, Some(1));
+    assert!(lobby.is_admin(1));
+    assert!(!lobby.is_admin(2));
+}
+
+#[test]
+fn admin_passes_to_the_next_member() {
+    let mut lobby = lobby();
+    lobby.leave(1);
+    assert_eq!(lobby.admin, Some(2));
+    lobby.leave(2);
+    lobby.leave(3);
+    assert_eq!(lobby.admin, None);
+    assert!(lobby.members.is_empty());
+}
+
+#[test]
+fn members_move_between_teams_and_spectators() {
+    let mut lobby = lobby();
+    assert_eq!(names(&lobby, Some(Team::Red)), ["Player 1"]);
+    assert_eq!(names(&lobby, None), ["Player 2"]);
+    assert_eq!(names(&lobby, Some(Team::Blue)), ["Player 3"]);
+
+    assert!(lobby.move_member(2, Some(Team::Red)));
+    assert!(lobby.move_member(1, None));
+    assert!(!lobby.move_member(4, None));
+    assert_eq!(names(&lobby, Some(Team::Red)), ["Player 2"]);
+    assert_eq!(names(&lobby, None), ["Player 1"]);
+}
+
+#[test]
+fn spectators_get_no_player() {
+    let lobby = lobby();
+    let players: Vec<u32> = lobby.players().iter().map(|member| member.id).collect();
+    assert_eq!(players, [1, 3]);
+    let state = lobby.new_match();
+    assert_eq!(state.players.len(), 2);
+    assert_eq!(state.players[0].team, Team::Red);
+    assert_eq!(state.players[1].team, Team::Blue);
+}
+
+#[test]
+fn match_needs_a_player() {
+    let mut lobby = lobby();
+    assert!(lobby.can_start());
+    lobby.move_member(1, None);
+    lobby.move_member(3, None);
+    assert!(!lobby.can_start());
+}
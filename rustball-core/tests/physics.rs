@@ -0,0 +1,468 @@
+use rustball_core::physics;
+use rustball_core::{CollisionGroups, Post, Segment, Vec2, BALL};
+use rustball_core::{Disc, MatchEvent, MatchRules, MatchState, PlayerInput, Stadium, Team};
+use rustball_core::{ACCELERATION, KICK_COOLDOWN, KICK_REACH, MAX_SPEED};
+use rustball_core::{BALL_DAMPING, BALL_RADIUS, PITCH_HEIGHT, PITCH_WIDTH, PLAYER_RADIUS};
+use rustball_core::{CORNER_UP_HEIGHT, GOAL_DEPTH, GOAL_RESET_DELAY};
+
+fn run(state: &mut MatchState, inputs: &[PlayerInput], ticks: usize) {
+    for _ in 0..ticks {
+        state.step(inputs);
+    }
+}
+
+fn one_on_one() -> MatchState {
+    MatchState::new(
+        Stadium::default(),
+        &[Team::Red, Team::Blue],
+        MatchRules::default(),
+    )
+}
+
+#[test]
+fn colliding_discs_are_separated() {
+    let mut disc1 = Disc::player(Vec2::new(0., 0.));
+    let mut disc2 = Disc::player(Vec2::new(30., 0.));
+    disc1.velocity = Vec2::new(1., 0.);
+
+    physics::collide(&mut disc1, &mut disc2);
+
+    let distance = disc1.position.distance(disc2.position);
+    assert!((distance - 2. * PLAYER_RADIUS).abs() < 1e-4);
+    assert!(disc1.velocity.x < 1.);
+    assert!(disc2.velocity.x > 0.);
+}
+
+#[test]
+fn separating_discs_keep_their_velocities() {
+    let mut disc1 = Disc::player(Vec2::new(0., 0.));
+    let mut disc2 = Disc::player(Vec2::new(30., 0.));
+    disc1.velocity = Vec2::new(-1., 0.);
+
+    physics::collide(&mut disc1, &mut disc2);
+
+    assert_eq!(disc1.velocity, Vec2::new(-1., 0.));
+    assert_eq!(disc2.velocity, Vec2::ZERO);
+}
+
+#[test]
+fn lighter_disc_is_pushed_further() {
+    let mut player = Disc::player(Vec2::new(0., 0.));
+    let mut ball = Disc::ball(Vec2::new(PLAYER_RADIUS, 0.));
+    player.velocity = Vec2::new(1., 0.);
+    ball.velocity = Vec2::new(-1., 0.);
+
+    physics::collide(&mut player, &mut ball);
+
+    assert!(ball.position.x - PLAYER_RADIUS > -player.position.x);
+    assert!(ball.velocity.x.abs() > player.velocity.x.abs());
+}
+
+#[test]
+fn static_discs_are_not_moved() {
+    let mut post = Disc::new(Vec2::new(0., 0.), 10., 0., 1.);
+    let mut ball = Disc::ball(Vec2::new(15., 0.));
+    ball.velocity = Vec2::new(-2., 0.);
+
+    physics::collide(&mut post, &mut ball);
+
+    assert_eq!(post.position, Vec2::ZERO);
+    assert_eq!(post.velocity, Vec2::ZERO);
+    assert_eq!(ball.velocity, Vec2::new(2., 0.));
+}
+
+#[test]
+fn discs_outside_of_each_others_masks_dont_collide() {
+    let player = Disc::player(Vec2::new(0., 0.));
+    let mut ghost = Disc::player(Vec2::new(30., 0.));
+    assert!(player.collides_with(&ghost));
+
+    ghost.mask = CollisionGroups::NONE;
+    assert!(!player.collides_with(&ghost));
+    assert!(!ghost.collides_with(&player));
+}
+
+#[test]
+fn diagonal_ball_slows_down_along_its_direction() {
+    let mut state = one_on_one();
+    state.kickoff = None;
+    state.discs[BALL].position = Vec2::new(0., -200.);
+    state.discs[BALL].velocity = Vec2::new(3., 1.);
+
+    run(&mut state, &[], 30);
+
+    let velocity = state.ball().velocity;
+    assert!(velocity.perp_dot(Vec2::new(3., 1.)).abs() < 1e-4);
+    let expected = Vec2::new(3., 1.).length() * BALL_DAMPING.powi(30);
+    assert!((velocity.length() - expected).abs() < 1e-4);
+}
+
+#[test]
+fn running_diagonally_is_not_faster() {
+    let mut diagonal = one_on_one();
+    let mut straight = one_on_one();
+    let up_right = PlayerInput::from_keys(true, false, false, true, false);
+    let up = PlayerInput::from_keys(true, false, false, false, false);
+
+    run(&mut diagonal, &[up_right, PlayerInput::default()], 60);
+    run(&mut straight, &[up, PlayerInput::default()], 60);
+
+    let velocity = diagonal.player_disc(0).velocity;
+    let straight_speed = straight.player_disc(0).velocity.length();
+    assert!((velocity.length() - straight_speed).abs() < 1e-4);
+    assert!(velocity.length() <= MAX_SPEED);
+    assert!((velocity.x - velocity.y).abs() < 1e-4);
+}
+
+#[test]
+fn diagonal_keys_accelerate_as_much_as_straight_ones() {
+    let diagonal = PlayerInput::from_keys(false, true, true, false, false);
+    let straight = PlayerInput::from_keys(false, true, false, false, false);
+    let constants = Stadium::default().physics;
+
+    for input in [diagonal, straight] {
+        let mut velocity = Vec2::ZERO;
+        physics::accelerate(&mut velocity, &input, &constants);
+        assert!((velocity.length() - ACCELERATION).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn opposite_keys_cancel_out() {
+    let input = PlayerInput::from_keys(true, true, true, false, false);
+
+    assert_eq!(input.direction, Vec2::new(-1., 0.));
+}
+
+#[test]
+fn analog_direction_accelerates_as_far_as_tilted() {
+    let constants = Stadium::default().physics;
+    let half_tilted = PlayerInput {
+        direction: Vec2::new(0.3, 0.4),
+        kick: false,
+    };
+    let too_long = PlayerInput {
+        direction: Vec2::new(3., 4.),
+        kick: false,
+    };
+
+    let mut velocity = Vec2::ZERO;
+    physics::accelerate(&mut velocity, &half_tilted, &constants);
+    assert!((velocity - Vec2::new(0.3, 0.4) * ACCELERATION).length() < 1e-6);
+
+    let mut velocity = Vec2::ZERO;
+    physics::accelerate(&mut velocity, &too_long, &constants);
+    assert!((velocity - Vec2::new(0.6, 0.8) * ACCELERATION).length() < 1e-6);
+}
+
+#[test]
+fn players_stop_without_input() {
+    let mut state = one_on_one();
+    let red = state.players[0].disc;
+    state.discs[red].velocity = Vec2::new(MAX_SPEED, 0.);
+
+    run(&mut state, &[], 300);
+
+    assert!(state.player_disc(0).velocity.length() < 1e-2);
+}
+
+#[test]
+fn ball_bounces_off_the_top_edge() {
+    let mut state = one_on_one();
+    state.kickoff = None;
+    state.discs[BALL].position = Vec2::new(0., PITCH_HEIGHT / 2. - BALL_RADIUS - 1.);
+    state.discs[BALL].velocity = Vec2::new(1., 2.);
+
+    state.step(&[]);
+
+    let ball = state.ball();
+    assert!(ball.position.y <= PITCH_HEIGHT / 2. - BALL_RADIUS);
+    assert!(ball.velocity.x > 0.);
+    assert!(ball.velocity.y < 0.);
+}
+
+#[test]
+fn ball_bounces_off_a_post_along_the_normal() {
+    let post = Post {
+        position: Vec2::ZERO,
+        radius: 10.,
+        restitution: 1.,
+        group: CollisionGroups::WALL,
+        mask: CollisionGroups::ALL,
+    };
+    // The ball hits the post at 45 degrees.
+    let mut ball = Disc::ball(Vec2::new(-14., 14.));
+    ball.velocity = Vec2::new(2., 0.);
+
+    physics::post_collision(&mut ball, &post);
+
+    assert!(ball.position.length() >= 20. - 1e-4);
+    assert!((ball.velocity - Vec2::new(0., 2.)).length() < 1e-4);
+}
+
+#[test]
+fn only_the_ball_enters_the_goal() {
+    let mut state = one_on_one();
+    state.kickoff = None;
+    let red = state.players[0].disc;
+    state.discs[red].position = Vec2::new(PITCH_WIDTH / 2. - PLAYER_RADIUS - 1., 0.);
+    let right = PlayerInput::from_keys(false, false, false, true, false);
+
+    run(&mut state, &[right, PlayerInput::default()], 60);
+
+    assert!(state.player_disc(0).position.x <= PITCH_WIDTH / 2. - PLAYER_RADIUS + 1e-3);
+}
+
+#[test]
+fn ball_only_wall_lets_the_players_through() {
+    let mut stadium = Stadium::default();
+    stadium.walls.push(Segment {
+        mask: CollisionGroups::BALL,
+        ..Segment::new(Vec2::new(-300., 384.), Vec2::new(-300., -384.))
+    });
+    let mut state = MatchState::new(stadium, &[Team::Red], MatchRules::default());
+    state.kickoff = None;
+    let red = state.players[0].disc;
+    state.discs[red].position = Vec2::new(-250., 200.);
+    state.discs[BALL].position = Vec2::new(-250., -200.);
+    state.discs[BALL].velocity = Vec2::new(-3., 0.);
+    let left = PlayerInput::from_keys(false, false, true, false, false);
+
+    run(&mut state, &[left], 60);
+
+    assert!(state.player_disc(0).position.x < -300.);
+    assert!(state.ball().position.x >= -300. + BALL_RADIUS - 1e-3);
+}
+
+#[test]
+fn ball_stops_in_the_goal_net() {
+    let mut state = one_on_one();
+    state.kickoff = None;
+    state.discs[BALL].position = Vec2::new(PITCH_WIDTH / 2. - 20., 50.);
+    state.discs[BALL].velocity = Vec2::new(8., 1.);
+
+    let mut events = Vec::new();
+    for _ in 0..GOAL_RESET_DELAY {
+        events.extend(state.step(&[]));
+    }
+
+    assert_eq!(events, vec![MatchEvent::Goal(Team::Red)]);
+    let ball = state.ball();
+    assert!(ball.position.x > PITCH_WIDTH / 2.);
+    assert!(ball.position.x <= PITCH_WIDTH / 2. + GOAL_DEPTH - BALL_RADIUS + 1e-3);
+    assert!(ball.position.y.abs() < CORNER_UP_HEIGHT);
+}
+
+#[test]
+fn fast_ball_bounces_off_every_post() {
+    for post in Stadium::default().posts {
+        let outward = Vec2::new(post.position.x.signum(), 0.);
+        for angle in [-45f32, 0., 45.] {
+            let (sin, cos) = angle.to_radians().sin_cos();
+            let direction = Vec2::new(outward.x * cos, sin);
+            let mut state = one_on_one();
+            state.kickoff = None;
+            state.discs[BALL].position = post.position - direction * 110.;
+            state.discs[BALL].velocity = direction * 200.;
+
+            state.step(&[]);
+
+            let ball = state.ball();
+            let delta = ball.position - post.position;
+            assert!(delta.length() >= post.radius + BALL_RADIUS - 1e-3);
+            assert!(delta.dot(direction) < 0., "ball passed through {:?}", post);
+            assert!(ball.velocity.dot(direction) < 0.);
+        }
+    }
+}
+
+#[test]
+fn fast_ball_doesnt_pass_through_a_player() {
+    let mut state = one_on_one();
+    state.kickoff = None;
+    let red = state.players[0].disc;
+    state.discs[red].position = Vec2::new(100., 0.);
+    state.discs[BALL].position = Vec2::new(-100., 0.);
+    state.discs[BALL].velocity = Vec2::new(250., 0.);
+
+    state.step(&[]);
+
+    assert!(state.ball().position.x < state.player_disc(0).position.x);
+    assert_eq!(state.last_touch, Some(0));
+}
+
+#[test]
+fn fast_ball_stays_in_the_goal_net() {
+    let mut state = one_on_one();
+    state.kickoff = None;
+    state.discs[BALL].position = Vec2::new(PITCH_WIDTH / 2. - 30., 0.);
+    state.discs[BALL].velocity = Vec2::new(100., 0.);
+
+    state.step(&[]);
+
+    let ball = state.ball();
+    assert!(ball.position.x <= PITCH_WIDTH / 2. + GOAL_DEPTH - BALL_RADIUS + 1e-3);
+    assert!(ball.velocity.x < 0.);
+}
+
+#[test]
+fn kick_shoots_the_ball_away_from_the_player() {
+    let mut state = one_on_one();
+    let red = state.players[0].disc;
+    state.discs[red].position = Vec2::new(-PLAYER_RADIUS - BALL_RADIUS + 1., 0.);
+
+    let kick = PlayerInput {
+        kick: true,
+        ..Default::default()
+    };
+    state.step(&[kick, PlayerInput::default()]);
+
+    assert!(state.ball().velocity.x > 0.);
+}
+
+const KICK: PlayerInput = PlayerInput {
+    direction: Vec2::ZERO,
+    kick: true,
+};
+
+// Match with the red player standing still next to the ball, at the given distance between them.
+fn ready_to_kick(gap: f32) -> MatchState {
+    let mut state = one_on_one();
+    let red = state.players[0].disc;
+    state.discs[red].position = Vec2::new(-PLAYER_RADIUS - BALL_RADIUS - gap, 0.);
+    state
+}
+
+#[test]
+fn holding_kick_shoots_only_once() {
+    let mut state = ready_to_kick(0.);
+
+    run(&mut state, &[KICK, PlayerInput::default()], 60);
+
+    assert_eq!(state.players[0].stats.kicks, 1);
+}
+
+#[test]
+fn kick_reaches_slightly_beyond_the_player() {
+    let mut state = ready_to_kick(KICK_REACH - 1.);
+    state.step(&[KICK, PlayerInput::default()]);
+    assert!(state.ball().velocity.x > 0.);
+
+    let mut state = ready_to_kick(KICK_REACH + 1.);
+    state.step(&[KICK, PlayerInput::default()]);
+    assert_eq!(state.ball().velocity, Vec2::ZERO);
+    assert!(state.players[0].kick_armed);
+}
+
+#[test]
+fn kicks_wait_for_the_cooldown() {
+    let mut state = ready_to_kick(0.);
+    let mut kicks = Vec::new();
+    for tick in 0..KICK_COOLDOWN + 2 {
+        // Press the button every other tick, with the ball put back in place.
+        let input = if tick % 2 == 0 {
+            KICK
+        } else {
+            PlayerInput::default()
+        };
+        state.discs[BALL].position = Vec2::ZERO;
+        state.discs[BALL].velocity = Vec2::ZERO;
+        state.step(&[input, PlayerInput::default()]);
+        kicks.push(state.players[0].stats.kicks);
+    }
+
+    assert_eq!(kicks[KICK_COOLDOWN as usize - 1], 1);
+    assert_eq!(kicks[KICK_COOLDOWN as usize + 1], 2);
+}
+
+#[test]
+fn holding_kick_slows_the_player_down() {
+    let mut running = one_on_one();
+    let mut kicking = one_on_one();
+    let right = PlayerInput::from_keys(false, false, false, true, false);
+    let right_kicking = PlayerInput {
+        kick: true,
+        ..right
+    };
+
+    run(&mut running, &[right, PlayerInput::default()], 10);
+    run(&mut kicking, &[right_kicking, PlayerInput::default()], 10);
+
+    assert!(kicking.player_disc(0).velocity.x < running.player_disc(0).velocity.x);
+}
+
+#[test]
+fn goal_updates_score_and_resets_positions() {
+    let mut state = one_on_one();
+    state.discs[BALL].position = Vec2::new(PITCH_WIDTH / 2. - 1., 0.);
+    state.discs[BALL].velocity = Vec2::new(3., 0.);
+
+    assert_eq!(state.step(&[]), vec![MatchEvent::Goal(Team::Red)]);
+    assert_eq!(state.score.red, 1);
+    assert_ne!(state.ball().position, Vec2::ZERO);
+
+    let mut events = Vec::new();
+    for _ in 0..GOAL_RESET_DELAY {
+        events.extend(state.step(&[]));
+    }
+
+    assert_eq!(events, vec![MatchEvent::Kickoff(Team::Blue)]);
+    assert_eq!(state.ball().position, Vec2::ZERO);
+    assert_eq!(
+        state.player_disc(0).position,
+        state.players[0].initial_position(&state.stadium)
+    );
+}
+
+#[test]
+fn reaching_score_limit_wins_the_match() {
+    let mut state = one_on_one();
+    state.score.blue = state.rules.score_limit.unwrap() - 1;
+    state.discs[BALL].position = Vec2::new(-PITCH_WIDTH / 2. + 1., 0.);
+    state.discs[BALL].velocity = Vec2::new(-3., 0.);
+
+    let events = state.step(&[]);
+
+    assert_eq!(
+        events,
+        vec![MatchEvent::Goal(Team::Blue), MatchEvent::Won(Team::Blue)]
+    );
+    assert_eq!(state.winner, Some(Team::Blue));
+    assert!(state.step(&[]).is_empty());
+}
+
+#[test]
+fn goals_are_credited_to_the_last_player_touching_the_ball() {
+    let mut state = one_on_one();
+    let blue = state.players[1].disc;
+    // Blue player pushes the ball into its own goal.
+    state.discs[BALL].position = Vec2::new(PITCH_WIDTH / 2. - 20., 0.);
+    state.discs[blue].position = Vec2::new(PITCH_WIDTH / 2. - 20. - PLAYER_RADIUS, 0.);
+    state.discs[blue].velocity = Vec2::new(3., 0.);
+
+    let mut events = Vec::new();
+    while events.is_empty() {
+        events = state.step(&[]);
+    }
+
+    assert_eq!(events, vec![MatchEvent::Goal(Team::Red)]);
+    assert_eq!(state.players[1].stats.own_goals, 1);
+    assert_eq!(state.players[0].stats.goals, 0);
+}
+
+#[test]
+fn simulation_is_deterministic() {
+    let mut state1 = one_on_one();
+    let mut state2 = one_on_one();
+    let inputs = [
+        PlayerInput::from_keys(false, false, false, true, true),
+        PlayerInput::from_keys(true, false, true, false, false),
+    ];
+
+    for _ in 0..600 {
+        state1.step(&inputs);
+        state2.step(&inputs);
+    }
+
+    assert_eq!(state1, state2);
+}
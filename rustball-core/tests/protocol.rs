@@ -0,0 +1,248 @@
+use rustball_core::protocol::{self, ClientMessage, PeerMessage, ServerMessage, PROTOCOL_VERSION};
+use rustball_core::protocol::{Location, MasterMessage, MasterReply, RoomInfo, RoomListing};
+use rustball_core::{
+    Bot, BotRole, Difficulty, Lobby, MatchRules, MatchState, PlayerInput, Stadium,
+};
+use rustball_core::{Team, Vec2, TICKS_PER_SECOND};
+use std::fs;
+
+const STADIUMS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/stadiums");
+
+fn new_match() -> MatchState {
+    MatchState::new(
+        Stadium::default(),
+        &[Team::Red, Team::Blue],
+        MatchRules::default(),
+    )
+}
+
+// Plays a few seconds of a match of two bots.
+fn play(state: &mut MatchState, seconds: u32) {
+    let mut red = Bot::new(0, Difficulty::Hard, BotRole::Attacker, 1);
+    let mut blue = Bot::new(1, Difficulty::Normal, BotRole::Attacker, 2);
+    for _ in 0..seconds * TICKS_PER_SECOND {
+        let inputs = [red.input(state), blue.input(state)];
+        state.step(&inputs);
+    }
+}
+
+#[test]
+fn client_messages_round_trip() {
+    let messages = [
+        ClientMessage::Join {
+            version: PROTOCOL_VERSION,
+            password: Some("secret".to_string()),
+        },
+        ClientMessage::Input {
+            sequence: 42,
+            input: PlayerInput {
+                direction: Vec2::new(0.6, -0.8),
+                kick: true,
+            },
+        },
+        ClientMessage::Leave,
+        ClientMessage::MoveMember {
+            member: 3,
+            team: Some(Team::Blue),
+        },
+        ClientMessage::ChangeMatch {
+            stadium: Box::new(Stadium::default()),
+            rules: MatchRules::default(),
+        },
+        ClientMessage::StartMatch,
+    ];
+    for message in messages {
+        assert_eq!(protocol::decode(&protocol::encode(&message)), Some(message));
+    }
+}
+
+#[test]
+fn lobby_with_every_stadium_fits_a_datagram() {
+    for entry in fs::read_dir(STADIUMS_DIR).unwrap() {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(&path).unwrap();
+        let stadium = if path.extension().is_some_and(|ext| ext == "hbs") {
+            Stadium::from_hbs(&source).unwrap()
+        } else {
+            Stadium::from_ron(&source).unwrap()
+        };
+        let mut lobby = Lobby::new(stadium, MatchRules::default());
+        for id in 0..30 {
+            lobby.join(id, format!("Player {}", id + 1), None);
+        }
+        let message = ServerMessage::Welcome { member: 29, lobby };
+        let bytes = protocol::encode(&message);
+        assert!(bytes.len() <= protocol::MAX_DATAGRAM_SIZE, "{:?}", path);
+        assert_eq!(protocol::decode(&bytes), Some(message), "{:?}", path);
+    }
+}
+
+#[test]
+fn peer_inputs_fit_a_datagram() {
+    let input = PlayerInput {
+        direction: Vec2::new(0.6, -0.8),
+        kick: true,
+    };
+    let message = PeerMessage::Inputs {
+        start: 1000,
+        inputs: vec![input; 64],
+        acknowledged: 990,
+        checksum: Some((985, u64::MAX)),
+    };
+    let bytes = protocol::encode(&message);
+    assert!(bytes.len() <= protocol::MAX_DATAGRAM_SIZE);
+    assert_eq!(protocol::decode(&bytes), Some(message));
+}
+
+#[test]
+fn master_messages_round_trip() {
+    let room = RoomInfo {
+        name: "Room".to_string(),
+        players: 3,
+        max_players: 12,
+        password: true,
+        stadium: "Classic".to_string(),
+        location: Location {
+            code: "PL".to_string(),
+            latitude: 52.23,
+            longitude: 21.01,
+        },
+    };
+    let messages = [
+        MasterMessage::Heartbeat {
+            version: PROTOCOL_VERSION,
+            room: room.clone(),
+        },
+        MasterMessage::Unregister,
+        MasterMessage::ListRooms {
+            version: PROTOCOL_VERSION,
+            cookie: [7; 8],
+        },
+    ];
+    for message in messages {
+        assert_eq!(protocol::decode(&protocol::encode(&message)), Some(message));
+    }
+    let reply = MasterReply::Rooms(vec![RoomListing {
+        address: "[::1]:5195".parse().unwrap(),
+        room,
+    }]);
+    assert_eq!(protocol::decode(&protocol::encode(&reply)), Some(reply));
+    let reply = MasterReply::Cookie([0xff; 8]);
+    assert_eq!(protocol::decode(&protocol::encode(&reply)), Some(reply));
+}
+
+#[test]
+fn longest_room_list_fits_a_datagram() {
+    // The longest characters, and one to keep the cut off their boundaries.
+    let long = format!("a{}", "😀".repeat(1000));
+    let room = RoomInfo {
+        name: long.clone(),
+        stadium: long.clone(),
+        location: Location {
+            code: long,
+            latitude: f32::NAN,
+            longitude: 1000.,
+        },
+        ..Default::default()
+    }
+    .sanitized();
+    assert_eq!(room.name, format!("a{}", "😀".repeat(9)));
+    assert!(room.location.code.len() <= protocol::MAX_ROOM_NAME);
+    assert_eq!(
+        (room.location.latitude, room.location.longitude),
+        (0., 180.)
+    );
+
+    let listing = RoomListing {
+        address: "[ffff::ffff]:65535".parse().unwrap(),
+        room,
+    };
+    let reply = MasterReply::Rooms(vec![listing; protocol::MAX_LISTED_ROOMS]);
+    assert!(protocol::encode(&reply).len() <= protocol::MAX_DATAGRAM_SIZE);
+}
+
+#[test]
+fn applied_snapshot_continues_the_same_match() {
+    let mut server = new_match();
+    play(&mut server, 5);
+    let message = ServerMessage::Snapshot {
+        sequence: 300,
+        snapshot: server.snapshot(),
+        last_input: Some(7),
+    };
+    let snapshot = match protocol::decode(&protocol::encode(&message)) {
+        Some(ServerMessage::Snapshot { snapshot, .. }) => snapshot,
+        other => panic!("expected a snapshot, got {:?}", other),
+    };
+
+    let mut client = new_match();
+    assert!(client.apply_snapshot(&snapshot));
+    assert_eq!(client.snapshot(), snapshot);
+
+    play(&mut server, 5);
+    play(&mut client, 5);
+    assert_eq!(client.snapshot(), server.snapshot());
+}
+
+#[test]
+fn forged_snapshots_are_not_applied() {
+    let mut server = new_match();
+    play(&mut server, 1);
+    let snapshot = server.snapshot();
+    let mut forged = Vec::new();
+    let mut out_of_range = snapshot.clone();
+    out_of_range.players[1].disc = 1000;
+    forged.push(out_of_range);
+    let mut ball = snapshot.clone();
+    ball.players[0].disc = 0;
+    forged.push(ball);
+    let mut fewer_discs = snapshot.clone();
+    fewer_discs.discs.pop();
+    forged.push(fewer_discs);
+    let mut more_players = snapshot.clone();
+    more_players.players.push(snapshot.players[0].clone());
+    forged.push(more_players);
+    let mut last_touch = snapshot;
+    last_touch.last_touch = Some(2);
+    forged.push(last_touch);
+
+    let mut client = new_match();
+    let before = client.snapshot();
+    for snapshot in &forged {
+        assert!(!client.apply_snapshot(snapshot));
+        assert_eq!(client.snapshot(), before);
+    }
+    // The match goes on as if nothing came.
+    play(&mut client, 1);
+}
+
+#[test]
+fn garbage_is_not_a_message() {
+    assert_eq!(protocol::decode::<ClientMessage>(&[]), None);
+    assert_eq!(protocol::decode::<ClientMessage>(&[0xff; 16]), None);
+    // A forged length must not make the decoder allocate gigabytes.
+    let mut bytes = vec![1];
+    bytes.extend([0xfc, 0xff, 0xff, 0xff, 0x7f]);
+    assert_eq!(protocol::decode::<ServerMessage>(&bytes), None);
+}
+
+#[test]
+fn sanitized_input_is_a_valid_direction() {
+    let input = |direction| PlayerInput {
+        direction,
+        kick: false,
+    };
+    assert_eq!(
+        input(Vec2::new(f32::NAN, 1.)).sanitized().direction,
+        Vec2::ZERO
+    );
+    assert_eq!(
+        input(Vec2::new(f32::INFINITY, 0.)).sanitized().direction,
+        Vec2::ZERO
+    );
+    assert!((input(Vec2::new(30., 40.)).sanitized().direction.length() - 1.).abs() < 1e-6);
+    assert_eq!(
+        input(Vec2::new(0.3, 0.4)).sanitized(),
+        input(Vec2::new(0.3, 0.4))
+    );
+}
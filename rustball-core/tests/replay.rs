@@ -0,0 +1,119 @@
+use rustball_core::{Bot, BotRole, Difficulty, MatchRules, MatchState, PlayerInput, Replay};
+use rustball_core::{ReplayError, ReplayPlayer, Stadium, Team, TICKS_PER_SECOND};
+use std::fs;
+
+const STADIUMS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/stadiums");
+
+// Records a match of two bots, returning it with the final state.
+fn record(seconds: u32) -> (Replay, MatchState) {
+    let mut state = MatchState::new(
+        Stadium::default(),
+        &[Team::Red, Team::Blue],
+        MatchRules::default(),
+    );
+    let mut replay = Replay::new(&state);
+    let mut red = Bot::new(0, Difficulty::Hard, BotRole::Attacker, 1);
+    let mut blue = Bot::new(1, Difficulty::Easy, BotRole::Defender, 2);
+    for _ in 0..seconds * TICKS_PER_SECOND {
+        let inputs = [red.input(&state), blue.input(&state)];
+        replay.record(&inputs);
+        state.step(&inputs);
+    }
+    (replay, state)
+}
+
+fn write(replay: &Replay) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    replay.write_to(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn replay_plays_the_same_match() {
+    let (replay, state) = record(30);
+    let mut player = ReplayPlayer::new(replay);
+
+    while !player.is_finished() {
+        player.step();
+    }
+
+    assert_eq!(player.state(), &state);
+}
+
+#[test]
+fn replay_survives_the_file_format() {
+    let (replay, _) = record(10);
+
+    let read = Replay::read_from(&mut write(&replay).as_slice()).unwrap();
+
+    assert_eq!(read, replay);
+}
+
+#[test]
+fn unchanged_directions_take_a_byte() {
+    let state = MatchState::new(
+        Stadium::default(),
+        &[Team::Red, Team::Blue],
+        MatchRules::default(),
+    );
+    let mut replay = Replay::new(&state);
+    let right = PlayerInput::from_keys(false, false, false, true, false);
+    let empty_size = write(&replay).len();
+    for _ in 0..1000 {
+        replay.record(&[right, PlayerInput::default()]);
+    }
+
+    // Two bytes per tick and the direction of the red player once.
+    assert_eq!(write(&replay).len(), empty_size + 2 * 1000 + 8);
+}
+
+#[test]
+fn seeking_matches_playing() {
+    let (replay, _) = record(30);
+    let mut played = ReplayPlayer::new(replay.clone());
+    let mut seeking = ReplayPlayer::new(replay);
+
+    for tick in [1500, 200, 1799, 0, 900, 5000] {
+        seeking.seek(tick);
+        played.seek(0);
+        while played.tick() < tick.min(played.replay().len()) {
+            played.step();
+        }
+        assert_eq!(seeking.tick(), played.tick());
+        assert_eq!(seeking.state(), played.state());
+    }
+}
+
+#[test]
+fn other_files_are_rejected() {
+    let result = Replay::read_from(&mut b"not a replay".as_slice());
+
+    assert!(matches!(result, Err(ReplayError::Format(_))));
+}
+
+#[test]
+fn truncated_replays_are_rejected() {
+    let (replay, _) = record(1);
+    let bytes = write(&replay);
+
+    let result = Replay::read_from(&mut &bytes[..bytes.len() - 1]);
+
+    assert!(matches!(result, Err(ReplayError::Io(_))));
+}
+
+#[test]
+fn replay_keeps_stadiums_from_files() {
+    let read = |file| fs::read_to_string(format!("{}/{}", STADIUMS_DIR, file)).unwrap();
+    let stadiums = [
+        Stadium::from_ron(&read("concrete.stadium.ron")).unwrap(),
+        Stadium::from_hbs(&read("haxball-classic.hbs")).unwrap(),
+    ];
+    for stadium in stadiums {
+        let state = MatchState::new(stadium, &[Team::Red], MatchRules::default());
+        let replay = Replay::new(&state);
+
+        let read = Replay::read_from(&mut write(&replay).as_slice()).unwrap();
+
+        assert_eq!(read.stadium, state.stadium);
+    }
+}
@@ -0,0 +1,188 @@
+use rustball_core::protocol::PeerMessage;
+use rustball_core::{Bot, BotRole, Difficulty, MatchRules, MatchState, PlayerInput};
+use rustball_core::{RollbackSession, Stadium, Vec2, MAX_ROLLBACK_TICKS, PEER_TEAMS};
+use std::collections::VecDeque;
+
+fn session(local: usize) -> RollbackSession {
+    RollbackSession::new(local, Stadium::default(), MatchRules::default())
+}
+
+// Plays a match of two bots over a connection delaying every message by the given ticks,
+// losing the messages the filter says so. Returns both peers and the inputs of the players.
+fn play(
+    ticks: usize,
+    delay: usize,
+    lost: impl Fn(usize) -> bool,
+) -> (RollbackSession, RollbackSession, Vec<[PlayerInput; 2]>) {
+    let mut peers = [session(0), session(1)];
+    let mut bots = [
+        Bot::new(0, Difficulty::Hard, BotRole::Attacker, 1),
+        Bot::new(1, Difficulty::Normal, BotRole::Attacker, 2),
+    ];
+    let mut in_flight: [VecDeque<(usize, PeerMessage)>; 2] = Default::default();
+    let mut inputs: [Vec<PlayerInput>; 2] = Default::default();
+    for tick in 0..ticks {
+        for peer in 0..2 {
+            while in_flight[peer]
+                .front()
+                .is_some_and(|(arrival, _)| *arrival <= tick)
+            {
+                let (_, message) = in_flight[peer].pop_front().unwrap();
+                peers[peer].receive(&message);
+            }
+            let input = bots[peer].input(peers[peer].state());
+            if peers[peer].advance(input).is_some() {
+                inputs[peer].push(input);
+            }
+            if !lost(tick * 2 + peer) {
+                in_flight[1 - peer].push_back((tick + delay, peers[peer].message()));
+            }
+        }
+    }
+    // Let the last messages arrive.
+    for _ in 0..delay + 1 {
+        for peer in 0..2 {
+            let message = peers[1 - peer].message();
+            peers[peer].receive(&message);
+        }
+    }
+    let [red, blue] = peers;
+    let inputs = inputs[0]
+        .iter()
+        .zip(&inputs[1])
+        .map(|(r, b)| [*r, *b])
+        .collect();
+    (red, blue, inputs)
+}
+
+// The match simulated directly with the inputs both peers ended up with.
+fn simulate(inputs: &[[PlayerInput; 2]]) -> MatchState {
+    let mut state = MatchState::new(Stadium::default(), &PEER_TEAMS, MatchRules::default());
+    for inputs in inputs {
+        state.step(inputs);
+    }
+    state
+}
+
+fn right() -> PlayerInput {
+    PlayerInput {
+        direction: Vec2::X,
+        kick: false,
+    }
+}
+
+#[test]
+fn peers_agree_on_the_match() {
+    let (red, blue, inputs) = play(600, 4, |_| false);
+    assert_eq!(red.desync(), None);
+    assert_eq!(blue.desync(), None);
+    let ticks = red.confirmed_tick().min(blue.confirmed_tick()) as usize;
+    assert!(ticks > 500);
+    let expected = simulate(&inputs[..ticks]);
+    if red.confirmed_tick() as usize == ticks {
+        assert_eq!(red.confirmed().checksum(), expected.checksum());
+    }
+    if blue.confirmed_tick() as usize == ticks {
+        assert_eq!(blue.confirmed().checksum(), expected.checksum());
+    }
+}
+
+#[test]
+fn wrong_predictions_are_rolled_back() {
+    let (red, blue, _) = play(600, 4, |_| false);
+    assert!(red.rollbacks() > 0);
+    assert!(blue.rollbacks() > 0);
+    // With all the inputs known, the shown match is the confirmed one.
+    if red.tick() == red.confirmed_tick() {
+        assert_eq!(red.state().checksum(), red.confirmed().checksum());
+    }
+}
+
+#[test]
+fn lost_messages_are_made_up_for() {
+    // Every third message gets lost, the next ones repeat the inputs.
+    let (red, blue, inputs) = play(600, 3, |message| message % 3 == 0);
+    assert_eq!(red.desync(), None);
+    assert_eq!(blue.desync(), None);
+    let ticks = red.confirmed_tick().min(blue.confirmed_tick()) as usize;
+    assert!(ticks > 500);
+    assert_eq!(
+        simulate(&inputs[..red.confirmed_tick() as usize]).checksum(),
+        red.confirmed().checksum()
+    );
+}
+
+#[test]
+fn session_waits_for_the_other_peer() {
+    let mut red = session(0);
+    for _ in 0..MAX_ROLLBACK_TICKS {
+        assert!(red.advance(right()).is_some());
+    }
+    assert!(red.is_waiting());
+    assert!(red.advance(right()).is_none());
+    assert_eq!(red.tick(), MAX_ROLLBACK_TICKS);
+
+    let mut blue = session(1);
+    blue.advance(PlayerInput::default());
+    red.receive(&blue.message());
+    assert!(!red.is_waiting());
+    assert_eq!(red.confirmed_tick(), 1);
+}
+
+#[test]
+fn prediction_repeats_the_last_remote_input() {
+    let mut red = session(0);
+    let mut blue = session(1);
+    blue.advance(right());
+    red.receive(&blue.message());
+    for _ in 0..5 {
+        red.advance(PlayerInput::default());
+    }
+    // Blue kept running right, as predicted, so nothing was rolled back.
+    for _ in 0..4 {
+        blue.advance(right());
+    }
+    red.receive(&blue.message());
+    assert_eq!(red.rollbacks(), 0);
+    assert_eq!(red.confirmed_tick(), 5);
+    assert_eq!(red.state().checksum(), red.confirmed().checksum());
+}
+
+#[test]
+fn different_checksum_is_a_desync() {
+    let mut red = session(0);
+    let mut blue = session(1);
+    for _ in 0..5 {
+        red.advance(right());
+        blue.advance(right());
+    }
+    red.receive(&blue.message());
+    blue.receive(&red.message());
+    assert_eq!(red.desync(), None);
+
+    // A checksum which doesn't match the local one of the same tick.
+    let mut red = session(0);
+    red.advance(right());
+    red.receive(&PeerMessage::Inputs {
+        start: 0,
+        inputs: vec![right()],
+        acknowledged: 1,
+        checksum: Some((1, 42)),
+    });
+    assert_eq!(red.desync(), Some(1));
+}
+
+#[test]
+fn checksum_covers_the_discs_and_the_score() {
+    let state = MatchState::new(Stadium::default(), &PEER_TEAMS, MatchRules::default());
+    let mut moved = state.clone();
+    moved.discs[1].position.x += 0.001;
+    let mut faster = state.clone();
+    faster.discs[0].velocity.y = 1.;
+    let mut scored = state.clone();
+    scored.score.blue = 1;
+    for other in [moved, faster, scored] {
+        assert_ne!(state.checksum(), other.checksum());
+    }
+    assert_eq!(state.checksum(), state.clone().checksum());
+}
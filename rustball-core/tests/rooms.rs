@@ -0,0 +1,115 @@
+use rustball_core::protocol::{Location, RoomInfo, RoomListing};
+use rustball_core::{RoomFilter, RoomSort};
+
+fn location(code: &str, latitude: f32, longitude: f32) -> Location {
+    Location {
+        code: code.to_string(),
+        latitude,
+        longitude,
+    }
+}
+
+fn warsaw() -> Location {
+    location("PL", 52.23, 21.01)
+}
+
+fn listing(port: u16, name: &str, players: u32, password: bool, location: Location) -> RoomListing {
+    RoomListing {
+        address: ([127, 0, 0, 1], port).into(),
+        room: RoomInfo {
+            name: name.to_string(),
+            players,
+            max_players: 4,
+            password,
+            stadium: "Classic".to_string(),
+            location,
+        },
+    }
+}
+
+// Nearly full room in Berlin, a full locked one in Warsaw and an empty one in New York.
+fn rooms() -> Vec<RoomListing> {
+    vec![
+        listing(5001, "berlin", 3, false, location("DE", 52.52, 13.40)),
+        listing(5002, "Warsaw", 4, true, warsaw()),
+        listing(5003, "New York", 0, false, location("US", 40.71, -74.01)),
+    ]
+}
+
+fn names(filter: RoomFilter, rooms: &[RoomListing]) -> Vec<&str> {
+    filter
+        .apply(rooms, &warsaw())
+        .iter()
+        .map(|listing| listing.room.name.as_str())
+        .collect()
+}
+
+#[test]
+fn distance_is_measured_along_the_earth() {
+    let berlin = location("DE", 52.52, 13.40);
+    assert!((warsaw().distance(&berlin) - 518.).abs() < 10.);
+    assert!((berlin.distance(&warsaw()) - warsaw().distance(&berlin)).abs() < 0.01);
+    assert_eq!(warsaw().distance(&warsaw()), 0.);
+}
+
+#[test]
+fn rooms_are_sorted() {
+    let rooms = rooms();
+    let filter = |sort| RoomFilter {
+        sort,
+        ..Default::default()
+    };
+    assert_eq!(
+        names(filter(RoomSort::Players), &rooms),
+        ["Warsaw", "berlin", "New York"]
+    );
+    assert_eq!(
+        names(filter(RoomSort::Name), &rooms),
+        ["berlin", "New York", "Warsaw"]
+    );
+    assert_eq!(
+        names(filter(RoomSort::Distance), &rooms),
+        ["Warsaw", "berlin", "New York"]
+    );
+}
+
+#[test]
+fn ties_keep_their_order() {
+    let rooms = vec![
+        listing(5002, "Room", 1, false, warsaw()),
+        listing(5001, "Room", 1, false, warsaw()),
+    ];
+    let ports: Vec<u16> = RoomFilter::default()
+        .apply(&rooms, &warsaw())
+        .iter()
+        .map(|listing| listing.address.port())
+        .collect();
+    assert_eq!(ports, [5001, 5002]);
+}
+
+#[test]
+fn full_and_locked_rooms_are_hidden() {
+    let rooms = rooms();
+    let hide_full = RoomFilter {
+        hide_full: true,
+        ..Default::default()
+    };
+    assert_eq!(names(hide_full, &rooms), ["berlin", "New York"]);
+
+    let mut rooms = rooms;
+    rooms[1].room.players = 1;
+    let hide_locked = RoomFilter {
+        hide_locked: true,
+        ..Default::default()
+    };
+    assert_eq!(names(hide_full, &rooms), ["berlin", "Warsaw", "New York"]);
+    assert_eq!(names(hide_locked, &rooms), ["berlin", "New York"]);
+}
+
+#[test]
+fn sort_cycles_through_every_order() {
+    let sorts = [RoomSort::Players, RoomSort::Name, RoomSort::Distance];
+    for (i, sort) in sorts.iter().enumerate() {
+        assert_eq!(sort.next(), sorts[(i + 1) % sorts.len()]);
+    }
+}
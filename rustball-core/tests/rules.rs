@@ -0,0 +1,88 @@
+use rustball_core::{MatchEvent, MatchRules, MatchState, Stadium, Team, Vec2, BALL};
+use rustball_core::{PITCH_WIDTH, TICKS_PER_SECOND};
+
+fn one_on_one(rules: MatchRules) -> MatchState {
+    MatchState::new(Stadium::default(), &[Team::Red, Team::Blue], rules)
+}
+
+// Puts the ball just before the goal line of the given team's opponent
+// and waits until everybody is back at the kickoff positions.
+fn score(state: &mut MatchState, team: Team) -> Vec<MatchEvent> {
+    let direction = match team {
+        Team::Red => 1.,
+        Team::Blue => -1.,
+    };
+    state.discs[BALL].position = Vec2::new(direction * (PITCH_WIDTH / 2. - 1.), 0.);
+    state.discs[BALL].velocity = Vec2::new(direction * 3., 0.);
+    let events = state.step(&[]);
+    while state.restart.is_some() && state.winner.is_none() {
+        state.step(&[]);
+    }
+    events
+}
+
+// Runs the match without any input until something happens.
+fn run_until_time_limit(state: &mut MatchState) -> Vec<MatchEvent> {
+    let ticks = state.rules.time_limit.unwrap() as u64 * 60 * TICKS_PER_SECOND as u64;
+    for _ in 0..ticks {
+        let events = state.step(&[]);
+        if !events.is_empty() {
+            return events;
+        }
+    }
+    panic!("nothing happened within the time limit");
+}
+
+#[test]
+fn unlimited_score_never_ends_the_match() {
+    let mut state = one_on_one(MatchRules {
+        score_limit: None,
+        time_limit: None,
+    });
+
+    for _ in 0..20 {
+        assert_eq!(
+            score(&mut state, Team::Red),
+            vec![MatchEvent::Goal(Team::Red)]
+        );
+    }
+    assert_eq!(state.score.red, 20);
+}
+
+#[test]
+fn leading_team_wins_when_time_is_up() {
+    let mut state = one_on_one(MatchRules {
+        score_limit: None,
+        time_limit: Some(1),
+    });
+    score(&mut state, Team::Blue);
+
+    let events = run_until_time_limit(&mut state);
+
+    assert_eq!(events, vec![MatchEvent::Won(Team::Blue)]);
+    assert_eq!(state.winner, Some(Team::Blue));
+}
+
+#[test]
+fn tie_leads_to_sudden_death_overtime() {
+    let mut state = one_on_one(MatchRules {
+        score_limit: Some(3),
+        time_limit: Some(1),
+    });
+
+    let events = run_until_time_limit(&mut state);
+    assert_eq!(events, vec![MatchEvent::Overtime]);
+    assert!(state.overtime);
+
+    // The clock keeps running in the overtime.
+    for _ in 0..TICKS_PER_SECOND {
+        assert!(state.step(&[]).is_empty());
+    }
+
+    let events = score(&mut state, Team::Red);
+    assert_eq!(
+        events,
+        vec![MatchEvent::Goal(Team::Red), MatchEvent::Won(Team::Red)]
+    );
+    assert_eq!(state.winner, Some(Team::Red));
+}
@@ -0,0 +1,117 @@
+use rustball_core::physics;
+use rustball_core::{CollisionGroups, Team, Vec2, BALL};
+use rustball_core::{Disc, MatchEvent, MatchRules, MatchState, Segment, Stadium, StadiumError};
+use std::fs;
+
+const STADIUMS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/stadiums");
+
+#[test]
+fn bundled_stadiums_are_valid() {
+    let mut count = 0;
+    for entry in fs::read_dir(STADIUMS_DIR).unwrap() {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(&path).unwrap();
+        let stadium = if path.extension().is_some_and(|extension| extension == "hbs") {
+            Stadium::from_hbs(&source)
+        } else {
+            Stadium::from_ron(&source)
+        };
+        if let Err(err) = stadium {
+            panic!("{}: {}", path.display(), err);
+        }
+        count += 1;
+    }
+    assert!(count >= 4);
+}
+
+#[test]
+fn classic_stadium_matches_the_default_one() {
+    let source = fs::read_to_string(format!("{}/classic.stadium.ron", STADIUMS_DIR)).unwrap();
+    assert_eq!(Stadium::from_ron(&source).unwrap(), Stadium::default());
+}
+
+#[test]
+fn stadium_without_goals_is_rejected() {
+    let source = r#"(
+        name: "Empty",
+        width: 800.0,
+        height: 600.0,
+        goals: [],
+        posts: [],
+        red_spawn_points: [(-100.0, 0.0)],
+        background: Color(0.2, 0.5, 0.2),
+    )"#;
+
+    assert!(matches!(
+        Stadium::from_ron(source),
+        Err(StadiumError::Invalid(_))
+    ));
+    assert!(matches!(
+        Stadium::from_ron("(name: 1)"),
+        Err(StadiumError::Parse(_))
+    ));
+}
+
+#[test]
+fn blue_spawn_points_can_be_given() {
+    let stadium = Stadium {
+        blue_spawn_points: vec![Vec2::new(100., 50.)],
+        ..Default::default()
+    };
+
+    assert_eq!(stadium.spawn_position(Team::Blue, 0), Vec2::new(100., 50.));
+    assert_eq!(stadium.spawn_position(Team::Blue, 1), Vec2::new(160., 50.));
+}
+
+#[test]
+fn goals_are_scored_on_a_smaller_stadium() {
+    let mut stadium = Stadium {
+        width: 600.,
+        ..Default::default()
+    };
+    for goal in stadium.goals.iter_mut() {
+        goal.x = goal.x.signum() * 300.;
+    }
+    let mut state = MatchState::new(stadium, &[Team::Red, Team::Blue], MatchRules::default());
+    state.kickoff = None;
+    state.discs[BALL].position = Vec2::new(-299., 0.);
+    state.discs[BALL].velocity = Vec2::new(-3., 0.);
+
+    assert_eq!(state.step(&[]), vec![MatchEvent::Goal(Team::Blue)]);
+}
+
+#[test]
+fn damping_is_given_by_the_stadium() {
+    let mut stadium = Stadium::default();
+    stadium.physics.ball.damping = 0.5;
+    let mut state = MatchState::new(stadium, &[Team::Red], MatchRules::default());
+    state.kickoff = None;
+    state.discs[BALL].velocity = Vec2::new(4., 0.);
+
+    state.step(&[]);
+
+    assert_eq!(state.ball().velocity, Vec2::new(2., 0.));
+}
+
+#[test]
+fn disc_bounces_off_a_wall() {
+    let wall = Segment::new(Vec2::new(-100., 0.), Vec2::new(100., 0.));
+    let mut ball = Disc::ball(Vec2::new(0., 5.));
+    ball.velocity = Vec2::new(1., -2.);
+
+    physics::wall_collision(&mut ball, &wall);
+
+    assert_eq!(ball.position, Vec2::new(0., 10.));
+    assert_eq!(ball.velocity, Vec2::new(1., 2.));
+}
+
+#[test]
+fn collision_groups_are_given_by_names() {
+    let source = fs::read_to_string(format!("{}/classic.stadium.ron", STADIUMS_DIR)).unwrap();
+    let stadium = Stadium::from_ron(&source).unwrap();
+
+    let goal_line = stadium.walls[3];
+    assert_eq!(goal_line.mask, CollisionGroups::RED | CollisionGroups::BLUE);
+    assert_eq!(goal_line.group, CollisionGroups::WALL);
+    assert!(Stadium::from_ron(&source.replace("\"blue\"", "\"green\"")).is_err());
+}
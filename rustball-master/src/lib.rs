@@ -0,0 +1,195 @@
+// Master server keeping the list of the rooms, the game servers which want to be found.
+// The servers send heartbeats with their room over UDP, the clients ask for the list of them.
+
+use bevy::prelude::*;
+use rustball_core::protocol::{self, MasterMessage, MasterReply, RoomInfo, RoomListing};
+use rustball_core::protocol::{MAX_DATAGRAM_SIZE, MAX_LISTED_ROOMS, PROTOCOL_VERSION};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+
+// Runs the `Master` resource, a tick per update.
+pub struct MasterPlugin;
+
+// The master server has nothing to simulate, it only checks for the requests this often.
+pub const TICKS_PER_SECOND: u64 = 10;
+// Rooms without a heartbeat for this long leave the list, their server is gone.
+pub const ROOM_TIMEOUT_TICKS: u64 = 15 * TICKS_PER_SECOND;
+// Cookies change this often, the ones of the previous period are still taken.
+pub const COOKIE_TICKS: u64 = 60 * TICKS_PER_SECOND;
+
+// Room on the list.
+struct Room {
+    address: SocketAddr,
+    info: RoomInfo,
+    // Tick of the last heartbeat.
+    last_heard: u64,
+}
+
+pub struct Master {
+    socket: UdpSocket,
+    // In the order they registered.
+    rooms: Vec<Room>,
+    ticks: u64,
+    // Random key of the cookies, nobody else can make them.
+    secret: RandomState,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+enum MasterSystem {
+    Receive,
+}
+
+impl Master {
+    pub fn bind(address: SocketAddr) -> io::Result<Master> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+        Ok(Master {
+            socket,
+            rooms: Vec::new(),
+            ticks: 0,
+            secret: RandomState::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn room_count(&self) -> usize {
+        self.rooms.len()
+    }
+
+    // The rooms as sent to the clients.
+    pub fn listings(&self) -> Vec<RoomListing> {
+        self.rooms
+            .iter()
+            .map(|room| RoomListing {
+                address: room.address,
+                room: room.info.clone(),
+            })
+            .collect()
+    }
+
+    // Cookie of the address for the period of the tick.
+    fn cookie(&self, address: SocketAddr, ticks: u64) -> [u8; 8] {
+        let period = ticks / COOKIE_TICKS;
+        self.secret.hash_one((address, period)).to_le_bytes()
+    }
+
+    // Whether the cookie was given to the address lately, so the request really came from there.
+    fn knows(&self, address: SocketAddr, cookie: [u8; 8]) -> bool {
+        cookie == self.cookie(address, self.ticks)
+            || cookie == self.cookie(address, self.ticks.saturating_sub(COOKIE_TICKS))
+    }
+
+    fn send(&self, address: SocketAddr, reply: &MasterReply) {
+        if let Err(err) = self.socket.send_to(&protocol::encode(reply), address) {
+            warn!("Can't send to {}: {}", address, err);
+        }
+    }
+
+    fn reject_version(&self, address: SocketAddr, version: u32) {
+        let reason = format!(
+            "The master server speaks protocol {}, you {}",
+            PROTOCOL_VERSION, version
+        );
+        self.send(address, &MasterReply::Rejected(reason));
+    }
+
+    fn handle(&mut self, address: SocketAddr, message: MasterMessage) {
+        match message {
+            MasterMessage::Heartbeat { version, .. } if version != PROTOCOL_VERSION => {
+                self.reject_version(address, version);
+            }
+            MasterMessage::Heartbeat { room, .. } => {
+                let info = room.sanitized();
+                match self.rooms.iter().position(|room| room.address == address) {
+                    Some(room) => {
+                        self.rooms[room].info = info;
+                        self.rooms[room].last_heard = self.ticks;
+                    }
+                    None if self.rooms.len() >= MAX_LISTED_ROOMS => {
+                        let reason = "The room list is full".to_string();
+                        self.send(address, &MasterReply::Rejected(reason));
+                    }
+                    None => {
+                        info!("{} registered room {:?}", address, info.name);
+                        self.rooms.push(Room {
+                            address,
+                            info,
+                            last_heard: self.ticks,
+                        });
+                    }
+                }
+            }
+            MasterMessage::Unregister => {
+                if let Some(room) = self.rooms.iter().position(|room| room.address == address) {
+                    info!("{} unregistered", address);
+                    self.rooms.remove(room);
+                }
+            }
+            MasterMessage::ListRooms { cookie, .. } if !self.knows(address, cookie) => {
+                let cookie = self.cookie(address, self.ticks);
+                self.send(address, &MasterReply::Cookie(cookie));
+            }
+            MasterMessage::ListRooms { version, .. } if version != PROTOCOL_VERSION => {
+                self.reject_version(address, version);
+            }
+            MasterMessage::ListRooms { .. } => {
+                self.send(address, &MasterReply::Rooms(self.listings()));
+            }
+        }
+    }
+}
+
+impl Plugin for MasterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(init_master_system)
+            .add_system(receive_system.label(MasterSystem::Receive))
+            .add_system(expire_system.after(MasterSystem::Receive));
+    }
+}
+
+fn init_master_system(master: Res<Master>) {
+    match master.local_addr() {
+        Ok(address) => info!("Listening on {}", address),
+        Err(err) => warn!("Can't get the master server's address: {}", err),
+    }
+}
+
+// Handles all the datagrams which came since the last tick.
+fn receive_system(mut master: ResMut<Master>) {
+    let mut buffer = vec![0; MAX_DATAGRAM_SIZE];
+    loop {
+        match master.socket.recv_from(&mut buffer) {
+            Ok((length, address)) => {
+                // Anything else than a message is ignored, it may come from anybody.
+                if let Some(message) = protocol::decode(&buffer[..length]) {
+                    master.handle(address, message);
+                }
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+            // A reply sent earlier didn't reach its client, which isn't this one's problem.
+            Err(err) if err.kind() == ErrorKind::ConnectionReset => {}
+            Err(err) => {
+                warn!("Can't receive: {}", err);
+                break;
+            }
+        }
+    }
+}
+
+// Drops the rooms whose heartbeats stopped.
+fn expire_system(mut master: ResMut<Master>) {
+    master.ticks += 1;
+    let ticks = master.ticks;
+    master.rooms.retain(|room| {
+        let alive = ticks - room.last_heard <= ROOM_TIMEOUT_TICKS;
+        if !alive {
+            info!("{} timed out", room.address);
+        }
+        alive
+    });
+}
@@ -0,0 +1,55 @@
+use bevy::app::ScheduleRunnerSettings;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use rustball_core::protocol::DEFAULT_MASTER_PORT;
+use rustball_master::{Master, MasterPlugin, TICKS_PER_SECOND};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use std::{env, process};
+
+const USAGE: &str = "Usage: rustball-master [--port PORT]";
+
+fn main() {
+    let address = match parse_args(env::args().skip(1)) {
+        Ok(address) => address,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+    let master = match Master::bind(address) {
+        Ok(master) => master,
+        Err(err) => {
+            eprintln!("Can't start the master server: {}", err);
+            process::exit(1);
+        }
+    };
+
+    App::new()
+        .insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(
+            1. / TICKS_PER_SECOND as f64,
+        )))
+        .add_plugins(MinimalPlugins)
+        .add_plugin(LogPlugin)
+        .insert_resource(master)
+        .add_plugin(MasterPlugin)
+        .run();
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<SocketAddr, String> {
+    let mut address = SocketAddr::from((Ipv4Addr::UNSPECIFIED, DEFAULT_MASTER_PORT));
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing the value of {}", arg))?;
+        match arg.as_str() {
+            "--port" => address.set_port(
+                value
+                    .parse()
+                    .map_err(|_| format!("Invalid value of {}: {}", arg, value))?,
+            ),
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+    Ok(address)
+}
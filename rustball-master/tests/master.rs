@@ -0,0 +1,232 @@
+use bevy::prelude::*;
+use rustball_core::protocol::{self, ClientMessage, Location, MasterMessage, MasterReply};
+use rustball_core::protocol::{RoomListing, ServerMessage, PROTOCOL_VERSION};
+use rustball_master::{Master, MasterPlugin, ROOM_TIMEOUT_TICKS};
+use rustball_server::{Server, ServerConfig, ServerPlugin};
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::Duration;
+
+// Master server on a free localhost port, stepped by hand.
+fn master() -> (App, SocketAddr) {
+    let master = Master::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let address = master.local_addr().unwrap();
+    let mut app = App::new();
+    app.insert_resource(master).add_plugin(MasterPlugin);
+    app.update();
+    (app, address)
+}
+
+// Game server registering its room on the master server, its first update sends the heartbeat.
+fn room(master: SocketAddr, name: &str, password: Option<&str>) -> (App, SocketAddr) {
+    let server = Server::bind(ServerConfig {
+        address: "127.0.0.1:0".parse().unwrap(),
+        max_clients: 4,
+        name: name.to_string(),
+        password: password.map(str::to_string),
+        master: Some(master),
+        location: Location {
+            code: "PL".to_string(),
+            latitude: 52.23,
+            longitude: 21.01,
+        },
+        ..Default::default()
+    })
+    .unwrap();
+    let address = server.local_addr().unwrap();
+    let mut app = App::new();
+    app.insert_resource(server).add_plugin(ServerPlugin);
+    app.update();
+    (app, address)
+}
+
+fn client() -> UdpSocket {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_nonblocking(true).unwrap();
+    socket
+}
+
+// Asks the master server for the rooms, gives the size of the request.
+fn list(socket: &UdpSocket, master: SocketAddr, version: u32, cookie: [u8; 8]) -> usize {
+    let message = MasterMessage::ListRooms { version, cookie };
+    socket.send_to(&protocol::encode(&message), master).unwrap()
+}
+
+fn join(socket: &UdpSocket, room: SocketAddr, password: Option<&str>) {
+    let message = ClientMessage::Join {
+        version: PROTOCOL_VERSION,
+        password: password.map(str::to_string),
+    };
+    socket.send_to(&protocol::encode(&message), room).unwrap();
+}
+
+// Steps the master server until the socket gets its reply, given with its size.
+fn master_reply(master: &mut App, socket: &UdpSocket) -> (MasterReply, usize) {
+    for _ in 0..100 {
+        master.update();
+        let mut buffer = vec![0; protocol::MAX_DATAGRAM_SIZE];
+        if let Ok(length) = socket.recv(&mut buffer) {
+            let reply = protocol::decode(&buffer[..length]).expect("invalid reply");
+            return (reply, length);
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    panic!("no reply from the master server");
+}
+
+// Cookie the master server gives to the socket.
+fn cookie(master: &mut App, address: SocketAddr, socket: &UdpSocket) -> [u8; 8] {
+    list(socket, address, PROTOCOL_VERSION, [0; 8]);
+    match master_reply(master, socket).0 {
+        MasterReply::Cookie(cookie) => cookie,
+        reply => panic!("expected a cookie, got {:?}", reply),
+    }
+}
+
+// Steps the game server until the socket gets a message other than a lobby update.
+fn server_reply(room: &mut App, socket: &UdpSocket) -> ServerMessage {
+    for _ in 0..100 {
+        room.update();
+        let mut buffer = vec![0; protocol::MAX_DATAGRAM_SIZE];
+        while let Ok(length) = socket.recv(&mut buffer) {
+            match protocol::decode(&buffer[..length]).expect("invalid message") {
+                ServerMessage::Lobby(_) => {}
+                message => return message,
+            }
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    panic!("no reply from the game server");
+}
+
+// Asks the master server for the rooms until the list is accepted, the heartbeats take a moment.
+fn rooms(
+    master: &mut App,
+    address: SocketAddr,
+    accept: impl Fn(&[RoomListing]) -> bool,
+) -> Vec<RoomListing> {
+    let socket = client();
+    let cookie = cookie(master, address, &socket);
+    for _ in 0..20 {
+        list(&socket, address, PROTOCOL_VERSION, cookie);
+        match master_reply(master, &socket).0 {
+            MasterReply::Rooms(rooms) if accept(&rooms) => return rooms,
+            MasterReply::Rooms(_) => thread::sleep(Duration::from_millis(5)),
+            reply => panic!("expected the rooms, got {:?}", reply),
+        }
+    }
+    panic!("the master server doesn't list the expected rooms");
+}
+
+fn count(count: usize) -> impl Fn(&[RoomListing]) -> bool {
+    move |rooms| rooms.len() == count
+}
+
+fn room_count(master: &App) -> usize {
+    master.world.resource::<Master>().room_count()
+}
+
+#[test]
+fn rooms_of_the_servers_are_listed() {
+    let (mut master, address) = master();
+    let (_first, first) = room(address, "First", None);
+    let (_second, second) = room(address, "Second", Some("secret"));
+
+    let mut rooms = rooms(&mut master, address, count(2));
+    rooms.sort_by_key(|listing| listing.room.name.clone());
+    assert_eq!(rooms[0].address, first);
+    assert_eq!(rooms[1].address, second);
+    assert!(!rooms[0].room.password);
+    assert!(rooms[1].room.password);
+    assert_eq!(rooms[0].room.players, 0);
+    assert_eq!(rooms[0].room.max_players, 4);
+    assert_eq!(rooms[0].room.location.code, "PL");
+}
+
+#[test]
+fn heartbeats_follow_the_players() {
+    let (mut master, address) = master();
+    let (mut room, room_address) = room(address, "Room", None);
+    rooms(&mut master, address, count(1));
+
+    let player = client();
+    join(&player, room_address, None);
+    server_reply(&mut room, &player);
+    rooms(&mut master, address, |rooms| rooms[0].room.players == 1);
+}
+
+#[test]
+fn client_joins_a_listed_room() {
+    let (mut master, address) = master();
+    let (_open, _) = room(address, "Open", None);
+    let (mut locked, _) = room(address, "Locked", Some("secret"));
+
+    let listing = rooms(&mut master, address, count(2))
+        .into_iter()
+        .find(|listing| listing.room.name == "Locked")
+        .unwrap();
+    let player = client();
+    join(&player, listing.address, Some("secret"));
+    assert!(matches!(
+        server_reply(&mut locked, &player),
+        ServerMessage::Welcome { .. }
+    ));
+}
+
+#[test]
+fn silent_rooms_leave_the_list() {
+    let (mut master, address) = master();
+    let (_room, _) = room(address, "Room", None);
+    rooms(&mut master, address, count(1));
+
+    for _ in 0..=ROOM_TIMEOUT_TICKS {
+        master.update();
+    }
+    assert_eq!(room_count(&master), 0);
+}
+
+#[test]
+fn stopped_server_unregisters() {
+    let (mut master, address) = master();
+    let (room, _) = room(address, "Room", None);
+    rooms(&mut master, address, count(1));
+
+    drop(room);
+    rooms(&mut master, address, count(0));
+}
+
+#[test]
+fn other_protocol_version_is_rejected() {
+    let (mut master, address) = master();
+    let socket = client();
+    let cookie = cookie(&mut master, address, &socket);
+    list(&socket, address, PROTOCOL_VERSION + 1, cookie);
+    assert!(matches!(
+        master_reply(&mut master, &socket).0,
+        MasterReply::Rejected(_)
+    ));
+}
+
+#[test]
+fn rooms_are_sent_only_with_the_cookie_of_the_address() {
+    let (mut master, address) = master();
+    let (_room, _) = room(address, "Room", None);
+    rooms(&mut master, address, count(1));
+
+    // Without the cookie, or with one of another address, the reply is no bigger than the request.
+    let socket = client();
+    let other = cookie(&mut master, address, &client());
+    for cookie in [[0; 8], other] {
+        let request = list(&socket, address, PROTOCOL_VERSION, cookie);
+        let (reply, length) = master_reply(&mut master, &socket);
+        assert!(matches!(reply, MasterReply::Cookie(_)));
+        assert!(length <= request);
+    }
+
+    let cookie = cookie(&mut master, address, &socket);
+    list(&socket, address, PROTOCOL_VERSION, cookie);
+    match master_reply(&mut master, &socket).0 {
+        MasterReply::Rooms(rooms) => assert_eq!(rooms.len(), 1),
+        reply => panic!("expected the rooms, got {:?}", reply),
+    }
+}
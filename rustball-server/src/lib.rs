@@ -0,0 +1,498 @@
+// Dedicated server of online matches, simulating the match without a window.
+// Clients send the inputs of their players over UDP and get the authoritative state after every tick.
+// With a master server the room is listed for the clients browsing the rooms.
+
+use bevy::prelude::*;
+use rustball_core::protocol::{self, ClientMessage, MasterMessage, MasterReply, ServerMessage};
+use rustball_core::protocol::{
+    Location, RoomInfo, DEFAULT_PORT, MAX_DATAGRAM_SIZE, PROTOCOL_VERSION,
+};
+use rustball_core::{Lobby, MatchRules, MatchState, PlayerInput, Stadium, TICKS_PER_SECOND};
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind};
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+
+// Runs the lobby and the match of the `Server` resource, a tick per update.
+pub struct ServerPlugin;
+
+// Clients not heard from for this long leave the lobby.
+pub const CLIENT_TIMEOUT_TICKS: u64 = 5 * TICKS_PER_SECOND as u64;
+// Ticks between the end of a match and the return to the lobby.
+pub const LOBBY_RETURN_DELAY: u64 = 5 * TICKS_PER_SECOND as u64;
+// Ticks between the lobby updates sent to every client, they may have lost the last one.
+pub const LOBBY_INTERVAL: u64 = TICKS_PER_SECOND as u64 / 4;
+// Ticks between the heartbeats sent to the master server, also sent when the room changes.
+pub const HEARTBEAT_INTERVAL: u64 = 5 * TICKS_PER_SECOND as u64;
+// Inputs waiting for their tick at most, older ones are dropped so the delay doesn't keep growing.
+const MAX_QUEUED_INPUTS: usize = TICKS_PER_SECOND as usize / 4;
+
+pub struct ServerConfig {
+    pub address: SocketAddr,
+    // Number of clients in the lobby at most, players and spectators.
+    pub max_clients: usize,
+    // Stadium and rules of the lobby until its admin changes them.
+    pub stadium: Stadium,
+    pub rules: MatchRules,
+    // Name of the room in the room list.
+    pub name: String,
+    // Password the clients need to join, `None` lets in anybody.
+    pub password: Option<String>,
+    // Master server to list the room on, `None` keeps it unlisted.
+    pub master: Option<SocketAddr>,
+    pub location: Location,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            address: SocketAddr::from((Ipv4Addr::UNSPECIFIED, DEFAULT_PORT)),
+            max_clients: 12,
+            stadium: Stadium::default(),
+            rules: MatchRules::default(),
+            name: "RustBall room".to_string(),
+            password: None,
+            master: None,
+            location: Location::default(),
+        }
+    }
+}
+
+// Client in the lobby, playing the match or watching it.
+struct Client {
+    address: SocketAddr,
+    // Id of the client in the lobby.
+    member: u32,
+    // Tick of the server the client sent something last.
+    last_heard: u64,
+    // Sequence number of the last applied input.
+    last_input: Option<u64>,
+    // Sequence number of the newest input received.
+    last_received: Option<u64>,
+    // Inputs received and not applied yet.
+    // One is applied per tick, as the client predicted them, even if more come at once.
+    queued: VecDeque<(u64, PlayerInput)>,
+}
+
+// Match started from the lobby.
+struct Game {
+    state: MatchState,
+    // Member controlling every player of the match, `None` after they left.
+    players: Vec<Option<u32>>,
+    inputs: Vec<PlayerInput>,
+    // Tick the server returns to the lobby at, once the match is over.
+    lobby_return: Option<u64>,
+}
+
+pub struct Server {
+    socket: UdpSocket,
+    max_clients: usize,
+    lobby: Lobby,
+    clients: Vec<Client>,
+    next_member: u32,
+    // `None` while everybody is in the lobby.
+    game: Option<Game>,
+    // Whether the lobby changed since it was sent last.
+    lobby_changed: bool,
+    // Ticks since the server started, the matches count their own.
+    ticks: u64,
+    name: String,
+    password: Option<String>,
+    master: Option<SocketAddr>,
+    location: Location,
+    // Room sent to the master server last and when.
+    heartbeat: Option<(RoomInfo, u64)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+enum ServerSystem {
+    Receive,
+    Step,
+}
+
+impl Server {
+    pub fn bind(config: ServerConfig) -> io::Result<Server> {
+        let socket = UdpSocket::bind(config.address)?;
+        socket.set_nonblocking(true)?;
+        Ok(Server {
+            socket,
+            max_clients: config.max_clients,
+            lobby: Lobby::new(config.stadium, config.rules),
+            clients: Vec::new(),
+            next_member: 1,
+            game: None,
+            lobby_changed: false,
+            ticks: 0,
+            name: config.name,
+            password: config.password,
+            master: config.master,
+            location: config.location,
+            heartbeat: None,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn lobby(&self) -> &Lobby {
+        &self.lobby
+    }
+
+    // The match being played, `None` in the lobby.
+    pub fn match_state(&self) -> Option<&MatchState> {
+        self.game.as_ref().map(|game| &game.state)
+    }
+
+    // The room as listed by the master server.
+    pub fn room_info(&self) -> RoomInfo {
+        RoomInfo {
+            name: self.name.clone(),
+            players: self.clients.len() as u32,
+            max_players: self.max_clients as u32,
+            password: self.password.is_some(),
+            stadium: self.lobby.stadium.name.clone(),
+            location: self.location.clone(),
+        }
+    }
+
+    fn send(&self, address: SocketAddr, message: &ServerMessage) {
+        if let Err(err) = self.socket.send_to(&protocol::encode(message), address) {
+            warn!("Can't send to {}: {}", address, err);
+        }
+    }
+
+    fn client_of(&self, address: SocketAddr) -> Option<usize> {
+        self.clients
+            .iter()
+            .position(|client| client.address == address)
+    }
+
+    // Index of the member's player in the match, if it's playing.
+    fn player_of(&self, member: u32) -> Option<usize> {
+        let game = self.game.as_ref()?;
+        game.players
+            .iter()
+            .position(|&player| player == Some(member))
+    }
+
+    fn handle(&mut self, address: SocketAddr, message: ClientMessage) {
+        let client = self.client_of(address);
+        if let Some(client) = client {
+            self.clients[client].last_heard = self.ticks;
+        }
+
+        match message {
+            ClientMessage::Join { version, .. } if version != PROTOCOL_VERSION => {
+                let reason = format!(
+                    "The server speaks protocol {}, the client {}",
+                    PROTOCOL_VERSION, version
+                );
+                self.send(address, &ServerMessage::Rejected(reason));
+            }
+            ClientMessage::Join { password, .. } => match client {
+                // The welcome or the start may have been lost, the client asks again.
+                Some(client) => self.welcome(client),
+                None if self.clients.len() >= self.max_clients => {
+                    let reason = "The lobby is full".to_string();
+                    self.send(address, &ServerMessage::Rejected(reason));
+                }
+                None if self.password.is_some() && password != self.password => {
+                    let reason = "Wrong password".to_string();
+                    self.send(address, &ServerMessage::Rejected(reason));
+                }
+                None => {
+                    let member = self.next_member;
+                    self.next_member += 1;
+                    info!("{} joined as member {}", address, member);
+                    self.clients.push(Client {
+                        address,
+                        member,
+                        last_heard: self.ticks,
+                        last_input: None,
+                        last_received: None,
+                        queued: VecDeque::new(),
+                    });
+                    // Newcomers watch until the admin puts them in a team.
+                    self.lobby.join(member, format!("Player {}", member), None);
+                    self.lobby_changed = true;
+                    self.welcome(self.clients.len() - 1);
+                }
+            },
+            ClientMessage::Input { sequence, input } => {
+                let client = match client {
+                    Some(client) => client,
+                    None => return,
+                };
+                if self.player_of(self.clients[client].member).is_none() {
+                    return;
+                }
+                let client = &mut self.clients[client];
+                // Datagrams may come out of order, an older input would undo a newer one.
+                if client.last_received.is_none_or(|last| sequence > last) {
+                    client.last_received = Some(sequence);
+                    client.queued.push_back((sequence, input.sanitized()));
+                    if client.queued.len() > MAX_QUEUED_INPUTS {
+                        client.queued.pop_front();
+                    }
+                }
+            }
+            ClientMessage::Leave => {
+                if let Some(client) = client {
+                    info!("{} left", address);
+                    self.remove_client(client);
+                }
+            }
+            // The lobby can be changed by its admin while there's no match.
+            message => {
+                let admin =
+                    client.is_some_and(|client| self.lobby.is_admin(self.clients[client].member));
+                if !admin || self.game.is_some() {
+                    return;
+                }
+                match message {
+                    ClientMessage::MoveMember { member, team } => {
+                        self.lobby_changed |= self.lobby.move_member(member, team);
+                    }
+                    // A stadium without spawn points or goals can't be played on.
+                    ClientMessage::ChangeMatch { stadium, rules } => match stadium.validate() {
+                        Ok(()) => {
+                            self.lobby.stadium = *stadium;
+                            self.lobby.rules = rules;
+                            self.lobby_changed = true;
+                        }
+                        Err(err) => {
+                            warn!("{} sent an {}", address, err);
+                            self.send(address, &ServerMessage::Rejected(err.to_string()));
+                        }
+                    },
+                    ClientMessage::StartMatch => self.start_match(),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // Sends the lobby to the client, and the match if one is played.
+    fn welcome(&self, client: usize) {
+        let client = &self.clients[client];
+        let welcome = ServerMessage::Welcome {
+            member: client.member,
+            lobby: self.lobby.clone(),
+        };
+        self.send(client.address, &welcome);
+        if self.game.is_some() {
+            self.send(client.address, &self.start(client.member));
+        }
+    }
+
+    fn start(&self, member: u32) -> ServerMessage {
+        let state = self.match_state().unwrap();
+        ServerMessage::Start {
+            player: self.player_of(member),
+            stadium: state.stadium.clone(),
+            rules: state.rules,
+            teams: state.players.iter().map(|player| player.team).collect(),
+        }
+    }
+
+    fn start_match(&mut self) {
+        if !self.lobby.can_start() {
+            return;
+        }
+        let players: Vec<Option<u32>> = self
+            .lobby
+            .players()
+            .iter()
+            .map(|member| Some(member.id))
+            .collect();
+        info!("The match starts with {} players", players.len());
+        self.game = Some(Game {
+            state: self.lobby.new_match(),
+            inputs: vec![PlayerInput::default(); players.len()],
+            players,
+            lobby_return: None,
+        });
+        for client in &mut self.clients {
+            client.last_input = None;
+            client.last_received = None;
+            client.queued.clear();
+        }
+        for client in &self.clients {
+            self.send(client.address, &self.start(client.member));
+        }
+    }
+
+    // Takes the next queued input of every player, the ones whose input didn't come keep the last.
+    fn apply_inputs(&mut self) {
+        let game = match &mut self.game {
+            Some(game) => game,
+            None => return,
+        };
+        for client in &mut self.clients {
+            let player = game
+                .players
+                .iter()
+                .position(|&player| player == Some(client.member));
+            if let (Some(player), Some((sequence, input))) = (player, client.queued.pop_front()) {
+                game.inputs[player] = input;
+                // Only the applied inputs are acknowledged, the client predicts the rest.
+                client.last_input = Some(sequence);
+            }
+        }
+    }
+
+    fn remove_client(&mut self, client: usize) {
+        let member = self.clients.remove(client).member;
+        if let Some(player) = self.player_of(member) {
+            let game = self.game.as_mut().unwrap();
+            game.players[player] = None;
+            game.inputs[player] = PlayerInput::default();
+        }
+        self.lobby.leave(member);
+        self.lobby_changed = true;
+    }
+}
+
+// The room leaves the list right away, instead of after its heartbeats stop.
+impl Drop for Server {
+    fn drop(&mut self) {
+        if let Some(master) = self.master {
+            let _ = self
+                .socket
+                .send_to(&protocol::encode(&MasterMessage::Unregister), master);
+        }
+    }
+}
+
+impl Plugin for ServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(init_server_system)
+            .add_system(receive_system.label(ServerSystem::Receive))
+            .add_system(
+                step_system
+                    .label(ServerSystem::Step)
+                    .after(ServerSystem::Receive),
+            )
+            .add_system(broadcast_system.after(ServerSystem::Step))
+            .add_system(heartbeat_system.after(ServerSystem::Step));
+    }
+}
+
+fn init_server_system(server: Res<Server>) {
+    match server.local_addr() {
+        Ok(address) => info!("Listening on {}", address),
+        Err(err) => warn!("Can't get the server's address: {}", err),
+    }
+}
+
+// Handles all the datagrams which came since the last tick.
+fn receive_system(mut server: ResMut<Server>) {
+    let mut buffer = vec![0; MAX_DATAGRAM_SIZE];
+    loop {
+        match server.socket.recv_from(&mut buffer) {
+            Ok((length, address)) if Some(address) == server.master => {
+                if let Some(MasterReply::Rejected(reason)) = protocol::decode(&buffer[..length]) {
+                    warn!("The master server refused the room: {}", reason);
+                }
+            }
+            Ok((length, address)) => {
+                // Anything else than a message is ignored, it may come from anybody.
+                if let Some(message) = protocol::decode(&buffer[..length]) {
+                    server.handle(address, message);
+                }
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+            // A datagram sent earlier didn't reach its client, which isn't this one's problem.
+            Err(err) if err.kind() == ErrorKind::ConnectionReset => {}
+            Err(err) => {
+                warn!("Can't receive: {}", err);
+                break;
+            }
+        }
+    }
+}
+
+// Advances the match by a tick. Everybody returns to the lobby a while after the match is over,
+// or right away when all its players left.
+fn step_system(mut server: ResMut<Server>) {
+    server.ticks += 1;
+    let ticks = server.ticks;
+    while let Some(client) = server
+        .clients
+        .iter()
+        .position(|client| ticks - client.last_heard > CLIENT_TIMEOUT_TICKS)
+    {
+        info!("Member {} timed out", server.clients[client].member);
+        server.remove_client(client);
+    }
+
+    server.apply_inputs();
+    let game = match &mut server.game {
+        Some(game) => game,
+        None => return,
+    };
+    let abandoned = game.players.iter().all(Option::is_none);
+    if game.state.winner.is_none() && !abandoned {
+        game.state.step(&game.inputs);
+        return;
+    }
+    let lobby_return = *game.lobby_return.get_or_insert(ticks + LOBBY_RETURN_DELAY);
+    if abandoned || lobby_return <= ticks {
+        server.game = None;
+        server.lobby_changed = true;
+    }
+}
+
+// Sends the state of the match to every client, spectators included,
+// or the lobby when it changed and every now and then.
+fn broadcast_system(mut server: ResMut<Server>) {
+    match &server.game {
+        Some(game) => {
+            let snapshot = game.state.snapshot();
+            for client in &server.clients {
+                let message = ServerMessage::Snapshot {
+                    sequence: server.ticks,
+                    snapshot: snapshot.clone(),
+                    last_input: client.last_input,
+                };
+                server.send(client.address, &message);
+            }
+        }
+        None if server.lobby_changed || server.ticks.is_multiple_of(LOBBY_INTERVAL) => {
+            let message = ServerMessage::Lobby(server.lobby.clone());
+            for client in &server.clients {
+                server.send(client.address, &message);
+            }
+            server.lobby_changed = false;
+        }
+        None => {}
+    }
+}
+
+// Tells the master server about the room now and then, and whenever it changes.
+fn heartbeat_system(mut server: ResMut<Server>) {
+    let master = match server.master {
+        Some(master) => master,
+        None => return,
+    };
+    let room = server.room_info();
+    let due = match &server.heartbeat {
+        Some((last, tick)) => *last != room || server.ticks >= tick + HEARTBEAT_INTERVAL,
+        None => true,
+    };
+    if !due {
+        return;
+    }
+    let message = MasterMessage::Heartbeat {
+        version: PROTOCOL_VERSION,
+        room: room.clone(),
+    };
+    if let Err(err) = server.socket.send_to(&protocol::encode(&message), master) {
+        warn!("Can't send to the master server {}: {}", master, err);
+    }
+    server.heartbeat = Some((room, server.ticks));
+}
@@ -0,0 +1,122 @@
+use bevy::app::ScheduleRunnerSettings;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use rustball_core::protocol::Location;
+use rustball_core::{Stadium, TICKS_PER_SECOND};
+use rustball_server::{Server, ServerConfig, ServerPlugin};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::Duration;
+use std::{env, fs, process};
+
+const USAGE: &str = "Usage: rustball-server [--port PORT] [--max-clients N] [--stadium FILE] \
+[--score-limit GOALS|none] [--time-limit MINUTES|none] [--name NAME] [--password PASSWORD] \
+[--master HOST:PORT] [--location CODE:LATITUDE:LONGITUDE]";
+
+fn main() {
+    let config = match parse_args(env::args().skip(1)) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+    let server = match Server::bind(config) {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("Can't start the server: {}", err);
+            process::exit(1);
+        }
+    };
+
+    App::new()
+        .insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(
+            1. / TICKS_PER_SECOND as f64,
+        )))
+        .add_plugins(MinimalPlugins)
+        .add_plugin(LogPlugin)
+        .insert_resource(server)
+        .add_plugin(ServerPlugin)
+        .run();
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<ServerConfig, String> {
+    let mut config = ServerConfig::default();
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing the value of {}", arg))?;
+        match arg.as_str() {
+            "--port" => config.address.set_port(parse_number(&arg, &value)?),
+            "--max-clients" => {
+                config.max_clients = parse_number(&arg, &value)?;
+                if config.max_clients == 0 {
+                    return Err("The lobby needs room for at least one client".to_string());
+                }
+            }
+            "--stadium" => config.stadium = read_stadium(&value)?,
+            "--score-limit" => config.rules.score_limit = parse_limit(&arg, &value)?,
+            "--time-limit" => config.rules.time_limit = parse_limit(&arg, &value)?,
+            "--name" => config.name = value,
+            "--password" => config.password = Some(value),
+            "--master" => config.master = Some(resolve(&value)?),
+            "--location" => config.location = parse_location(&value)?,
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+    Ok(config)
+}
+
+fn parse_number<T: std::str::FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value of {}: {}", arg, value))
+}
+
+// `none` means no limit.
+fn parse_limit(arg: &str, value: &str) -> Result<Option<u32>, String> {
+    if value == "none" {
+        Ok(None)
+    } else {
+        parse_number(arg, value).map(Some)
+    }
+}
+
+fn resolve(address: &str) -> Result<SocketAddr, String> {
+    address
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addresses| addresses.next())
+        .ok_or_else(|| format!("Can't resolve {}", address))
+}
+
+// `PL:52.23:21.01` is a room in Warsaw.
+fn parse_location(value: &str) -> Result<Location, String> {
+    let invalid = || {
+        format!(
+            "Invalid location {}, expected CODE:LATITUDE:LONGITUDE",
+            value
+        )
+    };
+    let mut parts = value.split(':');
+    let (code, latitude, longitude) = match (parts.next(), parts.next(), parts.next(), parts.next())
+    {
+        (Some(code), Some(latitude), Some(longitude), None) => (code, latitude, longitude),
+        _ => return Err(invalid()),
+    };
+    Ok(Location {
+        code: code.to_string(),
+        latitude: latitude.parse().map_err(|_| invalid())?,
+        longitude: longitude.parse().map_err(|_| invalid())?,
+    })
+}
+
+// Reads a `.stadium.ron` file or a HaxBall `.hbs` one, like the game does.
+fn read_stadium(path: &str) -> Result<Stadium, String> {
+    let source = fs::read_to_string(path).map_err(|err| format!("Can't read {}: {}", path, err))?;
+    let stadium = if path.ends_with(".hbs") {
+        Stadium::from_hbs(&source)
+    } else {
+        Stadium::from_ron(&source)
+    };
+    stadium.map_err(|err| format!("Invalid stadium {}: {}", path, err))
+}
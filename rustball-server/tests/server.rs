@@ -0,0 +1,508 @@
+use bevy::prelude::*;
+use rustball_core::protocol::{self, ClientMessage, ServerMessage, PROTOCOL_VERSION};
+use rustball_core::{ClientMatch, Lobby, MatchRules, MatchState, PlayerInput, Stadium, Team};
+use rustball_server::LOBBY_INTERVAL;
+use rustball_server::{Server, ServerConfig, ServerPlugin, CLIENT_TIMEOUT_TICKS};
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::Duration;
+
+// Server with room for the given number of clients on a free localhost port, stepped by hand.
+fn server(max_clients: usize) -> (App, SocketAddr) {
+    server_with(ServerConfig {
+        address: "127.0.0.1:0".parse().unwrap(),
+        max_clients,
+        ..Default::default()
+    })
+}
+
+fn server_with(config: ServerConfig) -> (App, SocketAddr) {
+    let server = Server::bind(config).unwrap();
+    let address = server.local_addr().unwrap();
+    let mut app = App::new();
+    app.insert_resource(server).add_plugin(ServerPlugin);
+    app.update();
+    (app, address)
+}
+
+fn client(server: SocketAddr) -> UdpSocket {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.connect(server).unwrap();
+    socket.set_nonblocking(true).unwrap();
+    socket
+}
+
+fn send(socket: &UdpSocket, message: &ClientMessage) {
+    socket.send(&protocol::encode(message)).unwrap();
+}
+
+// Steps the server until the client gets a message the filter accepts.
+fn receive<T>(app: &mut App, socket: &UdpSocket, filter: impl Fn(ServerMessage) -> Option<T>) -> T {
+    for _ in 0..100 {
+        app.update();
+        let mut buffer = vec![0; protocol::MAX_DATAGRAM_SIZE];
+        while let Ok(length) = socket.recv(&mut buffer) {
+            let message = protocol::decode(&buffer[..length]).expect("invalid message");
+            if let Some(message) = filter(message) {
+                return message;
+            }
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    panic!("no such message from the server");
+}
+
+// Steps the server until the client gets a message other than a snapshot or a lobby update.
+fn reply(app: &mut App, socket: &UdpSocket) -> ServerMessage {
+    receive(app, socket, |message| match message {
+        ServerMessage::Snapshot { .. } | ServerMessage::Lobby(_) => None,
+        message => Some(message),
+    })
+}
+
+// Steps the server until the client gets the lobby.
+fn lobby(app: &mut App, socket: &UdpSocket) -> Lobby {
+    receive(app, socket, |message| match message {
+        ServerMessage::Lobby(lobby) => Some(lobby),
+        _ => None,
+    })
+}
+
+// Steps the server until the client gets a snapshot, returning the latest one it got.
+fn snapshot(app: &mut App, socket: &UdpSocket) -> (protocol::Snapshot, Option<u64>) {
+    for _ in 0..100 {
+        app.update();
+        thread::sleep(Duration::from_millis(1));
+        let mut latest = None;
+        let mut buffer = vec![0; protocol::MAX_DATAGRAM_SIZE];
+        while let Ok(length) = socket.recv(&mut buffer) {
+            if let Some(ServerMessage::Snapshot {
+                snapshot,
+                last_input,
+                ..
+            }) = protocol::decode(&buffer[..length])
+            {
+                latest = Some((snapshot, last_input));
+            }
+        }
+        if let Some(latest) = latest {
+            return latest;
+        }
+    }
+    panic!("no snapshot from the server");
+}
+
+fn join(app: &mut App, socket: &UdpSocket) -> ServerMessage {
+    join_with(app, socket, None)
+}
+
+fn join_with(app: &mut App, socket: &UdpSocket, password: Option<&str>) -> ServerMessage {
+    send(
+        socket,
+        &ClientMessage::Join {
+            version: PROTOCOL_VERSION,
+            password: password.map(str::to_string),
+        },
+    );
+    reply(app, socket)
+}
+
+fn joined_member(message: ServerMessage) -> u32 {
+    match message {
+        ServerMessage::Welcome { member, .. } => member,
+        message => panic!("expected a welcome, got {:?}", message),
+    }
+}
+
+// Player of the client in the started match.
+fn started_player(message: ServerMessage) -> Option<usize> {
+    match message {
+        ServerMessage::Start { player, .. } => player,
+        message => panic!("expected a start, got {:?}", message),
+    }
+}
+
+// The admin moves the members to the teams and starts the match.
+fn start(app: &mut App, admin: &UdpSocket, teams: &[(u32, Option<Team>)]) {
+    for &(member, team) in teams {
+        send(admin, &ClientMessage::MoveMember { member, team });
+    }
+    send(admin, &ClientMessage::StartMatch);
+    app.update();
+    thread::sleep(Duration::from_millis(10));
+    app.update();
+}
+
+// A client joined as the red player of a started match, returns its player.
+fn play(app: &mut App, address: SocketAddr) -> (UdpSocket, usize) {
+    let socket = client(address);
+    let member = joined_member(join(app, &socket));
+    start(app, &socket, &[(member, Some(Team::Red))]);
+    let player = started_player(reply(app, &socket)).unwrap();
+    (socket, player)
+}
+
+fn match_state(app: &App) -> &MatchState {
+    app.world
+        .resource::<Server>()
+        .match_state()
+        .expect("no match is played")
+}
+
+fn client_count(app: &App) -> usize {
+    app.world.resource::<Server>().client_count()
+}
+
+fn right(sequence: u64) -> ClientMessage {
+    ClientMessage::Input {
+        sequence,
+        input: PlayerInput {
+            direction: Vec2::X,
+            kick: false,
+        },
+    }
+}
+
+#[test]
+fn clients_join_the_lobby_as_spectators() {
+    let (mut app, address) = server(4);
+    let (first, second) = (client(address), client(address));
+
+    assert_eq!(joined_member(join(&mut app, &first)), 1);
+    match join(&mut app, &second) {
+        ServerMessage::Welcome { member, lobby } => {
+            assert_eq!(member, 2);
+            assert_eq!(lobby.admin, Some(1));
+            assert_eq!(lobby.stadium, Stadium::default());
+            assert_eq!(lobby.rules, MatchRules::default());
+            assert_eq!(lobby.team_members(None).count(), 2);
+        }
+        message => panic!("expected a welcome, got {:?}", message),
+    }
+    assert_eq!(client_count(&app), 2);
+    assert!(app.world.resource::<Server>().match_state().is_none());
+}
+
+#[test]
+fn joining_again_keeps_the_member() {
+    let (mut app, address) = server(4);
+    let socket = client(address);
+    assert_eq!(joined_member(join(&mut app, &socket)), 1);
+    assert_eq!(joined_member(join(&mut app, &socket)), 1);
+    assert_eq!(client_count(&app), 1);
+}
+
+#[test]
+fn full_lobby_rejects_clients() {
+    let (mut app, address) = server(1);
+    join(&mut app, &client(address));
+    assert!(matches!(
+        join(&mut app, &client(address)),
+        ServerMessage::Rejected(_)
+    ));
+    assert_eq!(client_count(&app), 1);
+}
+
+#[test]
+fn other_protocol_version_is_rejected() {
+    let (mut app, address) = server(4);
+    let socket = client(address);
+    send(
+        &socket,
+        &ClientMessage::Join {
+            version: PROTOCOL_VERSION + 1,
+            password: None,
+        },
+    );
+    assert!(matches!(
+        reply(&mut app, &socket),
+        ServerMessage::Rejected(_)
+    ));
+    assert_eq!(client_count(&app), 0);
+}
+
+#[test]
+fn locked_room_needs_the_password() {
+    let (mut app, address) = server_with(ServerConfig {
+        address: "127.0.0.1:0".parse().unwrap(),
+        password: Some("secret".to_string()),
+        ..Default::default()
+    });
+    for password in [None, Some("wrong")] {
+        assert!(matches!(
+            join_with(&mut app, &client(address), password),
+            ServerMessage::Rejected(_)
+        ));
+    }
+    assert_eq!(client_count(&app), 0);
+    assert!(matches!(
+        join_with(&mut app, &client(address), Some("secret")),
+        ServerMessage::Welcome { .. }
+    ));
+    assert_eq!(client_count(&app), 1);
+}
+
+#[test]
+fn admin_changes_the_lobby_for_everybody() {
+    let (mut app, address) = server(4);
+    let (admin, other) = (client(address), client(address));
+    join(&mut app, &admin);
+    let member = joined_member(join(&mut app, &other));
+
+    let rules = MatchRules {
+        score_limit: Some(1),
+        ..Default::default()
+    };
+    let stadium = Stadium {
+        name: "Small".to_string(),
+        ..Default::default()
+    };
+    send(
+        &admin,
+        &ClientMessage::MoveMember {
+            member,
+            team: Some(Team::Blue),
+        },
+    );
+    send(
+        &admin,
+        &ClientMessage::ChangeMatch {
+            stadium: Box::new(stadium.clone()),
+            rules,
+        },
+    );
+    // Lobbies sent before the changes may still be on their way.
+    let lobby = receive(&mut app, &other, |message| match message {
+        ServerMessage::Lobby(lobby) if lobby.rules == rules => Some(lobby),
+        _ => None,
+    });
+    assert_eq!(lobby.member(member).unwrap().team, Some(Team::Blue));
+    assert_eq!(lobby.stadium, stadium);
+    assert_eq!(lobby.rules, rules);
+}
+
+#[test]
+fn invalid_stadium_is_rejected() {
+    let (mut app, address) = server(4);
+    let admin = client(address);
+    let member = joined_member(join(&mut app, &admin));
+
+    let stadium = Stadium {
+        red_spawn_points: Vec::new(),
+        ..Default::default()
+    };
+    send(
+        &admin,
+        &ClientMessage::ChangeMatch {
+            stadium: Box::new(stadium),
+            rules: MatchRules::default(),
+        },
+    );
+    assert!(matches!(
+        reply(&mut app, &admin),
+        ServerMessage::Rejected(_)
+    ));
+    start(&mut app, &admin, &[(member, Some(Team::Red))]);
+    assert_eq!(started_player(reply(&mut app, &admin)), Some(0));
+    assert_eq!(
+        app.world.resource::<Server>().lobby().stadium,
+        Stadium::default()
+    );
+}
+
+#[test]
+fn only_the_admin_changes_the_lobby() {
+    let (mut app, address) = server(4);
+    let (admin, other) = (client(address), client(address));
+    join(&mut app, &admin);
+    let member = joined_member(join(&mut app, &other));
+
+    start(&mut app, &other, &[(member, Some(Team::Red))]);
+    let lobby = app.world.resource::<Server>().lobby();
+    assert_eq!(lobby.member(member).unwrap().team, None);
+    assert!(app.world.resource::<Server>().match_state().is_none());
+}
+
+#[test]
+fn match_starts_with_the_players_of_the_teams() {
+    let (mut app, address) = server(4);
+    let sockets = [client(address), client(address), client(address)];
+    for socket in &sockets {
+        join(&mut app, socket);
+    }
+    start(
+        &mut app,
+        &sockets[0],
+        &[(2, Some(Team::Red)), (3, Some(Team::Blue))],
+    );
+
+    let players: Vec<Option<usize>> = sockets
+        .iter()
+        .map(|socket| started_player(reply(&mut app, socket)))
+        .collect();
+    assert_eq!(players, [None, Some(0), Some(1)]);
+    let state = match_state(&app);
+    assert_eq!(state.players.len(), 2);
+    assert_eq!(state.players[0].team, Team::Red);
+    assert_eq!(state.players[1].team, Team::Blue);
+}
+
+#[test]
+fn spectators_watch_without_a_player() {
+    let (mut app, address) = server(4);
+    let (admin, spectator) = (client(address), client(address));
+    let member = joined_member(join(&mut app, &admin));
+    join(&mut app, &spectator);
+    start(&mut app, &admin, &[(member, Some(Team::Red))]);
+    assert_eq!(started_player(reply(&mut app, &spectator)), None);
+
+    send(&spectator, &right(1));
+    let (snapshot, last_input) = snapshot(&mut app, &spectator);
+    assert_eq!(last_input, None);
+    assert_eq!(snapshot.players.len(), 1);
+    assert_eq!(match_state(&app).player_disc(0).velocity, Vec2::ZERO);
+}
+
+#[test]
+fn joining_during_the_match_gets_it_started() {
+    let (mut app, address) = server(4);
+    play(&mut app, address);
+    let late = client(address);
+    joined_member(join(&mut app, &late));
+    assert_eq!(started_player(reply(&mut app, &late)), None);
+}
+
+#[test]
+fn input_moves_the_clients_player() {
+    let (mut app, address) = server(4);
+    let (socket, player) = play(&mut app, address);
+    let start = match_state(&app).player_disc(player).position;
+
+    send(&socket, &right(1));
+    let (_, last_input) = snapshot(&mut app, &socket);
+    for _ in 0..30 {
+        app.update();
+    }
+
+    assert_eq!(last_input, Some(1));
+    assert!(match_state(&app).player_disc(player).position.x > start.x);
+}
+
+#[test]
+fn late_inputs_are_dropped() {
+    let (mut app, address) = server(4);
+    let (socket, player) = play(&mut app, address);
+
+    send(&socket, &right(2));
+    send(
+        &socket,
+        &ClientMessage::Input {
+            sequence: 1,
+            input: PlayerInput::default(),
+        },
+    );
+    let (_, last_input) = snapshot(&mut app, &socket);
+    for _ in 0..30 {
+        app.update();
+    }
+
+    assert_eq!(last_input, Some(2));
+    assert!(match_state(&app).player_disc(player).velocity.x > 0.);
+}
+
+#[test]
+fn inputs_arriving_together_are_applied_a_tick_each() {
+    let (mut app, address) = server(4);
+    let (socket, player) = play(&mut app, address);
+    let state = match_state(&app);
+    let teams: Vec<Team> = state.players.iter().map(|player| player.team).collect();
+    let mut client = ClientMatch::new(Some(player), state.stadium.clone(), state.rules, &teams);
+    client.receive_snapshot(0, state.snapshot(), None);
+
+    // The second input comes before the server ticks, as it happens under jitter.
+    for direction in [Vec2::X, Vec2::Y] {
+        let input = PlayerInput {
+            direction,
+            kick: false,
+        };
+        send(&socket, &client.step(input).unwrap());
+    }
+    thread::sleep(Duration::from_millis(10));
+    app.update();
+    app.update();
+
+    let server = match_state(&app).player_disc(player);
+    let predicted = client.predicted().player_disc(player);
+    assert_eq!(server.position, predicted.position);
+    assert_eq!(server.velocity, predicted.velocity);
+}
+
+#[test]
+fn snapshots_follow_the_match() {
+    let (mut app, address) = server(4);
+    let (socket, _) = play(&mut app, address);
+
+    let (snapshot, _) = snapshot(&mut app, &socket);
+    let state = match_state(&app);
+    assert!(snapshot.tick <= state.tick);
+    assert_eq!(snapshot.discs.len(), state.discs.len());
+    assert_eq!(snapshot.players.len(), 1);
+}
+
+#[test]
+fn everybody_returns_to_the_lobby_when_the_players_left() {
+    let (mut app, address) = server(4);
+    let (player, _) = play(&mut app, address);
+    let spectator = client(address);
+    join(&mut app, &spectator);
+
+    send(&player, &ClientMessage::Leave);
+    let lobby = lobby(&mut app, &spectator);
+    assert!(app.world.resource::<Server>().match_state().is_none());
+    assert_eq!(lobby.members.len(), 1);
+    assert_eq!(lobby.admin, Some(2));
+}
+
+#[test]
+fn lobby_is_sent_again_and_again() {
+    let (mut app, address) = server(4);
+    let socket = client(address);
+    join(&mut app, &socket);
+    for _ in 0..LOBBY_INTERVAL {
+        app.update();
+    }
+    lobby(&mut app, &socket);
+}
+
+#[test]
+fn leaving_frees_the_place() {
+    let (mut app, address) = server(1);
+    let socket = client(address);
+    join(&mut app, &socket);
+    send(&socket, &ClientMessage::Leave);
+    app.update();
+    thread::sleep(Duration::from_millis(10));
+    app.update();
+    assert_eq!(client_count(&app), 0);
+    assert!(app.world.resource::<Server>().lobby().members.is_empty());
+    assert_eq!(joined_member(join(&mut app, &client(address))), 2);
+}
+
+#[test]
+fn silent_clients_time_out() {
+    let (mut app, address) = server(1);
+    join(&mut app, &client(address));
+    for _ in 0..=CLIENT_TIMEOUT_TICKS {
+        app.update();
+    }
+    assert_eq!(client_count(&app), 0);
+}
+
+#[test]
+fn garbage_is_ignored() {
+    let (mut app, address) = server(4);
+    let socket = client(address);
+    socket.send(&[0xff; 64]).unwrap();
+    socket.send(&[]).unwrap();
+    assert_eq!(joined_member(join(&mut app, &socket)), 1);
+}
@@ -0,0 +1,45 @@
+use crate::{Player, PlayerInputs};
+use bevy::prelude::*;
+use rustball_core::{Bot, BotRole, Difficulty, MatchState};
+
+pub struct Bots;
+
+// Bots of the next match, chosen in the menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BotSettings {
+    // Whether a single local player plays against a bot.
+    pub opponent: bool,
+    pub difficulty: Difficulty,
+    pub role: BotRole,
+}
+
+impl Default for BotSettings {
+    fn default() -> Self {
+        BotSettings {
+            opponent: false,
+            difficulty: Difficulty::Normal,
+            role: BotRole::Attacker,
+        }
+    }
+}
+
+// Player controlled by the computer.
+#[derive(Component)]
+pub struct BotPlayer(pub Bot);
+
+impl Plugin for Bots {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BotSettings>();
+    }
+}
+
+// Lets the bots decide the inputs of their players, the same way local players do.
+pub fn bot_input_system(
+    match_state: Res<MatchState>,
+    mut inputs: ResMut<PlayerInputs>,
+    mut query: Query<(&Player, &mut BotPlayer)>,
+) {
+    for (player, mut bot) in query.iter_mut() {
+        inputs.0[player.id] = bot.0.input(&match_state);
+    }
+}
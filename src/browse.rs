@@ -0,0 +1,280 @@
+use crate::link::Link;
+use crate::online::{JoinRoom, NetworkSettings, CONNECT_TIMEOUT, JOIN_INTERVAL};
+use crate::ui::{spawn_row, spawn_screen, spawn_small_button, spawn_text, spawn_wide_button};
+use crate::{despawn_all_system, GameState};
+use bevy::prelude::*;
+use rustball_core::protocol::{MasterMessage, MasterReply, RoomListing, PROTOCOL_VERSION};
+use rustball_core::{RoomFilter, RoomSort};
+use std::net::SocketAddr;
+use std::time::Instant;
+
+// Lists the rooms of the master server to pick one to join.
+pub struct BrowseRooms;
+
+// Rooms shown on the screen at most, the filter narrows down the rest.
+const SHOWN_ROOMS: usize = 8;
+
+// Rooms as the master server sent them last, kept with the filter between the visits.
+#[derive(Default)]
+struct RoomList {
+    rooms: Vec<RoomListing>,
+    filter: RoomFilter,
+    // What's going on, or why the list couldn't be fetched.
+    status: String,
+}
+
+// Asking the master server for the rooms.
+struct Browser {
+    // `None` if the connection couldn't be opened.
+    link: Option<Link>,
+    // Given by the master server to send the rooms to this address, none at first.
+    cookie: [u8; 8],
+    next_request: Instant,
+    // `None` once the master server answered or didn't in time.
+    deadline: Option<Instant>,
+}
+
+// Root of the room list screen, rebuilt whenever the list changes.
+#[derive(Component)]
+struct BrowseScreen;
+
+#[derive(Component)]
+enum BrowseItem {
+    Room(SocketAddr),
+    Refresh,
+    Sort,
+    HideFull,
+    HideLocked,
+    Back,
+}
+
+impl Plugin for BrowseRooms {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RoomList>()
+            .add_system_set(SystemSet::on_enter(GameState::Browse).with_system(init_browse_system))
+            .add_system_set(
+                SystemSet::on_update(GameState::Browse)
+                    .with_system(browse_system)
+                    .with_system(update_browse_system),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Browse)
+                    .with_system(exit_browse_system)
+                    .with_system(despawn_all_system),
+            );
+    }
+}
+
+// Connects to the master server and asks for the rooms.
+fn init_browse_system(
+    mut commands: Commands,
+    settings: Res<NetworkSettings>,
+    mut list: ResMut<RoomList>,
+) {
+    commands.spawn_bundle(UiCameraBundle::default());
+    let now = Instant::now();
+    let link = match Link::connect(&settings.master, &settings) {
+        Ok(link) => {
+            list.status = format!("Asking {} for the rooms...", settings.master);
+            Some(link)
+        }
+        Err(err) => {
+            list.status = format!("Can't connect to {}: {}", settings.master, err);
+            None
+        }
+    };
+    commands.insert_resource(Browser {
+        link,
+        cookie: [0; 8],
+        next_request: now,
+        deadline: Some(now + CONNECT_TIMEOUT),
+    });
+}
+
+// Rebuilds the screen after the rooms, the filter or the status changed.
+fn update_browse_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<NetworkSettings>,
+    list: Res<RoomList>,
+    query: Query<Entity, With<BrowseScreen>>,
+) {
+    if !list.is_changed() {
+        return;
+    }
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_browse_screen(&mut commands, &asset_server, &settings, &list);
+}
+
+fn spawn_browse_screen(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    settings: &NetworkSettings,
+    list: &RoomList,
+) {
+    let rooms = list.filter.apply(&list.rooms, &settings.location);
+    spawn_screen(commands, Color::NONE)
+        .insert(BrowseScreen)
+        .with_children(|parent| {
+            spawn_text(parent, asset_server, "Rooms", 80.0);
+            spawn_text(parent, asset_server, &list.status, 28.0);
+            for listing in rooms.iter().take(SHOWN_ROOMS) {
+                spawn_wide_button(
+                    parent,
+                    asset_server,
+                    room_label(listing, settings),
+                    BrowseItem::Room(listing.address),
+                );
+            }
+            if rooms.len() > SHOWN_ROOMS {
+                let more = format!("and {} more", rooms.len() - SHOWN_ROOMS);
+                spawn_text(parent, asset_server, more, 24.0);
+            }
+            // The password is set in network.ron.
+            spawn_text(
+                parent,
+                asset_server,
+                "Locked rooms are joined with the password from the settings",
+                24.0,
+            );
+            spawn_row(parent).with_children(|parent| {
+                let filter = &list.filter;
+                for (label, item) in [
+                    (sort_label(filter.sort), BrowseItem::Sort),
+                    (
+                        toggle_label("Hide Full", filter.hide_full),
+                        BrowseItem::HideFull,
+                    ),
+                    (
+                        toggle_label("Hide Locked", filter.hide_locked),
+                        BrowseItem::HideLocked,
+                    ),
+                ] {
+                    spawn_small_button(parent, asset_server, label, item);
+                }
+            });
+            spawn_row(parent).with_children(|parent| {
+                spawn_small_button(parent, asset_server, "Refresh", BrowseItem::Refresh);
+                spawn_small_button(parent, asset_server, "Back", BrowseItem::Back);
+            });
+        });
+}
+
+fn room_label(listing: &RoomListing, settings: &NetworkSettings) -> String {
+    let room = &listing.room;
+    let distance = settings.location.distance(&room.location);
+    let mut label = format!(
+        "{}  {}/{}  {}  {} {:.0} km",
+        room.name, room.players, room.max_players, room.stadium, room.location.code, distance
+    );
+    if room.password {
+        label.push_str("  (locked)");
+    }
+    label
+}
+
+fn sort_label(sort: RoomSort) -> String {
+    let sort = match sort {
+        RoomSort::Players => "Players",
+        RoomSort::Name => "Name",
+        RoomSort::Distance => "Distance",
+    };
+    format!("Sort: {}", sort)
+}
+
+fn toggle_label(label: &str, on: bool) -> String {
+    format!("{}: {}", label, if on { "On" } else { "Off" })
+}
+
+// Asks for the rooms until the master server answers, then does the action of the pressed button.
+// Clicking a room joins it, Escape goes back to the online menu.
+fn browse_system(
+    mut commands: Commands,
+    mut kb: ResMut<Input<KeyCode>>,
+    mut app_state: ResMut<State<GameState>>,
+    settings: Res<NetworkSettings>,
+    mut browser: ResMut<Browser>,
+    mut list: ResMut<RoomList>,
+    query: Query<(&Interaction, &BrowseItem), Changed<Interaction>>,
+) {
+    if kb.clear_just_pressed(KeyCode::Escape) {
+        app_state
+            .set(GameState::OnlineMenu)
+            .expect("Something went wrong!");
+        return;
+    }
+
+    let now = Instant::now();
+    let browser = &mut *browser;
+    if let Some(link) = &mut browser.link {
+        for (_, reply) in link.poll() {
+            match reply {
+                // Asks again right away, with the cookie.
+                MasterReply::Cookie(cookie) => {
+                    browser.cookie = cookie;
+                    browser.next_request = now;
+                    continue;
+                }
+                MasterReply::Rooms(rooms) => {
+                    list.status = format!("{} rooms on {}", rooms.len(), settings.master);
+                    list.rooms = rooms;
+                }
+                MasterReply::Rejected(reason) => {
+                    list.status = format!("The master server refused: {}", reason);
+                }
+            }
+            browser.deadline = None;
+        }
+        match browser.deadline {
+            Some(deadline) if now >= deadline => {
+                list.status = format!("No answer from {}", settings.master);
+                browser.deadline = None;
+            }
+            Some(_) if now >= browser.next_request => {
+                link.send(&MasterMessage::ListRooms {
+                    version: PROTOCOL_VERSION,
+                    cookie: browser.cookie,
+                });
+                browser.next_request = now + JOIN_INTERVAL;
+            }
+            _ => {}
+        }
+    }
+
+    for (interaction, item) in query.iter() {
+        if interaction != &Interaction::Clicked {
+            continue;
+        }
+        match item {
+            BrowseItem::Room(address) => {
+                commands.insert_resource(JoinRoom(address.to_string()));
+                app_state
+                    .set(GameState::Connecting)
+                    .expect("Something went wrong!");
+                return;
+            }
+            BrowseItem::Refresh => {
+                if browser.link.is_some() {
+                    list.status = format!("Asking {} for the rooms...", settings.master);
+                    browser.next_request = now;
+                    browser.deadline = Some(now + CONNECT_TIMEOUT);
+                }
+            }
+            BrowseItem::Sort => list.filter.sort = list.filter.sort.next(),
+            BrowseItem::HideFull => list.filter.hide_full = !list.filter.hide_full,
+            BrowseItem::HideLocked => list.filter.hide_locked = !list.filter.hide_locked,
+            BrowseItem::Back => {
+                app_state
+                    .set(GameState::OnlineMenu)
+                    .expect("Something went wrong!");
+                return;
+            }
+        }
+    }
+}
+
+fn exit_browse_system(mut commands: Commands) {
+    commands.remove_resource::<Browser>();
+}
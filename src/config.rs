@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+// Directory of the game within the user's configuration directory.
+const CONFIG_DIR: &str = "rustball";
+
+// Directory of the user's configuration files, where the platform usually keeps them.
+fn user_config_dir() -> Option<PathBuf> {
+    let home = || env::var_os("HOME").map(PathBuf::from);
+    if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| home().map(|home| home.join(".config")))
+    }
+}
+
+// Path of the given configuration file of the game.
+pub fn config_path(file: &str) -> Option<PathBuf> {
+    user_config_dir().map(|dir| dir.join(CONFIG_DIR).join(file))
+}
+
+// Reads the configuration from the given file.
+// The defaults are used until it's saved for the first time, or if it can't be read.
+pub fn load<T: DeserializeOwned + Default>(file: &str) -> T {
+    match config_path(file) {
+        Some(path) => read(&path),
+        None => T::default(),
+    }
+}
+
+fn read<T: DeserializeOwned + Default>(path: &Path) -> T {
+    match fs::read_to_string(path) {
+        Ok(source) => ron::de::from_str(&source).unwrap_or_else(|err| {
+            warn!("Can't parse {:?}: {}", path, err);
+            T::default()
+        }),
+        Err(err) if err.kind() == ErrorKind::NotFound => T::default(),
+        Err(err) => {
+            warn!("Can't read {:?}: {}", path, err);
+            T::default()
+        }
+    }
+}
+
+// Writes the configuration to the given file, so it's loaded on the next start.
+pub fn save<T: Serialize>(file: &str, config: &T) {
+    let path = match config_path(file) {
+        Some(path) => path,
+        None => {
+            warn!("Can't save {}: no configuration directory", file);
+            return;
+        }
+    };
+    if let Err(err) = write(&path, config) {
+        warn!("Can't save {:?}: {}", path, err);
+    }
+}
+
+fn write<T: Serialize>(path: &Path, config: &T) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let source = ron::ser::to_string_pretty(config, Default::default())?;
+    fs::write(path, source)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controls::{ControlScheme, Device};
+
+    // Fresh directory for the files of a test.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rustball-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn saved_configuration_is_read_back() {
+        let dir = test_dir("saved");
+        let path = dir.join(CONFIG_DIR).join("controls.ron");
+        let mut scheme = ControlScheme::default();
+        scheme.players[2].device = Device::Gamepad(1);
+        write(&path, &scheme).unwrap();
+        assert_eq!(read::<ControlScheme>(&path), scheme);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_or_corrupt_file_gives_the_defaults() {
+        let dir = test_dir("corrupt");
+        let path = dir.join("controls.ron");
+        assert_eq!(read::<ControlScheme>(&path), ControlScheme::default());
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "(players: [oops").unwrap();
+        assert_eq!(read::<ControlScheme>(&path), ControlScheme::default());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
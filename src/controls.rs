@@ -0,0 +1,421 @@
+use crate::ui::{spawn_button, spawn_screen, spawn_small_button, spawn_text};
+use crate::{config, GameState};
+use bevy::prelude::*;
+use rustball_core::{PlayerInput, Team};
+use serde::{Deserialize, Serialize};
+
+pub struct Controls;
+
+// File in the user's configuration directory the key bindings are saved to.
+const CONTROLS_FILE: &str = "controls.ron";
+
+// Something a player does by holding a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Kick,
+}
+
+const ACTIONS: [Action; 5] = [
+    Action::Up,
+    Action::Down,
+    Action::Left,
+    Action::Right,
+    Action::Kick,
+];
+
+// Keys of a single local player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyBindings {
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub kick: KeyCode,
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: Action) -> KeyCode {
+        match action {
+            Action::Up => self.up,
+            Action::Down => self.down,
+            Action::Left => self.left,
+            Action::Right => self.right,
+            Action::Kick => self.kick,
+        }
+    }
+
+    fn key_mut(&mut self, action: Action) -> &mut KeyCode {
+        match action {
+            Action::Up => &mut self.up,
+            Action::Down => &mut self.down,
+            Action::Left => &mut self.left,
+            Action::Right => &mut self.right,
+            Action::Kick => &mut self.kick,
+        }
+    }
+
+    // Input of the player given by the held keys.
+    pub fn input(&self, kb: &Input<KeyCode>) -> PlayerInput {
+        PlayerInput::from_keys(
+            kb.pressed(self.up),
+            kb.pressed(self.down),
+            kb.pressed(self.left),
+            kb.pressed(self.right),
+            kb.pressed(self.kick),
+        )
+    }
+}
+
+// Device a local player plays with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Device {
+    // Nobody plays in the slot.
+    None,
+    Keyboard,
+    // The n-th connected gamepad, see `ConnectedGamepads`.
+    Gamepad(usize),
+}
+
+impl Device {
+    // The device chosen after this one on the controls screen.
+    fn next(self) -> Device {
+        match self {
+            Device::None => Device::Keyboard,
+            Device::Keyboard => Device::Gamepad(0),
+            Device::Gamepad(n) if n + 1 < LOCAL_PLAYERS => Device::Gamepad(n + 1),
+            Device::Gamepad(_) => Device::None,
+        }
+    }
+
+    fn label(self) -> String {
+        match self {
+            Device::None => "Off".to_string(),
+            Device::Keyboard => "Keyboard".to_string(),
+            Device::Gamepad(n) => format!("Gamepad {}", n + 1),
+        }
+    }
+}
+
+// Device of a local player and their keys, which are used only with the keyboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerControls {
+    pub device: Device,
+    pub keys: KeyBindings,
+}
+
+impl PlayerControls {
+    // Whether somebody plays in the slot.
+    pub fn is_active(&self) -> bool {
+        self.device != Device::None
+    }
+}
+
+// Number of people who can play on a single machine.
+pub const LOCAL_PLAYERS: usize = 4;
+
+// Controls of the local players, by their index.
+// Local players take turns joining the red and the blue team, see `team`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ControlScheme {
+    pub players: Vec<PlayerControls>,
+}
+
+// Team of the given local player.
+pub fn team(player: usize) -> Team {
+    if player.is_multiple_of(2) {
+        Team::Red
+    } else {
+        Team::Blue
+    }
+}
+
+impl Default for ControlScheme {
+    fn default() -> Self {
+        let player = |device, [up, down, left, right, kick]: [KeyCode; 5]| PlayerControls {
+            device,
+            keys: KeyBindings {
+                up,
+                down,
+                left,
+                right,
+                kick,
+            },
+        };
+        ControlScheme {
+            players: vec![
+                player(
+                    Device::Keyboard,
+                    [
+                        KeyCode::W,
+                        KeyCode::S,
+                        KeyCode::A,
+                        KeyCode::D,
+                        KeyCode::Space,
+                    ],
+                ),
+                player(
+                    Device::Keyboard,
+                    [
+                        KeyCode::Up,
+                        KeyCode::Down,
+                        KeyCode::Left,
+                        KeyCode::Right,
+                        KeyCode::RControl,
+                    ],
+                ),
+                player(
+                    Device::None,
+                    [KeyCode::I, KeyCode::K, KeyCode::J, KeyCode::L, KeyCode::H],
+                ),
+                player(
+                    Device::None,
+                    [
+                        KeyCode::Numpad8,
+                        KeyCode::Numpad5,
+                        KeyCode::Numpad4,
+                        KeyCode::Numpad6,
+                        KeyCode::Numpad0,
+                    ],
+                ),
+            ],
+        }
+    }
+}
+
+impl ControlScheme {
+    // Binds the key to the action of the given local player.
+    // An action which used the key before gets the previous key of this one,
+    // so a key never does two things at once.
+    pub fn bind(&mut self, player: usize, action: Action, key: KeyCode) {
+        let previous = self.players[player].keys.key(action);
+        for controls in self.players.iter_mut() {
+            for other in ACTIONS {
+                if controls.keys.key(other) == key {
+                    *controls.keys.key_mut(other) = previous;
+                }
+            }
+        }
+        *self.players[player].keys.key_mut(action) = key;
+    }
+}
+
+// Binding waiting for a key to be pressed, if any.
+#[derive(Default)]
+struct Rebinding(Option<(usize, Action)>);
+
+// Marks entities of the controls screen.
+#[derive(Component)]
+struct ControlsScreen;
+
+#[derive(Component)]
+enum ControlsItem {
+    Device(usize),
+    Binding(usize, Action),
+    Back,
+}
+
+impl Plugin for Controls {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(config::load::<ControlScheme>(CONTROLS_FILE))
+            .init_resource::<Rebinding>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Controls).with_system(init_controls_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Controls)
+                    .with_system(handle_buttons)
+                    .with_system(update_labels),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Controls).with_system(despawn_controls));
+    }
+}
+
+// Text shown on the button.
+fn button_label(item: &ControlsItem, scheme: &ControlScheme, rebinding: &Rebinding) -> String {
+    match item {
+        ControlsItem::Binding(player, action) if rebinding.0 == Some((*player, *action)) => {
+            format!("{:?}: press a key", action)
+        }
+        ControlsItem::Binding(player, action) => {
+            format!(
+                "{:?}: {:?}",
+                action,
+                scheme.players[*player].keys.key(*action)
+            )
+        }
+        ControlsItem::Device(player) => scheme.players[*player].device.label(),
+        ControlsItem::Back => "Back".to_string(),
+    }
+}
+
+// Do the action given by the pressed button, Escape goes back as well.
+// After a binding is clicked, the next pressed key is bound, Escape cancels it.
+fn handle_buttons(
+    mut kb: ResMut<Input<KeyCode>>,
+    mut app_state: ResMut<State<GameState>>,
+    mut scheme: ResMut<ControlScheme>,
+    mut rebinding: ResMut<Rebinding>,
+    query: Query<(&Interaction, &ControlsItem), Changed<Interaction>>,
+) {
+    if let Some((player, action)) = rebinding.0 {
+        let pressed = kb.get_just_pressed().next().copied();
+        if let Some(key) = pressed {
+            kb.clear_just_pressed(key);
+            if key != KeyCode::Escape {
+                scheme.bind(player, action, key);
+                config::save(CONTROLS_FILE, &*scheme);
+            }
+            rebinding.0 = None;
+        }
+        return;
+    }
+
+    if kb.clear_just_pressed(KeyCode::Escape) {
+        app_state.pop().expect("Something went wrong!");
+        return;
+    }
+
+    for (interaction, item) in query.iter() {
+        if interaction == &Interaction::Clicked {
+            match item {
+                ControlsItem::Device(player) => {
+                    let controls = &mut scheme.players[*player];
+                    controls.device = controls.device.next();
+                    config::save(CONTROLS_FILE, &*scheme);
+                }
+                ControlsItem::Binding(player, action) => rebinding.0 = Some((*player, *action)),
+                ControlsItem::Back => app_state.pop().expect("Something went wrong!"),
+            }
+        }
+    }
+}
+
+// Updates labels of the buttons after a key was bound or is waited for.
+fn update_labels(
+    scheme: Res<ControlScheme>,
+    rebinding: Res<Rebinding>,
+    query: Query<(&ControlsItem, &Children)>,
+    mut query_text: Query<&mut Text>,
+) {
+    if !scheme.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+    for (item, children) in query.iter() {
+        for &child in children.iter() {
+            if let Ok(mut text) = query_text.get_mut(child) {
+                text.sections[0].value = button_label(item, &scheme, &rebinding);
+            }
+        }
+    }
+}
+
+fn despawn_controls(
+    mut commands: Commands,
+    mut rebinding: ResMut<Rebinding>,
+    query: Query<Entity, With<ControlsScreen>>,
+) {
+    rebinding.0 = None;
+    for ent in query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
+
+// Node laying out its children in a row or a column.
+fn layout_node(flex_direction: FlexDirection) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_direction,
+            justify_content: JustifyContent::Center,
+            align_self: AlignSelf::Center,
+            ..Default::default()
+        },
+        color: Color::NONE.into(),
+        ..Default::default()
+    }
+}
+
+// Shows the devices and the key bindings of the local players side by side.
+fn init_controls_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    scheme: Res<ControlScheme>,
+    rebinding: Res<Rebinding>,
+) {
+    commands
+        .spawn_bundle(UiCameraBundle::default())
+        .insert(ControlsScreen);
+
+    spawn_screen(&mut commands, Color::rgba(0.0, 0.0, 0.0, 0.8))
+        .insert(ControlsScreen)
+        .with_children(|parent| {
+            spawn_text(parent, &asset_server, "Controls", 80.0);
+            parent
+                .spawn_bundle(layout_node(FlexDirection::Row))
+                .with_children(|row| {
+                    for player in 0..scheme.players.len() {
+                        row.spawn_bundle(layout_node(FlexDirection::ColumnReverse))
+                            .with_children(|column| {
+                                let title = format!("Player {} ({:?})", player + 1, team(player));
+                                spawn_text(column, &asset_server, title, 32.0);
+                                let items = std::iter::once(ControlsItem::Device(player)).chain(
+                                    ACTIONS
+                                        .into_iter()
+                                        .map(|action| ControlsItem::Binding(player, action)),
+                                );
+                                for item in items {
+                                    let label = button_label(&item, &scheme, &rebinding);
+                                    spawn_small_button(column, &asset_server, label, item);
+                                }
+                            });
+                    }
+                });
+            spawn_button(parent, &asset_server, "Back", ControlsItem::Back);
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binding_a_used_key_swaps_it() {
+        let mut scheme = ControlScheme::default();
+        // Space kicks for the first player, Up moves the second one.
+        scheme.bind(0, Action::Kick, KeyCode::Up);
+        assert_eq!(scheme.players[0].keys.kick, KeyCode::Up);
+        assert_eq!(scheme.players[1].keys.up, KeyCode::Space);
+
+        scheme.bind(0, Action::Up, KeyCode::S);
+        assert_eq!(scheme.players[0].keys.up, KeyCode::S);
+        assert_eq!(scheme.players[0].keys.down, KeyCode::W);
+    }
+
+    #[test]
+    fn binding_the_same_key_changes_nothing() {
+        let mut scheme = ControlScheme::default();
+        scheme.bind(1, Action::Left, KeyCode::Left);
+        assert_eq!(scheme, ControlScheme::default());
+    }
+
+    #[test]
+    fn binding_a_free_key_keeps_the_others() {
+        let mut scheme = ControlScheme::default();
+        scheme.bind(0, Action::Kick, KeyCode::Q);
+        let mut expected = ControlScheme::default();
+        expected.players[0].keys.kick = KeyCode::Q;
+        assert_eq!(scheme, expected);
+    }
+
+    #[test]
+    fn scheme_round_trips() {
+        let mut scheme = ControlScheme::default();
+        scheme.bind(3, Action::Kick, KeyCode::Return);
+        scheme.players[3].device = Device::Gamepad(2);
+        let source = ron::ser::to_string_pretty(&scheme, Default::default()).unwrap();
+        assert_eq!(ron::de::from_str::<ControlScheme>(&source).unwrap(), scheme);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::controls::{Device, PlayerControls};
+use bevy::ecs::system::SystemParam;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use rustball_core::PlayerInput;
+
+// Keeps track of the connected gamepads, so local players can be assigned to them.
+// Needs only the input plugin, so it runs in a headless app as well.
+pub struct LocalInput;
+
+// How far the stick has to be tilted to move the player.
+const STICK_DEAD_ZONE: f32 = 0.15;
+// Button kicking the ball, A on Xbox controllers and Cross on PlayStation ones.
+const KICK_BUTTON: GamepadButtonType = GamepadButtonType::South;
+
+// Gamepads in the order they were connected, local players choose the n-th one.
+// A disconnected gamepad leaves its slot empty and gets it back when it's reconnected,
+// so the other players keep their gamepads and the choice survives reconnecting them.
+#[derive(Default)]
+pub struct ConnectedGamepads {
+    // Gamepad which was in the slot last and whether it's still connected.
+    slots: Vec<(Gamepad, bool)>,
+}
+
+impl ConnectedGamepads {
+    // The gamepad in the n-th slot, if it's connected.
+    pub fn get(&self, n: usize) -> Option<Gamepad> {
+        self.slots
+            .get(n)
+            .filter(|(_, connected)| *connected)
+            .map(|(gamepad, _)| *gamepad)
+    }
+
+    // Puts the gamepad in its old slot, or the first empty one, or a new one.
+    fn connect(&mut self, gamepad: Gamepad) {
+        let slot = self
+            .slots
+            .iter()
+            .position(|(old, _)| *old == gamepad)
+            .or_else(|| self.slots.iter().position(|(_, connected)| !connected));
+        match slot {
+            Some(slot) => self.slots[slot] = (gamepad, true),
+            None => self.slots.push((gamepad, true)),
+        }
+    }
+
+    fn disconnect(&mut self, gamepad: Gamepad) {
+        for (old, connected) in &mut self.slots {
+            if *old == gamepad {
+                *connected = false;
+            }
+        }
+    }
+}
+
+impl Plugin for LocalInput {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConnectedGamepads>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                gamepad_connection_system.after(InputSystem),
+            );
+    }
+}
+
+// Gives the newly connected gamepads their slots and empties the slots of the disconnected ones.
+fn gamepad_connection_system(
+    mut gamepads: ResMut<ConnectedGamepads>,
+    mut events: EventReader<GamepadEvent>,
+) {
+    for GamepadEvent(gamepad, event_type) in events.iter() {
+        match event_type {
+            GamepadEventType::Connected => {
+                info!("{:?} connected", gamepad);
+                gamepads.connect(*gamepad);
+            }
+            GamepadEventType::Disconnected => {
+                info!("{:?} disconnected", gamepad);
+                gamepads.disconnect(*gamepad);
+            }
+            _ => {}
+        }
+    }
+}
+
+// Everything the inputs of the local players are read from.
+#[derive(SystemParam)]
+pub struct InputDevices<'w, 's> {
+    keyboard: Res<'w, Input<KeyCode>>,
+    gamepads: Res<'w, ConnectedGamepads>,
+    buttons: Res<'w, Input<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    #[system_param(ignore)]
+    marker: std::marker::PhantomData<&'s ()>,
+}
+
+impl InputDevices<'_, '_> {
+    // Input of the local player from the device they chose.
+    // A player whose gamepad isn't connected stands still.
+    pub fn input(&self, controls: &PlayerControls) -> PlayerInput {
+        match controls.device {
+            Device::None => PlayerInput::default(),
+            Device::Keyboard => controls.keys.input(&self.keyboard),
+            Device::Gamepad(n) => self
+                .gamepads
+                .get(n)
+                .map(|gamepad| self.gamepad_input(gamepad))
+                .unwrap_or_default(),
+        }
+    }
+
+    // The left stick moves the player as far as it's tilted, the D-pad moves them at full speed.
+    fn gamepad_input(&self, gamepad: Gamepad) -> PlayerInput {
+        let axis = |axis_type| self.axes.get(GamepadAxis(gamepad, axis_type)).unwrap_or(0.);
+        let button = |button_type| self.buttons.pressed(GamepadButton(gamepad, button_type));
+
+        let d_pad = PlayerInput::from_keys(
+            button(GamepadButtonType::DPadUp),
+            button(GamepadButtonType::DPadDown),
+            button(GamepadButtonType::DPadLeft),
+            button(GamepadButtonType::DPadRight),
+            button(KICK_BUTTON),
+        );
+        let stick = Vec2::new(
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+        );
+        PlayerInput {
+            direction: if d_pad.direction != Vec2::ZERO {
+                d_pad.direction
+            } else {
+                stick_direction(stick)
+            },
+            ..d_pad
+        }
+    }
+}
+
+// Direction of the tilted stick, at most one unit long.
+// The dead zone is cut off, so a stick resting slightly off center doesn't move the player,
+// and the rest of the range is stretched to start at zero.
+fn stick_direction(stick: Vec2) -> Vec2 {
+    let tilt = stick.length();
+    if tilt <= STICK_DEAD_ZONE {
+        return Vec2::ZERO;
+    }
+    let scaled = ((tilt - STICK_DEAD_ZONE) / (1. - STICK_DEAD_ZONE)).min(1.);
+    stick * (scaled / tilt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controls::ControlScheme;
+    use bevy::ecs::event::Events;
+    use bevy::ecs::system::SystemState;
+    use bevy::input::gamepad::GamepadEventRaw;
+    use bevy::input::InputPlugin;
+
+    // Headless app with the input plugins only, the gamepads are fed by hand.
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugin(InputPlugin).add_plugin(LocalInput);
+        app
+    }
+
+    // Sends the event as a gamepad driver would and runs a frame.
+    fn send(app: &mut App, gamepad: usize, event: GamepadEventType) {
+        app.world
+            .resource_mut::<Events<GamepadEventRaw>>()
+            .send(GamepadEventRaw(Gamepad(gamepad), event));
+        app.update();
+    }
+
+    fn slots(app: &App) -> Vec<Option<usize>> {
+        let gamepads = app.world.resource::<ConnectedGamepads>();
+        (0..4)
+            .map(|n| gamepads.get(n).map(|gamepad| gamepad.0))
+            .collect()
+    }
+
+    // Input of a local player playing with the n-th gamepad.
+    fn input(app: &mut App, n: usize) -> PlayerInput {
+        let controls = PlayerControls {
+            device: Device::Gamepad(n),
+            ..ControlScheme::default().players[0]
+        };
+        let mut state = SystemState::<InputDevices>::new(&mut app.world);
+        state.get_mut(&mut app.world).input(&controls)
+    }
+
+    fn stick(app: &mut App, gamepad: usize, x: f32) {
+        send(
+            app,
+            gamepad,
+            GamepadEventType::AxisChanged(GamepadAxisType::LeftStickX, x),
+        );
+    }
+
+    #[test]
+    fn gamepads_keep_their_slots() {
+        let mut app = app();
+        for gamepad in [10, 11, 12] {
+            send(&mut app, gamepad, GamepadEventType::Connected);
+        }
+        assert_eq!(slots(&app), [Some(10), Some(11), Some(12), None]);
+
+        send(&mut app, 10, GamepadEventType::Disconnected);
+        assert_eq!(slots(&app), [None, Some(11), Some(12), None]);
+        send(&mut app, 10, GamepadEventType::Connected);
+        assert_eq!(slots(&app), [Some(10), Some(11), Some(12), None]);
+
+        // A new gamepad takes the empty slot, the one which left it gets a new one.
+        send(&mut app, 11, GamepadEventType::Disconnected);
+        send(&mut app, 13, GamepadEventType::Connected);
+        send(&mut app, 11, GamepadEventType::Connected);
+        assert_eq!(slots(&app), [Some(10), Some(13), Some(12), Some(11)]);
+    }
+
+    #[test]
+    fn gamepad_moves_and_kicks() {
+        let mut app = app();
+        send(&mut app, 7, GamepadEventType::Connected);
+        assert_eq!(input(&mut app, 0), PlayerInput::default());
+
+        // A stick resting slightly off center is in the dead zone.
+        stick(&mut app, 7, 0.1);
+        assert_eq!(input(&mut app, 0).direction, Vec2::ZERO);
+        stick(&mut app, 7, 0.575);
+        assert!((input(&mut app, 0).direction - Vec2::new(0.5, 0.)).length() < 1e-4);
+        stick(&mut app, 7, 1.);
+        assert_eq!(input(&mut app, 0).direction, Vec2::X);
+
+        send(
+            &mut app,
+            7,
+            GamepadEventType::ButtonChanged(KICK_BUTTON, 1.),
+        );
+        assert!(input(&mut app, 0).kick);
+        // The D-pad wins over the stick.
+        send(
+            &mut app,
+            7,
+            GamepadEventType::ButtonChanged(GamepadButtonType::DPadUp, 1.),
+        );
+        assert_eq!(input(&mut app, 0).direction, Vec2::Y);
+        // Nobody holds the second gamepad.
+        assert_eq!(input(&mut app, 1), PlayerInput::default());
+
+        send(&mut app, 7, GamepadEventType::Disconnected);
+        assert_eq!(input(&mut app, 0), PlayerInput::default());
+    }
+}
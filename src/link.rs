@@ -0,0 +1,132 @@
+use crate::online::NetworkSettings;
+use bevy::prelude::*;
+use rustball_core::protocol::{self, MAX_DATAGRAM_SIZE};
+use rustball_core::LinkSimulator;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Instant;
+
+// UDP socket talking to a single peer, a server or another player,
+// with the datagrams going through the simulated network both ways.
+pub struct Link {
+    socket: UdpSocket,
+    // `None` while listening for the first message.
+    peer: Option<SocketAddr>,
+    outgoing: LinkSimulator<(SocketAddr, Vec<u8>)>,
+    incoming: LinkSimulator<(SocketAddr, Vec<u8>)>,
+    // Time the last message came from the peer.
+    last_heard: Instant,
+}
+
+impl Link {
+    // Link to the given address, e.g. `127.0.0.1:5195`.
+    pub fn connect(address: &str, settings: &NetworkSettings) -> anyhow::Result<Link> {
+        let peer = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("no address of {}", address))?;
+        let local: SocketAddr = if peer.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let mut link = Link::bind(local, settings)?;
+        link.peer = Some(peer);
+        Ok(link)
+    }
+
+    // Link waiting for a peer on the given port, see `set_peer`.
+    pub fn listen(port: u16, settings: &NetworkSettings) -> io::Result<Link> {
+        Link::bind(([0, 0, 0, 0], port).into(), settings)
+    }
+
+    fn bind(address: SocketAddr, settings: &NetworkSettings) -> io::Result<Link> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+        let seed = crate::bot_seed();
+        Ok(Link {
+            socket,
+            peer: None,
+            outgoing: LinkSimulator::new(settings.conditions(), seed),
+            incoming: LinkSimulator::new(settings.conditions(), !seed),
+            last_heard: Instant::now(),
+        })
+    }
+
+    // Talks only to the given peer from now on.
+    pub fn set_peer(&mut self, peer: SocketAddr) {
+        self.peer = Some(peer);
+        self.last_heard = Instant::now();
+    }
+
+    pub fn last_heard(&self) -> Instant {
+        self.last_heard
+    }
+
+    // Sends the message to the peer, or to the given address when listening.
+    pub fn send_to<T: Serialize>(&mut self, address: SocketAddr, message: &T) {
+        self.outgoing
+            .push(Instant::now(), (address, protocol::encode(message)));
+    }
+
+    pub fn send<T: Serialize>(&mut self, message: &T) {
+        if let Some(peer) = self.peer {
+            self.send_to(peer, message);
+        }
+    }
+
+    // Sends the message right away, the simulated network would hold it back,
+    // e.g. when leaving.
+    pub fn send_now<T: Serialize>(&self, message: &T) {
+        if let Some(peer) = self.peer {
+            let _ = self.socket.send_to(&protocol::encode(message), peer);
+        }
+    }
+
+    // Sends and receives the datagrams the simulated network let through,
+    // returning the messages which arrived with their senders.
+    // Once there's a peer, anybody else is ignored.
+    pub fn poll<T: DeserializeOwned>(&mut self) -> Vec<(SocketAddr, T)> {
+        let now = Instant::now();
+        while let Some((address, datagram)) = self.outgoing.pop(now) {
+            if let Err(err) = self.socket.send_to(&datagram, address) {
+                warn!("Can't send to {}: {}", address, err);
+            }
+        }
+
+        let mut buffer = vec![0; MAX_DATAGRAM_SIZE];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((length, address)) if self.peer.is_none_or(|peer| peer == address) => {
+                    self.incoming
+                        .push(now, (address, buffer[..length].to_vec()));
+                }
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                // Some systems report datagrams which didn't reach the peer on the next receive.
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        ErrorKind::ConnectionReset | ErrorKind::ConnectionRefused
+                    ) => {}
+                Err(err) => {
+                    warn!("Can't receive: {}", err);
+                    break;
+                }
+            }
+        }
+
+        let mut messages = Vec::new();
+        while let Some((address, datagram)) = self.incoming.pop(now) {
+            if let Some(message) = protocol::decode(&datagram) {
+                if self.peer == Some(address) {
+                    self.last_heard = now;
+                }
+                messages.push((address, message));
+            }
+        }
+        messages
+    }
+}
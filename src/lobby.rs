@@ -0,0 +1,425 @@
+use crate::controls::{self, ControlScheme};
+use crate::link::Link;
+use crate::menu::{next_choice, SCORE_LIMITS, TIME_LIMITS};
+use crate::online::{leave_server, playable, OnlineMatch, JOIN_INTERVAL, PEER_TIMEOUT};
+use crate::stadium::{StadiumAsset, Stadiums};
+use crate::ui::{spawn_row, spawn_screen, spawn_small_button, spawn_text};
+use crate::{despawn_all_system, GameState};
+use bevy::prelude::*;
+use rustball_core::protocol::{ClientMessage, ServerMessage, PROTOCOL_VERSION};
+use rustball_core::{ClientMatch, Lobby, MatchRules, Team};
+use std::time::Instant;
+
+// Gathers the players before a match, local ones or the ones on a server.
+pub struct Lobbies;
+
+// Lobby shown on the lobby screen.
+pub enum Room {
+    // Members are the local players, with their index in `ControlScheme` as the id.
+    Local(Lobby),
+    // Lobby of the server as it was sent last, where the client is the given member.
+    Online { member: u32, lobby: Lobby },
+}
+
+impl Room {
+    pub fn lobby(&self) -> &Lobby {
+        match self {
+            Room::Local(lobby) | Room::Online { lobby, .. } => lobby,
+        }
+    }
+
+    // Replaces the lobby, unless it's the same, so the screen isn't rebuilt for nothing.
+    pub fn set_lobby(&mut self, new: Lobby) {
+        if self.lobby() != &new {
+            match self {
+                Room::Local(lobby) | Room::Online { lobby, .. } => *lobby = new,
+            }
+        }
+    }
+
+    // Whether the local player may change the lobby, every local one does.
+    pub fn is_admin(&self) -> bool {
+        match self {
+            Room::Local(_) => true,
+            Room::Online { member, lobby } => lobby.is_admin(*member),
+        }
+    }
+
+    fn is_own(&self, id: u32) -> bool {
+        matches!(self, Room::Online { member, .. } if *member == id)
+    }
+}
+
+// Member the admin clicked, to be moved to the team clicked next.
+#[derive(Default)]
+struct SelectedMember(Option<u32>);
+
+// Root of the lobby screen, rebuilt whenever the lobby changes.
+#[derive(Component)]
+struct LobbyScreen;
+
+#[derive(Component)]
+enum LobbyItem {
+    // Column of a team, or of the spectators with `None`.
+    Team(Option<Team>),
+    Member(u32),
+    ChangePitch,
+    ScoreLimit,
+    TimeLimit,
+    Start,
+    Leave,
+}
+
+impl Plugin for Lobbies {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectedMember>()
+            .add_system_set(SystemSet::on_enter(GameState::Lobby).with_system(init_lobby_system))
+            .add_system_set(
+                SystemSet::on_update(GameState::Lobby)
+                    .with_system(lobby_system)
+                    .with_system(update_lobby_system),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Lobby).with_system(despawn_all_system));
+    }
+}
+
+// Shows the lobby of the server, or gathers the local players who chose a device.
+// They keep their teams from the last time.
+#[allow(clippy::too_many_arguments)]
+fn init_lobby_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    room: Option<Res<Room>>,
+    scheme: Res<ControlScheme>,
+    stadiums: Res<Stadiums>,
+    stadium_assets: Res<Assets<StadiumAsset>>,
+    rules: Res<MatchRules>,
+    mut selected: ResMut<SelectedMember>,
+) {
+    selected.0 = None;
+    commands.spawn_bundle(UiCameraBundle::default());
+    if let Some(room) = room
+        .as_deref()
+        .filter(|room| matches!(room, Room::Online { .. }))
+    {
+        spawn_lobby_screen(&mut commands, &asset_server, room, None);
+        return;
+    }
+
+    let previous = room.as_deref().map(Room::lobby);
+    let mut lobby = Lobby::new(stadiums.selected(&stadium_assets), *rules);
+    for (local, _) in scheme
+        .players
+        .iter()
+        .enumerate()
+        .filter(|(_, controls)| controls.is_active())
+    {
+        let id = local as u32;
+        let team = previous
+            .and_then(|lobby| lobby.member(id))
+            .map_or(Some(controls::team(local)), |member| member.team);
+        lobby.join(id, format!("Player {}", local + 1), team);
+    }
+    let room = Room::Local(lobby);
+    spawn_lobby_screen(&mut commands, &asset_server, &room, None);
+    commands.insert_resource(room);
+}
+
+// Rebuilds the screen after the lobby or the selection changed.
+fn update_lobby_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    room: Res<Room>,
+    selected: Res<SelectedMember>,
+    query: Query<Entity, With<LobbyScreen>>,
+) {
+    if !room.is_changed() && !selected.is_changed() {
+        return;
+    }
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_lobby_screen(&mut commands, &asset_server, &room, selected.0);
+}
+
+fn spawn_lobby_screen(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    room: &Room,
+    selected: Option<u32>,
+) {
+    let lobby = room.lobby();
+    let admin = room.is_admin();
+    spawn_screen(commands, Color::NONE)
+        .insert(LobbyScreen)
+        .with_children(|parent| {
+            spawn_text(parent, asset_server, "Lobby", 80.0);
+            spawn_row(parent).with_children(|parent| {
+                for team in [Some(Team::Red), None, Some(Team::Blue)] {
+                    spawn_column(parent).with_children(|parent| {
+                        spawn_small_button(
+                            parent,
+                            asset_server,
+                            team_label(team),
+                            LobbyItem::Team(team),
+                        );
+                        for member in lobby.team_members(team) {
+                            let mut label = member.name.clone();
+                            if room.is_own(member.id) {
+                                label.push_str(" (you)");
+                            }
+                            if lobby.is_admin(member.id) && matches!(room, Room::Online { .. }) {
+                                label.push_str(" (admin)");
+                            }
+                            if selected == Some(member.id) {
+                                label = format!("> {} <", label);
+                            }
+                            spawn_small_button(
+                                parent,
+                                asset_server,
+                                label,
+                                LobbyItem::Member(member.id),
+                            );
+                        }
+                    });
+                }
+            });
+
+            spawn_text(
+                parent,
+                asset_server,
+                format!(
+                    "Stadium: {}  {}",
+                    lobby.stadium.name,
+                    rules_label(&lobby.rules)
+                ),
+                28.0,
+            );
+            let help = if !admin {
+                "Waiting for the admin to start the match"
+            } else if lobby.can_start() {
+                "Click a player, then the team to move them to"
+            } else {
+                "Move somebody to a team to start the match"
+            };
+            spawn_text(parent, asset_server, help, 24.0);
+
+            if admin {
+                spawn_row(parent).with_children(|parent| {
+                    for (label, item) in [
+                        ("Change Pitch", LobbyItem::ChangePitch),
+                        ("Score Limit", LobbyItem::ScoreLimit),
+                        ("Time Limit", LobbyItem::TimeLimit),
+                    ] {
+                        spawn_small_button(parent, asset_server, label, item);
+                    }
+                });
+            }
+            spawn_row(parent).with_children(|parent| {
+                if admin {
+                    spawn_small_button(parent, asset_server, "Start", LobbyItem::Start);
+                }
+                spawn_small_button(parent, asset_server, "Leave", LobbyItem::Leave);
+            });
+        });
+}
+
+fn spawn_column<'w, 's, 'a>(
+    parent: &'a mut ChildBuilder<'w, 's, '_>,
+) -> bevy::ecs::system::EntityCommands<'w, 's, 'a> {
+    parent.spawn_bundle(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            size: Size::new(Val::Px(300.0), Val::Auto),
+            ..Default::default()
+        },
+        color: Color::NONE.into(),
+        ..Default::default()
+    })
+}
+
+fn team_label(team: Option<Team>) -> &'static str {
+    match team {
+        Some(Team::Red) => "Red",
+        Some(Team::Blue) => "Blue",
+        None => "Spectators",
+    }
+}
+
+fn rules_label(rules: &MatchRules) -> String {
+    let score_limit = match rules.score_limit {
+        Some(limit) => format!("Score Limit: {}", limit),
+        None => "Score Limit: None".to_string(),
+    };
+    let time_limit = match rules.time_limit {
+        Some(limit) => format!("Time Limit: {} min", limit),
+        None => "Time Limit: None".to_string(),
+    };
+    format!("{}  {}", score_limit, time_limit)
+}
+
+// Does the action of the pressed button, Escape leaves as well.
+// Online, the changes are asked from the server, which sends the changed lobby and starts the match.
+#[allow(clippy::too_many_arguments)]
+fn lobby_system(
+    mut commands: Commands,
+    mut kb: ResMut<Input<KeyCode>>,
+    mut app_state: ResMut<State<GameState>>,
+    mut room: ResMut<Room>,
+    mut selected: ResMut<SelectedMember>,
+    mut link: Option<ResMut<Link>>,
+    mut stadiums: ResMut<Stadiums>,
+    stadium_assets: Res<Assets<StadiumAsset>>,
+    mut rules: ResMut<MatchRules>,
+    mut rejoin: Local<Option<Instant>>,
+    query: Query<(&Interaction, &LobbyItem), Changed<Interaction>>,
+) {
+    let mut next = None;
+    let mut leaving = false;
+    if let (Room::Online { .. }, Some(link)) = (&*room, &mut link) {
+        for (_, message) in link.poll() {
+            match message {
+                ServerMessage::Welcome { lobby, .. } | ServerMessage::Lobby(lobby)
+                    if !playable(&lobby.stadium) =>
+                {
+                    leaving = true;
+                    break;
+                }
+                ServerMessage::Welcome { lobby, .. } | ServerMessage::Lobby(lobby) => {
+                    room.set_lobby(lobby);
+                }
+                ServerMessage::Start { stadium, .. } if !playable(&stadium) => {
+                    leaving = true;
+                    break;
+                }
+                ServerMessage::Start {
+                    player,
+                    stadium,
+                    rules,
+                    teams,
+                } => {
+                    let client = ClientMatch::new(player, stadium, rules, &teams);
+                    commands.insert_resource(OnlineMatch::new(client));
+                    next = Some(GameState::Online);
+                    break;
+                }
+                // A match is played and its start was lost, joining again brings it.
+                ServerMessage::Snapshot { .. } => {
+                    let now = Instant::now();
+                    if rejoin.is_none_or(|rejoin| now >= rejoin) {
+                        // The client is a member already, the password isn't checked again.
+                        link.send(&ClientMessage::Join {
+                            version: PROTOCOL_VERSION,
+                            password: None,
+                        });
+                        *rejoin = Some(now + JOIN_INTERVAL);
+                    }
+                }
+                ServerMessage::Rejected(_) => {}
+            }
+        }
+        if link.last_heard().elapsed() > PEER_TIMEOUT {
+            warn!("Lost the connection to the server");
+            leaving = true;
+        }
+    }
+
+    leaving |= kb.clear_just_pressed(KeyCode::Escape);
+    for (interaction, item) in query.iter() {
+        if interaction != &Interaction::Clicked {
+            continue;
+        }
+        if let LobbyItem::Leave = item {
+            leaving = true;
+        }
+        if !room.is_admin() {
+            continue;
+        }
+        let lobby = room.lobby();
+        match item {
+            LobbyItem::Member(id) => {
+                selected.0 = if selected.0 == Some(*id) {
+                    None
+                } else {
+                    Some(*id)
+                };
+            }
+            LobbyItem::Team(team) => {
+                if let Some(member) = selected.0.take() {
+                    let team = *team;
+                    change_lobby(
+                        &mut room,
+                        &mut link,
+                        ClientMessage::MoveMember { member, team },
+                    );
+                }
+            }
+            LobbyItem::ChangePitch => {
+                stadiums.select_next();
+                let change = ClientMessage::ChangeMatch {
+                    stadium: Box::new(stadiums.selected(&stadium_assets)),
+                    rules: lobby.rules,
+                };
+                change_lobby(&mut room, &mut link, change);
+            }
+            LobbyItem::ScoreLimit | LobbyItem::TimeLimit => {
+                let mut new_rules = lobby.rules;
+                if let LobbyItem::ScoreLimit = item {
+                    new_rules.score_limit = next_choice(&SCORE_LIMITS, new_rules.score_limit);
+                } else {
+                    new_rules.time_limit = next_choice(&TIME_LIMITS, new_rules.time_limit);
+                }
+                // The menu shows the same rules for the next local match.
+                if let Room::Local(_) = *room {
+                    *rules = new_rules;
+                }
+                let change = ClientMessage::ChangeMatch {
+                    stadium: Box::new(lobby.stadium.clone()),
+                    rules: new_rules,
+                };
+                change_lobby(&mut room, &mut link, change);
+            }
+            LobbyItem::Start => match &mut link {
+                Some(link) => link.send(&ClientMessage::StartMatch),
+                None if lobby.can_start() => next = Some(GameState::InGame),
+                None => {}
+            },
+            LobbyItem::Leave => {}
+        }
+    }
+
+    if leaving {
+        next = match &link {
+            Some(link) => {
+                leave_server(&mut commands, link);
+                Some(GameState::OnlineMenu)
+            }
+            None => Some(GameState::InMenu),
+        };
+    }
+    if let Some(next) = next {
+        app_state.set(next).expect("Something went wrong!");
+    }
+}
+
+// Changes the local lobby right away, the one of the server is changed by the server.
+fn change_lobby(room: &mut Room, link: &mut Option<ResMut<Link>>, change: ClientMessage) {
+    match room {
+        Room::Local(lobby) => match change {
+            ClientMessage::MoveMember { member, team } => {
+                lobby.move_member(member, team);
+            }
+            ClientMessage::ChangeMatch { stadium, rules } => {
+                lobby.stadium = *stadium;
+                lobby.rules = rules;
+            }
+            _ => {}
+        },
+        Room::Online { .. } => {
+            if let Some(link) = link {
+                link.send(&change);
+            }
+        }
+    }
+}
@@ -1,63 +1,120 @@
-use bevy::math::vec3;
+use bevy::core::{FixedTimestep, FixedTimesteps};
+use bevy::math::{vec2, vec3};
 use bevy::prelude::*;
-
+use rustball_core::{
+    Bot, Disc, MatchEvent, MatchRules, MatchState, PlayerInput, Replay, Team, BALL,
+};
+
+mod bots;
+mod browse;
+mod config;
+mod controls;
+mod input;
+mod link;
+mod lobby;
+mod match_over;
 mod menu;
-
-use menu::Background;
+mod online;
+mod online_menu;
+mod pause;
+mod peer;
+mod replay;
+mod settings;
+mod stadium;
+mod ui;
+
+use bots::{BotPlayer, BotSettings};
+use controls::ControlScheme;
+use input::InputDevices;
+use lobby::Room;
+use replay::Recording;
+use stadium::{StadiumAsset, Stadiums};
 
 // Assets
 const PLAYER_RED_SPRITE: &str = "player_red.png";
 const PLAYER_BLUE_SPRITE: &str = "player_blue.png";
 const BALL_SPRITE: &str = "ball.png";
-const PITCH1_SPRITE: &str = "pitch1.png";
-const PITCH2_SPRITE: &str = "pitch2.png";
-const PITCH3_SPRITE: &str = "pitch3.png";
 const FONT: &str = "fonts/FiraSans-Regular.ttf";
 
 // Constants
-const MAX_SPEED: f32 = 3.0;
-const PLAYER_RADIUS: f32 = 25.0;
-const BALL_RADIUS: f32 = 10.0;
-const CORNER_RADIUS: f32 = 10.0;
-const RED_INITIAL_X: f32 = -200.0;
-const BLUE_INITIAL_X: f32 = 200.0;
-const WINDOW_WIDTH: f32 = 1024.0;
-const WINDOW_HEIGHT: f32 = 768.0;
-const CORNER_UP_HEIGHT: f32 = 100.0;
-const CORNER_DOWN_HEIGHT: f32 = -100.0;
+const WINDOW_WIDTH: f32 = rustball_core::PITCH_WIDTH;
+const WINDOW_HEIGHT: f32 = rustball_core::PITCH_HEIGHT;
+const TICKS_PER_SECOND: f64 = rustball_core::TICKS_PER_SECOND as f64;
+const PHYSICS_TIMESTEP: &str = "physics_timestep";
+// Ring around the players showing their kick reach while they hold the kick button.
+const KICK_RING_PIECES: usize = 24;
+const KICK_RING_THICKNESS: f32 = 2.0;
+const KICK_RING_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.8);
 
 // Components
+// Index of the rendered disc in the simulated match.
 #[derive(Component)]
-pub struct Velocity {
-    pub x: f32,
-    pub y: f32,
-}
+struct DiscId(usize);
 
+// Position of the disc before the last physics tick, used for interpolation.
 #[derive(Component)]
-struct PlayerRed;
+struct PreviousPosition(Vec2);
 
+// Team and other details of the player are kept in the simulated match.
 #[derive(Component)]
-struct PlayerBlue;
+struct Player {
+    // Index of the player in the simulated match.
+    id: usize,
+}
 
+// Index of the local player controlling the player, see `ControlScheme`.
 #[derive(Component)]
-struct Ball;
+struct LocalPlayer(usize);
 
+// Piece of the ring drawn around the parent player when the kick is armed.
 #[derive(Component)]
-struct Radius(f32);
+struct KickRing;
 
 #[derive(Component)]
-pub struct Score {
-    pub red: i32,
-    pub blue: i32,
-}
+struct ScoreText;
 
 #[derive(Component)]
-struct ScoreText(String);
+struct ClockText;
+
+// Inputs of all the players for the next physics tick, in the order of players in the match.
+struct PlayerInputs(Vec<PlayerInput>);
+
+// Who controls a player of the match.
+enum Controller {
+    // Index of the local player, see `ControlScheme`.
+    Local(usize),
+    Bot(Bot),
+    Nobody,
+}
+
+// Stage running the physics with a fixed timestep,
+// so the game runs at the same speed regardless of the frame rate.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
+struct PhysicsStage;
+
+// Order of the systems within a single physics tick.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+enum PhysicsSystem {
+    Input,
+    Step,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 pub enum GameState {
     InMenu,
+    Lobby,
     InGame,
+    Paused,
+    Settings,
+    Controls,
+    MatchOver,
+    Replay,
+    OnlineMenu,
+    Browse,
+    Connecting,
+    Online,
+    PeerConnecting,
+    PeerMatch,
 }
 
 fn main() {
@@ -73,37 +130,137 @@ fn main() {
             ..Default::default()
         })
         .add_plugins(DefaultPlugins)
+        .add_plugin(stadium::StadiumAssets)
         .add_plugin(menu::Menu)
-        .add_system_set(
-            SystemSet::on_enter(GameState::InGame)
-                .with_system(init_game_system)
-                .with_system(spawn_players_system)
-                .with_system(spawn_ball_system),
+        .add_plugin(match_over::MatchOver)
+        .add_plugin(pause::Pause)
+        .add_plugin(settings::Settings)
+        .add_plugin(controls::Controls)
+        .add_plugin(input::LocalInput)
+        .add_plugin(bots::Bots)
+        .add_plugin(replay::Replays)
+        .add_plugin(lobby::Lobbies)
+        .add_plugin(online_menu::OnlineMenu)
+        .add_plugin(browse::BrowseRooms)
+        .add_plugin(online::Online)
+        .add_plugin(peer::PeerToPeer)
+        .add_event::<MatchEvent>()
+        .init_resource::<MatchRules>()
+        .add_system_set(SystemSet::on_enter(GameState::InGame).with_system(init_game_system))
+        .add_system_set(SystemSet::on_exit(GameState::InGame).with_system(despawn_all_system))
+        .add_stage_before(
+            CoreStage::Update,
+            PhysicsStage,
+            SystemStage::parallel().with_run_criteria(
+                FixedTimestep::step(1.0 / TICKS_PER_SECOND).with_label(PHYSICS_TIMESTEP),
+            ),
+        )
+        .add_system_set_to_stage(
+            PhysicsStage,
+            SystemSet::on_update(GameState::InGame)
+                .with_system(local_input_system.label(PhysicsSystem::Input))
+                .with_system(bots::bot_input_system.label(PhysicsSystem::Input))
+                .with_system(
+                    step_system
+                        .label(PhysicsSystem::Step)
+                        .after(PhysicsSystem::Input),
+                ),
+        )
+        .add_system_set_to_stage(
+            PhysicsStage,
+            SystemSet::on_update(GameState::Online).with_system(online::online_tick_system),
+        )
+        .add_system_set_to_stage(
+            PhysicsStage,
+            SystemSet::on_update(GameState::PeerMatch).with_system(peer::peer_tick_system),
         )
         .add_system_set(
             SystemSet::on_update(GameState::InGame)
-                .with_system(player_red_keyboard_system)
-                .with_system(player_blue_keyboard_system)
-                .with_system(movement_system)
-                .with_system(collision_system_red)
-                .with_system(collision_system_blue)
-                .with_system(players_collision_system)
-                .with_system(control_ball_velocity)
-                .with_system(edge_collision_system)
-                .with_system(corner_collision_system)
-                .with_system(goal_system),
+                .with_system(interpolation_system)
+                .with_system(kick_ring_system)
+                .with_system(score_system)
+                .with_system(match_over_system)
+                .with_system(clock_system),
         )
         .run();
 }
 
 // Initialize the game state.
+#[allow(clippy::too_many_arguments)]
 fn init_game_system(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    background_query: Query<(Entity, &Background)>,
+    stadiums: Res<Stadiums>,
+    stadium_assets: Res<Assets<StadiumAsset>>,
+    rules: Res<MatchRules>,
+    scheme: Res<ControlScheme>,
+    bot_settings: Res<BotSettings>,
+    room: Option<Res<Room>>,
+) {
+    // Set pitch as selected in the menu.
+    let stadium = stadiums.selected(&stadium_assets);
+
+    // The match itself is simulated by rustball-core, entities only render it.
+    // The local players play in the teams they were put in in the lobby, spectators don't play.
+    // Only the first local player plays against a bot, in their usual team.
+    // A team without any of them gets a bot or a player nobody controls.
+    let (mut teams, mut controllers): (Vec<Team>, Vec<Controller>) = match room.as_deref() {
+        Some(Room::Local(lobby)) if !bot_settings.opponent => lobby
+            .players()
+            .iter()
+            .filter_map(|member| Some((member.team?, Controller::Local(member.id as usize))))
+            .unzip(),
+        _ => scheme
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(_, controls)| controls.is_active())
+            .map(|(local, _)| (controls::team(local), Controller::Local(local)))
+            .take(if bot_settings.opponent { 1 } else { usize::MAX })
+            .unzip(),
+    };
+    for team in [Team::Red, Team::Blue] {
+        if !teams.contains(&team) {
+            let controller = if bot_settings.opponent {
+                let seed = bot_seed() ^ teams.len() as u64;
+                let bot = Bot::new(
+                    teams.len(),
+                    bot_settings.difficulty,
+                    bot_settings.role,
+                    seed,
+                );
+                Controller::Bot(bot)
+            } else {
+                Controller::Nobody
+            };
+            teams.push(team);
+            controllers.push(controller);
+        }
+    }
+    let match_state = MatchState::new(stadium, &teams, *rules);
+    spawn_match(&mut commands, &asset_server, &match_state, controllers);
+
+    commands.insert_resource(PlayerInputs(vec![
+        PlayerInput::default();
+        match_state.players.len()
+    ]));
+    commands.insert_resource(Recording(Replay::new(&match_state)));
+    commands.insert_resource(match_state);
+}
+
+// Spawns the camera, the score, the clock and the sprites rendering the match.
+fn spawn_match(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    match_state: &MatchState,
+    controllers: Vec<Controller>,
 ) {
-    // Init camera.
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+    // Init camera, zoomed to fit the stadium with its goal nets in the window.
+    let extent = match_state.stadium.half_extent() * 2.;
+    let scale = (extent.x / WINDOW_WIDTH).max(extent.y / WINDOW_HEIGHT);
+    let mut camera = OrthographicCameraBundle::new_2d();
+    camera.orthographic_projection.scale = scale;
+    commands.spawn_bundle(camera);
 
     let font = asset_server.load(FONT);
     let text_style = TextStyle {
@@ -118,429 +275,270 @@ fn init_game_system(
 
     // Show score on the screen (on the bottom left corner)
     let score_text = String::from("Score: 0–0");
-    commands.spawn_bundle(Text2dBundle {
-        text: Text {
-            sections: vec![TextSection {
-                value: score_text,
-                style: text_style,
-            }],
-            alignment: text_alignment,
-        },
-        transform: Transform::from_translation(vec3(
-            -WINDOW_WIDTH / 2. + 125.,
-            -WINDOW_HEIGHT / 2. + 50.,
-            2.0,
-        )),
-        global_transform: Default::default(),
-        text_2d_size: Default::default(),
-        text_2d_bounds: Default::default(),
-        visibility: Visibility { is_visible: true },
-    });
-    let (_, background_type) = background_query.iter().next().unwrap();
+    let clock_style = text_style.clone();
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: score_text,
+                    style: text_style,
+                }],
+                alignment: text_alignment,
+            },
+            transform: Transform {
+                translation: vec3(
+                    (-WINDOW_WIDTH / 2. + 125.) * scale,
+                    (-WINDOW_HEIGHT / 2. + 50.) * scale,
+                    2.0,
+                ),
+                scale: Vec3::splat(scale),
+                ..Default::default()
+            },
+            global_transform: Default::default(),
+            text_2d_size: Default::default(),
+            text_2d_bounds: Default::default(),
+            visibility: Visibility { is_visible: true },
+        })
+        .insert(ScoreText);
 
-    // Set pitch as selected in the menu.
-    let pitch = match background_type {
-        Background::Pitch1 => PITCH1_SPRITE,
-        Background::Pitch2 => PITCH2_SPRITE,
-        Background::Pitch3 => PITCH3_SPRITE,
-    };
-    commands.spawn_bundle(SpriteBundle {
-        texture: asset_server.load(pitch),
-        transform: Transform {
-            translation: vec3(0.0, 0.0, 1.0),
+    // Show match clock next to the score.
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::with_section("0:00", clock_style, text_alignment),
+            transform: Transform {
+                translation: vec3(
+                    (-WINDOW_WIDTH / 2. + 350.) * scale,
+                    (-WINDOW_HEIGHT / 2. + 50.) * scale,
+                    2.0,
+                ),
+                scale: Vec3::splat(scale),
+                ..Default::default()
+            },
             ..Default::default()
-        },
+        })
+        .insert(ClockText);
 
-        ..Default::default()
-    });
+    stadium::spawn_stadium(commands, asset_server, &match_state.stadium);
+    spawn_discs(commands, asset_server, match_state, controllers);
+}
 
-    // Score as a resource.
-    commands.insert_resource(Score { red: 0, blue: 0 });
+// Removes all entities of the screen, so they're not duplicated when the screen is shown again.
+fn despawn_all_system(mut commands: Commands, query: Query<Entity>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
 }
 
-// Spawns the players.
-fn spawn_players_system(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // Spawn red circle that'll be representing first player.
-    commands
-        .spawn_bundle(SpriteBundle {
-            texture: asset_server.load(PLAYER_RED_SPRITE),
-            //Move the player to the left side.
-            transform: Transform::from_translation(Vec3::new(RED_INITIAL_X, 0.0, 5.0)),
-            ..Default::default()
-        })
-        .insert(PlayerRed)
-        .insert(Velocity { x: 0.0, y: 0.0 })
-        .insert(Radius(PLAYER_RADIUS));
+fn team_sprite(team: Team) -> &'static str {
+    match team {
+        Team::Red => PLAYER_RED_SPRITE,
+        Team::Blue => PLAYER_BLUE_SPRITE,
+    }
+}
 
-    // Spawn blue circle that'll be representing second player.
-    commands
-        .spawn_bundle(SpriteBundle {
-            texture: asset_server.load(PLAYER_BLUE_SPRITE),
-            //Move the player to the right side.
-            transform: Transform::from_translation(Vec3::new(BLUE_INITIAL_X, 0.0, 5.0)),
-            ..Default::default()
-        })
-        .insert(PlayerBlue)
-        .insert(Velocity { x: 0.0, y: 0.0 })
-        .insert(Radius(PLAYER_RADIUS));
+// Sprite as big as the disc, stadiums may use different sizes than the images.
+fn disc_sprite(disc: &Disc) -> Sprite {
+    Sprite {
+        custom_size: Some(Vec2::splat(2. * disc.radius)),
+        ..Default::default()
+    }
+}
+
+// Seed making the bots play differently in every match.
+fn bot_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64)
 }
 
-fn spawn_ball_system(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // Spawn ball.
+// Spawns sprites of the ball and the players of the match.
+// Controllers are given in the order of the players in the match.
+fn spawn_discs(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    match_state: &MatchState,
+    controllers: Vec<Controller>,
+) {
+    let ball = match_state.ball();
     commands
         .spawn_bundle(SpriteBundle {
+            sprite: disc_sprite(ball),
             texture: asset_server.load(BALL_SPRITE),
-            //Move the ball to the center of the screen.
-            transform: Transform::from_translation(Vec3::new(0.0, 0.0, 5.0)),
+            transform: Transform::from_translation(ball.position.extend(5.0)),
             ..Default::default()
         })
-        .insert(Ball)
-        .insert(Velocity { x: 0.0, y: 0.0 })
-        .insert(Radius(BALL_RADIUS));
-}
-
-// Slows down the ball.
-fn control_ball_velocity(mut query: Query<&mut Velocity, With<Ball>>) {
-    // Get ball velocity.
-    let mut velocity = query.iter_mut().next().unwrap();
-
-    if velocity.x > 0. {
-        velocity.x -= 0.05;
-        velocity.x = velocity.x.max(-MAX_SPEED);
-    } else if velocity.x < 0. {
-        velocity.x += 0.05;
-        velocity.x = velocity.x.min(MAX_SPEED);
-    };
-
-    if velocity.y > 0. {
-        velocity.y -= 0.05;
-        velocity.y = velocity.y.max(-MAX_SPEED);
-    } else if velocity.y < 0. {
-        velocity.y += 0.05;
-        velocity.y = velocity.y.min(MAX_SPEED);
-    };
+        .insert(DiscId(BALL))
+        .insert(PreviousPosition(ball.position));
+
+    for ((id, player), controller) in match_state.players.iter().enumerate().zip(controllers) {
+        let disc = match_state.player_disc(id);
+        let position = disc.position;
+        let mut entity = commands.spawn_bundle(SpriteBundle {
+            sprite: disc_sprite(disc),
+            texture: asset_server.load(team_sprite(player.team)),
+            transform: Transform::from_translation(position.extend(5.0)),
+            ..Default::default()
+        });
+        entity
+            .insert(Player { id })
+            .insert(DiscId(player.disc))
+            .insert(PreviousPosition(position));
+
+        // The ring is hidden until the player holds the kick button.
+        let ring_radius = disc.radius + match_state.stadium.physics.kick_reach;
+        let ring_point = |i: usize| {
+            let angle = std::f32::consts::TAU * i as f32 / KICK_RING_PIECES as f32;
+            vec2(angle.cos(), angle.sin()) * ring_radius
+        };
+        entity.with_children(|parent| {
+            for i in 0..KICK_RING_PIECES {
+                let mut piece = stadium::line_sprite(
+                    ring_point(i),
+                    ring_point(i + 1),
+                    KICK_RING_THICKNESS,
+                    KICK_RING_COLOR,
+                    0.1,
+                );
+                piece.visibility.is_visible = false;
+                parent.spawn_bundle(piece).insert(KickRing);
+            }
+        });
+
+        match controller {
+            Controller::Local(local) => {
+                entity.insert(LocalPlayer(local));
+            }
+            Controller::Bot(bot) => {
+                entity.insert(BotPlayer(bot));
+            }
+            Controller::Nobody => {}
+        }
+    }
 }
 
-// Parses keyboard input and changes velocity of the red player.
-fn player_red_keyboard_system(
-    kb: Res<Input<KeyCode>>,
-    mut query: Query<&mut Velocity, With<PlayerRed>>,
+// Reads input of the local players from their keyboard keys or gamepads.
+fn local_input_system(
+    devices: InputDevices,
+    scheme: Res<ControlScheme>,
+    mut inputs: ResMut<PlayerInputs>,
+    query: Query<(&Player, &LocalPlayer)>,
 ) {
-    for mut velocity in query.iter_mut() {
-        if kb.pressed(KeyCode::W) {
-            velocity.y += 0.1;
-            velocity.y = velocity.y.min(MAX_SPEED);
-        } else if kb.pressed(KeyCode::S) {
-            velocity.y -= 0.1;
-            velocity.y = velocity.y.max(-MAX_SPEED);
-        } else if velocity.y > 0. {
-            velocity.y -= 0.05;
-            velocity.y = velocity.y.max(-MAX_SPEED);
-        } else if velocity.y < 0. {
-            velocity.y += 0.05;
-            velocity.y = velocity.y.min(MAX_SPEED);
-        }
-
-        if kb.pressed(KeyCode::A) {
-            velocity.x -= 0.1;
-            velocity.x = velocity.x.max(-MAX_SPEED);
-        } else if kb.pressed(KeyCode::D) {
-            velocity.x += 0.1;
-            velocity.x = velocity.x.min(MAX_SPEED);
-        } else if velocity.x > 0. {
-            velocity.x -= 0.05;
-            velocity.x = velocity.x.max(-MAX_SPEED);
-        } else if velocity.x < 0. {
-            velocity.x += 0.05;
-            velocity.x = velocity.x.min(MAX_SPEED);
+    for (player, local_player) in query.iter() {
+        if let Some(controls) = scheme.players.get(local_player.0) {
+            inputs.0[player.id] = devices.input(controls);
         }
     }
 }
 
-// Parses keyboard input and changes velocity of the blue player.
-fn player_blue_keyboard_system(
-    kb: Res<Input<KeyCode>>,
-    mut query: Query<&mut Velocity, With<PlayerBlue>>,
+// Advances the simulated match by a single tick.
+// The replay records the same inputs, until the match is won and the simulation stops.
+fn step_system(
+    mut match_state: ResMut<MatchState>,
+    inputs: Res<PlayerInputs>,
+    mut recording: ResMut<Recording>,
+    mut events: EventWriter<MatchEvent>,
+    mut query: Query<(&DiscId, &mut PreviousPosition)>,
 ) {
-    for mut velocity in query.iter_mut() {
-        if kb.pressed(KeyCode::Up) {
-            velocity.y += 0.1;
-            velocity.y = velocity.y.min(MAX_SPEED);
-        } else if kb.pressed(KeyCode::Down) {
-            velocity.y -= 0.1;
-            velocity.y = velocity.y.max(-MAX_SPEED);
-        } else if velocity.y > 0. {
-            velocity.y -= 0.05;
-            velocity.y = velocity.y.max(-MAX_SPEED);
-        } else if velocity.y < 0. {
-            velocity.y += 0.05;
-            velocity.y = velocity.y.min(MAX_SPEED);
-        }
+    remember_positions(&match_state, &mut query);
 
-        if kb.pressed(KeyCode::Left) {
-            velocity.x -= 0.1;
-            velocity.x = velocity.x.max(-MAX_SPEED);
-        } else if kb.pressed(KeyCode::Right) {
-            velocity.x += 0.1;
-            velocity.x = velocity.x.min(MAX_SPEED);
-        } else if velocity.x > 0. {
-            velocity.x -= 0.05;
-            velocity.x = velocity.x.max(-MAX_SPEED);
-        } else if velocity.x < 0. {
-            velocity.x += 0.05;
-            velocity.x = velocity.x.min(MAX_SPEED);
-        }
+    if match_state.winner.is_none() {
+        recording.0.record(&inputs.0);
     }
-}
+    let tick_events = match_state.step(&inputs.0);
 
-// Changes the position of the entities, based on their velocity.
-fn movement_system(mut query: Query<(&Velocity, &mut Transform)>) {
-    for (velocity, mut transform) in query.iter_mut() {
-        let translation = &mut transform.translation;
-        translation.x += velocity.x;
-        translation.y += velocity.y;
+    // Discs are teleported to the kickoff positions, don't interpolate their way back.
+    if tick_events
+        .iter()
+        .any(|event| matches!(event, MatchEvent::Kickoff(_)))
+    {
+        remember_positions(&match_state, &mut query);
     }
+    events.send_batch(tick_events.into_iter());
 }
 
-// Calculates new velocity vectors after collision.
-// Using some math formulas from the internet.
-// Inspired with: https://stackoverflow.com/questions/345838/ball-to-ball-collision-detection-and-handling
-fn handle_collision(
-    velocity1: &mut Velocity,
-    velocity2: &mut Velocity,
-    transform_red: &mut Transform,
-    transform_blue: &mut Transform,
-    radius1: f32,
-    radius2: f32,
+// Keeps the current positions of the discs as the ones to interpolate from.
+fn remember_positions(
+    match_state: &MatchState,
+    query: &mut Query<(&DiscId, &mut PreviousPosition)>,
 ) {
-    let delta = (transform_red.translation - transform_blue.translation).truncate();
-    let players_distance = transform_red
-        .translation
-        .distance(transform_blue.translation);
-    let d = players_distance;
-    let multiplier = (-d + radius1 + radius2) / d;
-    let delta_x = delta.x * multiplier;
-    let delta_y = delta.y * multiplier;
-    let mtd = Vec2::new(delta_x, delta_y);
-
-    let im1 = 1.;
-    let im2 = 1.;
-
-    transform_red.translation.x += mtd[0] * (im1 / (im1 + im2));
-    transform_red.translation.y += mtd[1] * (im1 / (im1 + im2));
-
-    transform_blue.translation.x -= mtd[0] * (im2 / (im1 + im2));
-    transform_blue.translation.y -= mtd[1] * (im2 / (im1 + im2));
-
-    let v = Vec2::new(velocity1.x - velocity2.x, velocity1.y - velocity2.y);
-    let vn = v.dot(mtd.normalize());
-
-    if vn > 0.0 {
-        return;
+    for (disc, mut previous_position) in query.iter_mut() {
+        previous_position.0 = match_state.discs[disc.0].position;
     }
-
-    let i = (-1.5 * vn) / (im1 + im2);
-    let impulse = mtd.normalize() * i;
-
-    velocity1.x += impulse[0] * im1;
-    velocity1.y += impulse[1] * im1;
-
-    velocity2.x -= impulse[0] * im2;
-    velocity2.y -= impulse[1] * im2;
 }
 
-// Detects collision between red player and the ball.
-fn collision_system_red(
-    mut query_red: Query<(&mut Velocity, &mut Transform, &PlayerRed), Without<Ball>>,
-    mut query_ball: Query<(&mut Velocity, &mut Transform, &Ball, Without<PlayerRed>)>,
-    kb: Res<Input<KeyCode>>,
+// Moves the sprites between the positions from the last two physics ticks,
+// so the movement looks smooth when the frame rate is higher than the tick rate.
+fn interpolation_system(
+    timesteps: Res<FixedTimesteps>,
+    match_state: Res<MatchState>,
+    mut query: Query<(&DiscId, &PreviousPosition, &mut Transform)>,
 ) {
-    let (mut velocity_red, mut transform_red, _) = query_red.iter_mut().next().unwrap();
-    let (mut velocity_ball, mut transform_ball, _, _) = query_ball.iter_mut().next().unwrap();
-
-    let player_ball_distance = transform_red
-        .translation
-        .distance(transform_ball.translation);
-    if player_ball_distance < PLAYER_RADIUS + BALL_RADIUS {
-        // If space pressed, shoot the ball
-        if kb.pressed(KeyCode::Space) {
-            let diff_x = transform_red.translation.x - transform_ball.translation.x;
-            let diff_y = transform_red.translation.y - transform_ball.translation.y;
-            let angle = diff_y.atan2(diff_x);
-            velocity_ball.y += -5.0 * angle.sin();
-            velocity_ball.x += -5.0 * angle.cos();
-        }
-        handle_collision(
-            &mut velocity_red,
-            &mut velocity_ball,
-            &mut transform_red,
-            &mut transform_ball,
-            PLAYER_RADIUS,
-            BALL_RADIUS,
-        );
-    }
+    let alpha = timesteps
+        .get(PHYSICS_TIMESTEP)
+        .map_or(1.0, |timestep| timestep.overstep_percentage() as f32);
+    move_sprites(&match_state, alpha, &mut query);
 }
 
-// Detects collision between blue player and the ball.
-fn collision_system_blue(
-    mut query_blue: Query<(&mut Velocity, &mut Transform, &PlayerBlue), Without<Ball>>,
-    mut query_ball: Query<(&mut Velocity, &mut Transform, &Ball, Without<PlayerBlue>)>,
-    kb: Res<Input<KeyCode>>,
+// Moves the sprites to the given part of the way from their previous positions to the current ones.
+fn move_sprites(
+    match_state: &MatchState,
+    alpha: f32,
+    query: &mut Query<(&DiscId, &PreviousPosition, &mut Transform)>,
 ) {
-    let (mut velocity_blue, mut transform_blue, _) = query_blue.iter_mut().next().unwrap();
-    let (mut velocity_ball, mut transform_ball, _, _) = query_ball.iter_mut().next().unwrap();
-
-    let player_ball_distance = transform_blue
-        .translation
-        .distance(transform_ball.translation);
-    if player_ball_distance < PLAYER_RADIUS + BALL_RADIUS {
-        // If right control pressed, shoot the ball.
-        if kb.pressed(KeyCode::RControl) {
-            let diff_x = transform_blue.translation.x - transform_ball.translation.x;
-            let diff_y = transform_blue.translation.y - transform_ball.translation.y;
-            let angle = diff_y.atan2(diff_x);
-            velocity_ball.y += -5.0 * angle.sin();
-            velocity_ball.x += -5.0 * angle.cos();
-        }
-
-        handle_collision(
-            &mut velocity_blue,
-            &mut velocity_ball,
-            &mut transform_blue,
-            &mut transform_ball,
-            PLAYER_RADIUS,
-            BALL_RADIUS,
-        );
+    for (disc, previous_position, mut transform) in query.iter_mut() {
+        let position = match_state.discs[disc.0].position;
+        let interpolated = previous_position.0.lerp(position, alpha);
+        transform.translation.x = interpolated.x;
+        transform.translation.y = interpolated.y;
     }
 }
 
-// Handles collision between the players.
-fn players_collision_system(
-    mut query_red: Query<(&mut Velocity, &mut Transform, &PlayerRed), Without<PlayerBlue>>,
-    mut query_blue: Query<(&mut Velocity, &mut Transform, &PlayerBlue), Without<PlayerRed>>,
+// Shows the kick ring of the players who are ready to kick.
+fn kick_ring_system(
+    match_state: Res<MatchState>,
+    players: Query<&Player>,
+    mut rings: Query<(&Parent, &mut Visibility), With<KickRing>>,
 ) {
-    let (mut velocity_red, mut transform_red, _) = query_red.iter_mut().next().unwrap();
-    let (mut velocity_blue, mut transform_blue, _) = query_blue.iter_mut().next().unwrap();
-
-    // If player red and player blue collide
-    let players_distance = transform_red
-        .translation
-        .distance(transform_blue.translation);
-    if players_distance < PLAYER_RADIUS * 2.0 {
-        handle_collision(
-            &mut velocity_red,
-            &mut velocity_blue,
-            &mut transform_red,
-            &mut transform_blue,
-            PLAYER_RADIUS,
-            PLAYER_RADIUS,
-        );
-    };
-}
-
-// Handles collision between the players and corners of the goal.
-fn corner_collision_system(mut query: Query<(&mut Velocity, &Transform, &Radius)>) {
-    let corner1 = Vec3::new(-WINDOW_WIDTH / 2., CORNER_UP_HEIGHT, 5.);
-    let corner2 = Vec3::new(WINDOW_WIDTH / 2., CORNER_UP_HEIGHT, 5.);
-    let corner3 = Vec3::new(WINDOW_WIDTH / 2., CORNER_DOWN_HEIGHT, 5.);
-    let corner4 = Vec3::new(-WINDOW_WIDTH / 2., CORNER_DOWN_HEIGHT, 5.);
-
-    for (mut velocity, transform, radius) in query.iter_mut() {
-        let radius = radius.0;
-
-        let d1 = transform.translation.distance(corner1);
-        let d2 = transform.translation.distance(corner2);
-        let d3 = transform.translation.distance(corner3);
-        let d4 = transform.translation.distance(corner4);
-
-        if d1 <= radius + CORNER_RADIUS {
-            velocity.x = -velocity.x;
-            velocity.y = -velocity.y;
-        }
-        if d2 <= radius + CORNER_RADIUS {
-            velocity.x = -velocity.x;
-            velocity.y = -velocity.y;
-        }
-        if d3 <= radius + CORNER_RADIUS {
-            velocity.x = -velocity.x;
-            velocity.y = -velocity.y;
-        }
-        if d4 <= radius + CORNER_RADIUS {
-            velocity.x = -velocity.x;
-            velocity.y = -velocity.y;
+    for (parent, mut visibility) in rings.iter_mut() {
+        if let Ok(player) = players.get(parent.0) {
+            visibility.is_visible = match_state.players[player.id].kick_armed;
         }
     }
 }
 
-// Handles collision between players and edges of the pitch.
-fn edge_collision_system(mut query: Query<(&mut Velocity, &Transform, &Radius)>) {
-    for (mut velocity, transform, radius) in query.iter_mut() {
-        let translation = transform.translation;
-        let radius = radius.0;
-
-        if (translation.x + radius >= WINDOW_WIDTH / 2.
-            || translation.x - radius <= -WINDOW_WIDTH / 2.)
-            && ((translation.y >= CORNER_UP_HEIGHT || translation.y <= CORNER_DOWN_HEIGHT)
-                || radius == PLAYER_RADIUS)
-        {
-            velocity.x = -velocity.x;
-        }
-
-        if (translation.y + radius >= WINDOW_HEIGHT / 2.
-            || translation.y - radius <= -WINDOW_HEIGHT / 2.)
-            && ((translation.y >= CORNER_UP_HEIGHT || translation.y <= CORNER_DOWN_HEIGHT)
-                || radius == PLAYER_RADIUS)
-        {
-            velocity.y = -velocity.y;
-        }
+// Updates the score text, also when a replay jumps to another moment of the match.
+fn score_system(match_state: Res<MatchState>, mut score_text: Query<&mut Text, With<ScoreText>>) {
+    let mut text = score_text.iter_mut().next().unwrap();
+    let score = format!(
+        "Score: {}–{}",
+        match_state.score.red, match_state.score.blue
+    );
+    if text.sections[0].value != score {
+        text.sections[0].value = score;
     }
 }
 
-// Check if there was a goal.
-// If there was, update the score.
-fn goal_system(
-    mut query_ball: Query<(&mut Velocity, &mut Transform, &Ball)>,
-    mut query_players: Query<(&mut Velocity, &mut Transform), Without<Ball>>,
-    mut score: ResMut<Score>,
-    mut score_text: Query<&mut Text>,
-) {
-    // Get tuple from query
-    let (mut velocity_ball, mut transform_ball, _) = query_ball.iter_mut().next().unwrap();
-
-    // Get text from score_text
-    let mut text = score_text.iter_mut().next().unwrap();
-
-    if transform_ball.translation.x >= WINDOW_WIDTH / 2.
-        || transform_ball.translation.x <= -WINDOW_WIDTH / 2.
+// Shows the result when the match is over.
+fn match_over_system(mut events: EventReader<MatchEvent>, mut app_state: ResMut<State<GameState>>) {
+    if events
+        .iter()
+        .any(|event| matches!(event, MatchEvent::Won(_)))
     {
-        if transform_ball.translation.x >= WINDOW_WIDTH / 2. {
-            score.red += 1;
-        } else {
-            score.blue += 1;
-        }
-        text.sections[0].value = format!("Score: {}–{}", score.red, score.blue);
-        transform_ball.translation.x = 0.;
-        transform_ball.translation.y = 0.;
-
-        velocity_ball.x = 0.;
-        velocity_ball.y = 0.;
-        let mut i = RED_INITIAL_X;
-        for (mut velocity, mut transform) in query_players.iter_mut() {
-            velocity.x = 0.;
-            velocity.y = 0.;
-            transform.translation.x = i;
-            i += 2.0 * BLUE_INITIAL_X;
-            transform.translation.y = 0.;
-        }
+        app_state
+            .set(GameState::MatchOver)
+            .expect("Something went wrong!");
     }
+}
 
-    if score.red == 3 {
-        score.red = 0;
-        score.blue = 0;
-        text.sections[0].value = "Red Wins!".to_string();
-    } else if score.blue == 3 {
-        score.red = 0;
-        score.blue = 0;
-        text.sections[0].value = "Blue Wins!".to_string();
-    }
+// Shows time since the start of the match.
+fn clock_system(match_state: Res<MatchState>, mut clock_text: Query<&mut Text, With<ClockText>>) {
+    let mut text = clock_text.iter_mut().next().unwrap();
+    let seconds = match_state.elapsed_seconds();
+    let clock = format!("{}:{:02}", seconds / 60, seconds % 60);
+
+    text.sections[0].value = if match_state.overtime {
+        format!("OT {}", clock)
+    } else {
+        clock
+    };
 }
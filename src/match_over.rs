@@ -0,0 +1,99 @@
+use crate::ui::{spawn_button, spawn_screen, spawn_text};
+use crate::{despawn_all_system, GameState};
+use bevy::prelude::*;
+use rustball_core::{MatchState, Team};
+
+pub struct MatchOver;
+
+#[derive(Component)]
+enum MatchOverItem {
+    Rematch,
+    BackToMenu,
+}
+
+impl Plugin for MatchOver {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(GameState::MatchOver).with_system(init_match_over_system),
+        )
+        .add_system_set(SystemSet::on_update(GameState::MatchOver).with_system(handle_buttons))
+        .add_system_set(SystemSet::on_exit(GameState::MatchOver).with_system(despawn_all_system));
+    }
+}
+
+// Do the action given by the pressed button.
+fn handle_buttons(
+    mut app_state: ResMut<State<GameState>>,
+    query: Query<(&Interaction, &MatchOverItem), Changed<Interaction>>,
+) {
+    for (interaction, item) in query.iter() {
+        if interaction == &Interaction::Clicked {
+            let next_state = match item {
+                MatchOverItem::Rematch => GameState::InGame,
+                MatchOverItem::BackToMenu => GameState::InMenu,
+            };
+            app_state.set(next_state).expect("Something went wrong!");
+        }
+    }
+}
+
+pub fn team_name(team: Team) -> &'static str {
+    match team {
+        Team::Red => "Red",
+        Team::Blue => "Blue",
+    }
+}
+
+// Shows the winner, the final score and the stats of the players.
+fn init_match_over_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    match_state: Res<MatchState>,
+) {
+    commands.spawn_bundle(UiCameraBundle::default());
+
+    let winner = match match_state.winner {
+        Some(team) => format!("{} Wins!", team_name(team)),
+        None => "Match Over".to_string(),
+    };
+    let score = format!(
+        "Score: {}–{}",
+        match_state.score.red, match_state.score.blue
+    );
+
+    spawn_screen(&mut commands, Color::NONE).with_children(|parent| {
+        spawn_text(parent, &asset_server, winner, 80.0);
+        spawn_text(parent, &asset_server, score, 60.0);
+
+        for player in match_state.players.iter() {
+            let stats = format!(
+                "{} {}: {} goals, {} own goals, {} kicks",
+                team_name(player.team),
+                player.slot + 1,
+                player.stats.goals,
+                player.stats.own_goals,
+                player.stats.kicks
+            );
+            spawn_text(parent, &asset_server, stats, 30.0);
+        }
+
+        parent
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    align_self: AlignSelf::Center,
+                    ..Default::default()
+                },
+                color: Color::NONE.into(),
+                ..Default::default()
+            })
+            .with_children(|parent| {
+                spawn_button(parent, &asset_server, "Rematch", MatchOverItem::Rematch);
+                spawn_button(
+                    parent,
+                    &asset_server,
+                    "Back to Menu",
+                    MatchOverItem::BackToMenu,
+                );
+            });
+    });
+}
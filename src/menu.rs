@@ -1,79 +1,104 @@
-use crate::{GameState, FONT};
-use crate::{PITCH1_SPRITE, PITCH2_SPRITE, PITCH3_SPRITE};
+use crate::bots::BotSettings;
+use crate::stadium::{background_texture, StadiumAsset, Stadiums};
+use crate::{despawn_all_system, GameState, FONT};
 use bevy::app::AppExit;
 use bevy::{prelude::*, ui::FocusPolicy};
+use rustball_core::{BotRole, Difficulty, MatchRules};
 
 pub struct Menu;
 
+// Options the match rules can be switched between.
+pub const SCORE_LIMITS: [Option<u32>; 6] = [Some(1), Some(2), Some(3), Some(5), Some(10), None];
+pub const TIME_LIMITS: [Option<u32>; 6] = [Some(1), Some(2), Some(3), Some(5), Some(10), None];
+
 #[derive(Component)]
 enum MenuItem {
     Start,
+    PlayVsBot,
+    PlayOnline,
+    BotDifficulty,
+    BotRole,
     ChangePitch,
+    ScoreLimit,
+    TimeLimit,
+    Replays,
+    Controls,
     Quit,
 }
 
-#[derive(Component, Clone)]
-pub enum Background {
-    Pitch1,
-    Pitch2,
-    Pitch3,
-}
+// Image showing the selected stadium behind the buttons.
+#[derive(Component)]
+struct MenuBackground;
 
 impl Plugin for Menu {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(init_menu_system)
+        app.add_system_set(SystemSet::on_enter(GameState::InMenu).with_system(init_menu_system))
             .add_system(handle_buttons)
-            .add_system_set(SystemSet::on_exit(GameState::InMenu).with_system(despawn_menu));
+            .add_system(update_labels)
+            .add_system_set(
+                SystemSet::on_update(GameState::InMenu).with_system(update_background_system),
+            )
+            .add_system_set(SystemSet::on_resume(GameState::InMenu).with_system(init_menu_system))
+            .add_system_set(SystemSet::on_pause(GameState::InMenu).with_system(despawn_all_system))
+            .add_system_set(SystemSet::on_exit(GameState::InMenu).with_system(despawn_all_system));
     }
 }
 
-// Removes all components of a menu (except Background).
-fn despawn_menu(
-    mut commands: Commands,
-    query: Query<Entity>,
-    query_background: Query<&Background>,
-) {
-    let background = query_background.iter().next().unwrap();
-    for ent in query.iter() {
-        commands.entity(ent).despawn_recursive();
-    }
-    commands.spawn().insert((*background).clone());
-}
-
 // Do the action given by the pressed button.
 fn handle_buttons(
     mut app_exit_events: EventWriter<AppExit>,
     mut app_state: ResMut<State<GameState>>,
     query: Query<(&Interaction, &MenuItem), Changed<Interaction>>,
-    mut query_background: Query<(&mut Background, &mut UiImage)>,
-    asset_server: Res<AssetServer>,
+    mut stadiums: ResMut<Stadiums>,
+    mut rules: ResMut<MatchRules>,
+    mut bot_settings: ResMut<BotSettings>,
 ) {
     // If button clicked, change state
     for (interaction, item) in query.iter() {
         if interaction == &Interaction::Clicked {
             match item {
                 MenuItem::Start => {
+                    bot_settings.opponent = false;
+                    app_state
+                        .set(GameState::Lobby)
+                        .expect("Something went wrong!");
+                }
+                MenuItem::PlayVsBot => {
+                    bot_settings.opponent = true;
                     app_state
                         .set(GameState::InGame)
                         .expect("Something went wrong!");
                 }
+                MenuItem::PlayOnline => {
+                    app_state
+                        .set(GameState::OnlineMenu)
+                        .expect("Something went wrong!");
+                }
+                MenuItem::BotDifficulty => {
+                    bot_settings.difficulty =
+                        next_choice(&Difficulty::ALL, bot_settings.difficulty);
+                }
+                MenuItem::BotRole => {
+                    bot_settings.role = next_choice(&BotRole::ALL, bot_settings.role);
+                }
                 MenuItem::ChangePitch => {
-                    let (mut pitch_type, mut pitch_image) =
-                        query_background.iter_mut().next().unwrap();
-                    match *pitch_type {
-                        Background::Pitch1 => {
-                            *pitch_type = Background::Pitch2;
-                            *pitch_image = asset_server.load(PITCH2_SPRITE).into();
-                        }
-                        Background::Pitch2 => {
-                            *pitch_type = Background::Pitch3;
-                            *pitch_image = asset_server.load(PITCH3_SPRITE).into();
-                        }
-                        Background::Pitch3 => {
-                            *pitch_type = Background::Pitch1;
-                            *pitch_image = asset_server.load(PITCH1_SPRITE).into();
-                        }
-                    }
+                    stadiums.select_next();
+                }
+                MenuItem::ScoreLimit => {
+                    rules.score_limit = next_choice(&SCORE_LIMITS, rules.score_limit);
+                }
+                MenuItem::TimeLimit => {
+                    rules.time_limit = next_choice(&TIME_LIMITS, rules.time_limit);
+                }
+                MenuItem::Replays => {
+                    app_state
+                        .set(GameState::Replay)
+                        .expect("Something went wrong!");
+                }
+                MenuItem::Controls => {
+                    app_state
+                        .push(GameState::Controls)
+                        .expect("Something went wrong!");
                 }
                 MenuItem::Quit => {
                     app_exit_events.send(AppExit);
@@ -83,15 +108,69 @@ fn handle_buttons(
     }
 }
 
+// Returns the choice following the current one, wrapping around.
+pub fn next_choice<T: Copy + PartialEq>(choices: &[T], current: T) -> T {
+    let index = choices.iter().position(|&choice| choice == current);
+    choices[index.map_or(0, |index| (index + 1) % choices.len())]
+}
+
+// Text shown on the button.
+fn button_label(item: &MenuItem, rules: &MatchRules, bot_settings: &BotSettings) -> String {
+    match item {
+        MenuItem::Start => "Start".to_string(),
+        MenuItem::PlayVsBot => "Play vs Bot".to_string(),
+        MenuItem::PlayOnline => "Play Online".to_string(),
+        MenuItem::BotDifficulty => format!("Bot: {:?}", bot_settings.difficulty),
+        MenuItem::BotRole => format!("Bot Role: {:?}", bot_settings.role),
+        MenuItem::ChangePitch => "Change Pitch".to_string(),
+        MenuItem::ScoreLimit => match rules.score_limit {
+            Some(limit) => format!("Score Limit: {}", limit),
+            None => "Score Limit: None".to_string(),
+        },
+        MenuItem::TimeLimit => match rules.time_limit {
+            Some(limit) => format!("Time Limit: {} min", limit),
+            None => "Time Limit: None".to_string(),
+        },
+        MenuItem::Replays => "Replays".to_string(),
+        MenuItem::Controls => "Controls".to_string(),
+        MenuItem::Quit => "Quit".to_string(),
+    }
+}
+
+// Updates labels of the buttons after the rules or the bot settings were changed.
+fn update_labels(
+    rules: Res<MatchRules>,
+    bot_settings: Res<BotSettings>,
+    query: Query<(&MenuItem, &Children)>,
+    mut query_text: Query<&mut Text>,
+) {
+    if !rules.is_changed() && !bot_settings.is_changed() {
+        return;
+    }
+    for (item, children) in query.iter() {
+        for &child in children.iter() {
+            if let Ok(mut text) = query_text.get_mut(child) {
+                text.sections[0].value = button_label(item, &rules, &bot_settings);
+            }
+        }
+    }
+}
+
 // Spawns new button as the child of the given parent.
-fn spawn_button(parent: &mut ChildBuilder, asset_server: &Res<AssetServer>, item: MenuItem) {
+fn spawn_button(
+    parent: &mut ChildBuilder,
+    asset_server: &Res<AssetServer>,
+    rules: &MatchRules,
+    bot_settings: &BotSettings,
+    item: MenuItem,
+) {
     parent
         .spawn_bundle(ButtonBundle {
             style: Style {
                 align_self: AlignSelf::Center,
                 align_items: AlignItems::Center,
                 justify_content: JustifyContent::Center,
-                size: Size::new(Val::Percent(20.0), Val::Percent(10.0)),
+                size: Size::new(Val::Percent(30.0), Val::Percent(8.0)),
                 margin: Rect::all(Val::Auto),
                 ..Default::default()
             },
@@ -101,11 +180,7 @@ fn spawn_button(parent: &mut ChildBuilder, asset_server: &Res<AssetServer>, item
         .with_children(|parent| {
             parent.spawn_bundle(TextBundle {
                 text: Text::with_section(
-                    match item {
-                        MenuItem::Start => "Start",
-                        MenuItem::ChangePitch => "Change Pitch",
-                        MenuItem::Quit => "Quit",
-                    },
+                    button_label(&item, rules, bot_settings),
                     TextStyle {
                         font: asset_server.load(FONT),
                         font_size: 40.0,
@@ -120,22 +195,59 @@ fn spawn_button(parent: &mut ChildBuilder, asset_server: &Res<AssetServer>, item
         .insert(item);
 }
 
+// Shows the selected stadium in the background,
+// also after it finished loading or was changed on the disk.
+fn update_background_system(
+    asset_server: Res<AssetServer>,
+    stadiums: Res<Stadiums>,
+    stadium_assets: Res<Assets<StadiumAsset>>,
+    mut asset_events: EventReader<AssetEvent<StadiumAsset>>,
+    mut query: Query<(&mut UiImage, &mut UiColor), With<MenuBackground>>,
+) {
+    let assets_changed = asset_events.iter().count() > 0;
+    if !stadiums.is_changed() && !assets_changed {
+        return;
+    }
+
+    let stadium = stadiums.selected(&stadium_assets);
+    let (texture, color) = background_texture(&stadium.background, &asset_server);
+    for (mut image, mut ui_color) in query.iter_mut() {
+        *image = texture.clone().into();
+        *ui_color = color.into();
+    }
+}
+
 // Spawns background image as the child of the given parent.
-fn spawn_background(parent: &mut ChildBuilder, asset_server: &Res<AssetServer>) {
+fn spawn_background(
+    parent: &mut ChildBuilder,
+    asset_server: &Res<AssetServer>,
+    stadiums: &Stadiums,
+    stadium_assets: &Assets<StadiumAsset>,
+) {
+    let stadium = stadiums.selected(stadium_assets);
+    let (texture, color) = background_texture(&stadium.background, asset_server);
     parent
         .spawn_bundle(ImageBundle {
             style: Style {
                 size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
                 ..Default::default()
             },
-            image: asset_server.load(PITCH1_SPRITE).into(),
+            image: texture.into(),
+            color: color.into(),
             ..Default::default()
         })
-        .insert(Background::Pitch1);
+        .insert(MenuBackground);
 }
 
 // Creates simple menu.
-fn init_menu_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn init_menu_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    rules: Res<MatchRules>,
+    bot_settings: Res<BotSettings>,
+    stadiums: Res<Stadiums>,
+    stadium_assets: Res<Assets<StadiumAsset>>,
+) {
     commands.spawn_bundle(UiCameraBundle::default());
 
     commands
@@ -148,16 +260,28 @@ fn init_menu_system(mut commands: Commands, asset_server: Res<AssetServer>) {
                 ..Default::default()
             },
             color: Color::NONE.into(),
-            image: UiImage(asset_server.load(PITCH1_SPRITE)),
+            image: Default::default(),
             focus_policy: Default::default(),
             transform: Default::default(),
             global_transform: Default::default(),
             visibility: Visibility { is_visible: true },
         })
         .with_children(|parent| {
-            spawn_background(parent, &asset_server);
-            spawn_button(parent, &asset_server, MenuItem::Start);
-            spawn_button(parent, &asset_server, MenuItem::ChangePitch);
-            spawn_button(parent, &asset_server, MenuItem::Quit);
+            spawn_background(parent, &asset_server, &stadiums, &stadium_assets);
+            for item in [
+                MenuItem::Start,
+                MenuItem::PlayVsBot,
+                MenuItem::PlayOnline,
+                MenuItem::BotDifficulty,
+                MenuItem::BotRole,
+                MenuItem::ChangePitch,
+                MenuItem::ScoreLimit,
+                MenuItem::TimeLimit,
+                MenuItem::Replays,
+                MenuItem::Controls,
+                MenuItem::Quit,
+            ] {
+                spawn_button(parent, &asset_server, &rules, &bot_settings, item);
+            }
         });
 }
@@ -0,0 +1,509 @@
+use crate::controls::ControlScheme;
+use crate::input::InputDevices;
+use crate::link::Link;
+use crate::lobby::Room;
+use crate::match_over::team_name;
+use crate::ui::{spawn_button, spawn_screen, spawn_text, TEXT_COLOR};
+use crate::{clock_system, config, despawn_all_system, interpolation_system, kick_ring_system};
+use crate::{remember_positions, score_system, spawn_match, Controller};
+use crate::{DiscId, GameState, PreviousPosition, FONT};
+use bevy::prelude::*;
+use rustball_core::protocol::{ClientMessage, ServerMessage, PROTOCOL_VERSION};
+use rustball_core::protocol::{Location, DEFAULT_MASTER_PORT, DEFAULT_PEER_PORT, DEFAULT_PORT};
+use rustball_core::TICKS_PER_SECOND;
+use rustball_core::{ClientMatch, MatchState, NetworkConditions, PlayerInput, Stadium};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+// Plays matches on a server, predicting the local player so it reacts without delay.
+// Between the matches everybody waits in the lobby of the server, see `Room`.
+pub struct Online;
+
+const NETWORK_FILE: &str = "network.ron";
+// The join request is sent again after this long, it may have been lost.
+pub const JOIN_INTERVAL: Duration = Duration::from_millis(500);
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// The other side is considered gone after it's silent for this long.
+pub const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+pub const HELP: &str = "Esc: leave";
+
+// Server to play on and the network conditions to simulate, saved in the configuration.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    // Address of the server with its port, e.g. `127.0.0.1:5195`.
+    pub server: String,
+    // Password of the server, or of a locked room joined from the room list.
+    pub password: Option<String>,
+    // Master server listing the rooms.
+    pub master: String,
+    // Where the player is, the room list measures the distances from it.
+    pub location: Location,
+    // Address of the peer hosting a peer-to-peer match to join.
+    pub peer: String,
+    // Port to host peer-to-peer matches on.
+    pub host_port: u16,
+    // Simulated delay of the datagrams in milliseconds, each way.
+    // The latency changes randomly by up to a quarter of it.
+    pub latency: u32,
+    // Simulated percentage of the datagrams lost, each way.
+    pub packet_loss: u32,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        NetworkSettings {
+            server: format!("127.0.0.1:{}", DEFAULT_PORT),
+            password: None,
+            master: format!("127.0.0.1:{}", DEFAULT_MASTER_PORT),
+            location: Location::default(),
+            peer: format!("127.0.0.1:{}", DEFAULT_PEER_PORT),
+            host_port: DEFAULT_PEER_PORT,
+            latency: 0,
+            packet_loss: 0,
+        }
+    }
+}
+
+impl NetworkSettings {
+    pub fn save(&self) {
+        config::save(NETWORK_FILE, self);
+    }
+
+    pub fn conditions(&self) -> NetworkConditions {
+        let latency = Duration::from_millis(self.latency as u64);
+        NetworkConditions {
+            latency,
+            jitter: latency / 4,
+            packet_loss: self.packet_loss as f32 / 100.,
+        }
+    }
+}
+
+// Room chosen in the room list, joined instead of the server in the settings.
+pub struct JoinRoom(pub String);
+
+// Joining the server, shown on the connecting screen.
+struct Connecting {
+    server: String,
+    // `None` if the connection couldn't be opened or joining failed.
+    link: Option<Link>,
+    // What's going on, or why joining failed.
+    status: String,
+    next_join: Instant,
+    deadline: Instant,
+}
+
+// Online match the local player plays or watches.
+pub struct OnlineMatch {
+    client: ClientMatch,
+    // Whether the server went back to the lobby.
+    ended: bool,
+    // Whether the server sent a lobby the client can't show, it leaves then.
+    invalid: bool,
+}
+
+impl OnlineMatch {
+    pub fn new(client: ClientMatch) -> Self {
+        OnlineMatch {
+            client,
+            ended: false,
+            invalid: false,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct ConnectingText;
+
+#[derive(Component)]
+pub enum ConnectingItem {
+    Cancel,
+}
+
+// Text with the state of the connection, shown over the match.
+#[derive(Component)]
+pub struct StatusText;
+
+impl Plugin for Online {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(config::load::<NetworkSettings>(NETWORK_FILE))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Connecting).with_system(init_connecting_system),
+            )
+            .add_system_set(SystemSet::on_update(GameState::Connecting).with_system(join_system))
+            .add_system_set(
+                SystemSet::on_exit(GameState::Connecting).with_system(exit_connecting_system),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Online).with_system(init_online_system))
+            .add_system_set(
+                SystemSet::on_update(GameState::Online)
+                    .with_system(leave_system)
+                    .with_system(status_system)
+                    .with_system(interpolation_system)
+                    .with_system(kick_ring_system)
+                    .with_system(score_system)
+                    .with_system(clock_system),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Online)
+                    .with_system(exit_online_system)
+                    .with_system(despawn_all_system),
+            );
+    }
+}
+
+// Opens the connection and shows the connecting screen.
+fn init_connecting_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<NetworkSettings>,
+    room: Option<Res<JoinRoom>>,
+) {
+    let now = Instant::now();
+    let server = match room {
+        Some(room) => {
+            commands.remove_resource::<JoinRoom>();
+            room.0.clone()
+        }
+        None => settings.server.clone(),
+    };
+    let (link, status) = match Link::connect(&server, &settings) {
+        Ok(link) => (Some(link), format!("Connecting to {}...", server)),
+        Err(err) => (None, format!("Can't connect to {}: {}", server, err)),
+    };
+
+    spawn_connecting_screen(&mut commands, &asset_server, &status);
+    commands.insert_resource(Connecting {
+        server,
+        link,
+        status,
+        next_join: now,
+        deadline: now + CONNECT_TIMEOUT,
+    });
+}
+
+// Screen showing how joining goes, with a button to cancel it.
+pub fn spawn_connecting_screen(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    status: &str,
+) {
+    commands.spawn_bundle(UiCameraBundle::default());
+    spawn_screen(commands, Color::NONE).with_children(|parent| {
+        spawn_text(parent, asset_server, "Online", 80.0);
+        parent
+            .spawn_bundle(TextBundle {
+                style: Style {
+                    align_self: AlignSelf::Center,
+                    margin: Rect::all(Val::Px(5.0)),
+                    ..Default::default()
+                },
+                text: Text::with_section(
+                    status,
+                    TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size: 32.0,
+                        color: TEXT_COLOR,
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            })
+            .insert(ConnectingText);
+        spawn_button(parent, asset_server, "Cancel", ConnectingItem::Cancel);
+    });
+}
+
+// Whether Cancel or Escape was pressed on the connecting screen.
+pub fn connecting_cancelled(
+    kb: &mut Input<KeyCode>,
+    buttons: &Query<(&Interaction, &ConnectingItem), Changed<Interaction>>,
+) -> bool {
+    let cancel = buttons.iter().any(|(interaction, item)| {
+        interaction == &Interaction::Clicked && matches!(item, ConnectingItem::Cancel)
+    });
+    kb.clear_just_pressed(KeyCode::Escape) || cancel
+}
+
+pub fn show_connecting_status(status: &str, query: &mut Query<&mut Text, With<ConnectingText>>) {
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value = status.to_string();
+        }
+    }
+}
+
+// Asks the server to join until it answers, the welcome opens its lobby.
+// Cancel or Escape go back to the online menu.
+fn join_system(
+    mut commands: Commands,
+    mut kb: ResMut<Input<KeyCode>>,
+    mut app_state: ResMut<State<GameState>>,
+    settings: Res<NetworkSettings>,
+    mut connecting: ResMut<Connecting>,
+    buttons: Query<(&Interaction, &ConnectingItem), Changed<Interaction>>,
+    mut query: Query<&mut Text, With<ConnectingText>>,
+) {
+    if connecting_cancelled(&mut kb, &buttons) {
+        app_state
+            .set(GameState::OnlineMenu)
+            .expect("Something went wrong!");
+        return;
+    }
+
+    let connecting = &mut *connecting;
+    if let Some(link) = &mut connecting.link {
+        let now = Instant::now();
+        for (_, message) in link.poll() {
+            match message {
+                ServerMessage::Welcome { lobby, .. } if !playable(&lobby.stadium) => {
+                    link.send_now(&ClientMessage::Leave);
+                    connecting.status = "The server sent an invalid stadium".to_string();
+                    connecting.link = None;
+                    break;
+                }
+                ServerMessage::Welcome { member, lobby } => {
+                    commands.insert_resource(Room::Online { member, lobby });
+                    commands.insert_resource(connecting.link.take().unwrap());
+                    app_state
+                        .set(GameState::Lobby)
+                        .expect("Something went wrong!");
+                    return;
+                }
+                ServerMessage::Rejected(reason) => {
+                    connecting.status = format!("The server refused: {}", reason);
+                    connecting.link = None;
+                    break;
+                }
+                ServerMessage::Lobby(_)
+                | ServerMessage::Start { .. }
+                | ServerMessage::Snapshot { .. } => {}
+            }
+        }
+        if let Some(link) = &mut connecting.link {
+            if now >= connecting.deadline {
+                connecting.status = format!("No answer from {}", connecting.server);
+                connecting.link = None;
+            } else if now >= connecting.next_join {
+                link.send(&ClientMessage::Join {
+                    version: PROTOCOL_VERSION,
+                    password: settings.password.clone(),
+                });
+                connecting.next_join = now + JOIN_INTERVAL;
+            }
+        }
+    }
+
+    show_connecting_status(&connecting.status, &mut query);
+}
+
+fn exit_connecting_system(
+    mut commands: Commands,
+    connecting: Res<Connecting>,
+    query: Query<Entity>,
+) {
+    // The server may have let the client join already.
+    if let Some(link) = &connecting.link {
+        link.send_now(&ClientMessage::Leave);
+    }
+    commands.remove_resource::<Connecting>();
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn init_online_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    online: Res<OnlineMatch>,
+) {
+    spawn_online_match(&mut commands, &asset_server, online.client.display_state());
+}
+
+// Spawns the match played over the network, nobody is controlled through the entities.
+pub fn spawn_online_match(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    match_state: MatchState,
+) {
+    let controllers = match_state
+        .players
+        .iter()
+        .map(|_| Controller::Nobody)
+        .collect();
+    spawn_match(commands, asset_server, &match_state, controllers);
+    commands.insert_resource(match_state);
+
+    commands.spawn_bundle(UiCameraBundle::default());
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                String::new(),
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: 24.0,
+                    color: TEXT_COLOR,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(StatusText);
+}
+
+// Sends the input of the first local player and predicts the tick,
+// then shows the match as the server and the prediction see it.
+pub fn online_tick_system(
+    devices: InputDevices,
+    scheme: Res<ControlScheme>,
+    mut link: ResMut<Link>,
+    mut online: ResMut<OnlineMatch>,
+    mut room: ResMut<Room>,
+    mut match_state: ResMut<MatchState>,
+    mut positions: Query<(&DiscId, &mut PreviousPosition)>,
+) {
+    if online.ended {
+        return;
+    }
+    for (_, message) in link.poll() {
+        match message {
+            ServerMessage::Snapshot {
+                sequence,
+                snapshot,
+                last_input,
+            } => online
+                .client
+                .receive_snapshot(sequence, snapshot, last_input),
+            ServerMessage::Lobby(lobby) if !playable(&lobby.stadium) => {
+                online.invalid = true;
+                return;
+            }
+            ServerMessage::Lobby(lobby) => {
+                room.set_lobby(lobby);
+                online.ended = true;
+                return;
+            }
+            // The lobby was lost and the next match started already, the lobby asks for it again.
+            ServerMessage::Start { .. } => {
+                online.ended = true;
+                return;
+            }
+            ServerMessage::Welcome { .. } | ServerMessage::Rejected(_) => {}
+        }
+    }
+
+    let input = first_local_input(&devices, &scheme);
+    if let Some(message) = online.client.step(input) {
+        link.send(&message);
+    }
+    show_match(
+        &mut match_state,
+        online.client.display_state(),
+        &mut positions,
+    );
+}
+
+// Input of the first local player with a device, who plays the online matches.
+pub fn first_local_input(devices: &InputDevices, scheme: &ControlScheme) -> PlayerInput {
+    scheme
+        .players
+        .iter()
+        .find(|controls| controls.is_active())
+        .map_or_else(PlayerInput::default, |controls| devices.input(controls))
+}
+
+// Replaces the shown match, the sprites move from where they were.
+pub fn show_match(
+    match_state: &mut MatchState,
+    next: MatchState,
+    positions: &mut Query<(&DiscId, &mut PreviousPosition)>,
+) {
+    remember_positions(match_state, positions);
+    let previous = std::mem::replace(match_state, next);
+    // Discs are teleported after goals and when the next match starts, don't interpolate there.
+    if previous.score != match_state.score || previous.tick > match_state.tick {
+        remember_positions(match_state, positions);
+    }
+}
+
+// Escape leaves the server, so does losing it. Everybody goes back to the lobby after the match.
+fn leave_system(
+    mut commands: Commands,
+    mut kb: ResMut<Input<KeyCode>>,
+    mut app_state: ResMut<State<GameState>>,
+    link: Res<Link>,
+    online: Res<OnlineMatch>,
+) {
+    let timed_out = link.last_heard().elapsed() > PEER_TIMEOUT;
+    if timed_out {
+        warn!("Lost the connection to the server");
+    }
+    let next = if kb.clear_just_pressed(KeyCode::Escape) || timed_out || online.invalid {
+        leave_server(&mut commands, &link);
+        GameState::OnlineMenu
+    } else if online.ended {
+        GameState::Lobby
+    } else {
+        return;
+    };
+    app_state.set(next).expect("Something went wrong!");
+}
+
+// Whether a match can be played on the stadium the other side sent, a broken one would panic.
+// Clients leave the servers and peers sending such stadiums.
+pub fn playable(stadium: &Stadium) -> bool {
+    match stadium.validate() {
+        Ok(()) => true,
+        Err(err) => {
+            warn!("Leaving, got an {}", err);
+            false
+        }
+    }
+}
+
+// Tells the server the client left and forgets it.
+pub fn leave_server(commands: &mut Commands, link: &Link) {
+    link.send_now(&ClientMessage::Leave);
+    commands.remove_resource::<Link>();
+    commands.remove_resource::<Room>();
+}
+
+fn exit_online_system(mut commands: Commands) {
+    commands.remove_resource::<OnlineMatch>();
+}
+
+// Shows the ping, the controls and the winner of the finished match.
+fn status_system(online: Res<OnlineMatch>, mut query: Query<&mut Text, With<StatusText>>) {
+    let mut status = if online.client.player().is_some() {
+        // Inputs wait for the server about the round trip time.
+        let ping = online.client.pending_inputs() as u32 * 1000 / TICKS_PER_SECOND;
+        format!("Ping: {} ms  {}", ping, HELP)
+    } else {
+        format!("Spectating  {}", HELP)
+    };
+    if let Some(winner) = online.client.server_state().winner {
+        status.push_str(&format!(
+            "\n{} team wins! Back to the lobby soon.",
+            team_name(winner)
+        ));
+    }
+    show_status(&status, &mut query);
+}
+
+pub fn show_status(status: &str, query: &mut Query<&mut Text, With<StatusText>>) {
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value = status.to_string();
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use crate::online::NetworkSettings;
+use crate::peer::PeerRole;
+use crate::ui::{spawn_button, spawn_screen, spawn_text};
+use crate::{despawn_all_system, GameState};
+use bevy::prelude::*;
+
+// Chooses between playing on a server, one from the room list
+// and a peer-to-peer match with another player.
+pub struct OnlineMenu;
+
+#[derive(Component)]
+enum OnlineMenuItem {
+    JoinServer,
+    BrowseRooms,
+    HostPeer,
+    JoinPeer,
+    Back,
+}
+
+impl Plugin for OnlineMenu {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(GameState::OnlineMenu).with_system(init_online_menu_system),
+        )
+        .add_system_set(SystemSet::on_update(GameState::OnlineMenu).with_system(handle_buttons))
+        .add_system_set(SystemSet::on_exit(GameState::OnlineMenu).with_system(despawn_all_system));
+    }
+}
+
+fn init_online_menu_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<NetworkSettings>,
+) {
+    commands.spawn_bundle(UiCameraBundle::default());
+    spawn_screen(&mut commands, Color::NONE).with_children(|parent| {
+        spawn_text(parent, &asset_server, "Online", 80.0);
+        // The addresses are changed in network.ron.
+        spawn_text(
+            parent,
+            &asset_server,
+            format!(
+                "Server: {}  Peer: {}  Hosting on port {}",
+                settings.server, settings.peer, settings.host_port
+            ),
+            24.0,
+        );
+        spawn_button(
+            parent,
+            &asset_server,
+            "Join Server",
+            OnlineMenuItem::JoinServer,
+        );
+        spawn_button(
+            parent,
+            &asset_server,
+            "Browse Rooms",
+            OnlineMenuItem::BrowseRooms,
+        );
+        spawn_button(parent, &asset_server, "Host 1v1", OnlineMenuItem::HostPeer);
+        spawn_button(parent, &asset_server, "Join 1v1", OnlineMenuItem::JoinPeer);
+        spawn_button(parent, &asset_server, "Back", OnlineMenuItem::Back);
+    });
+}
+
+// Do the action given by the pressed button, Escape goes back as well.
+fn handle_buttons(
+    mut commands: Commands,
+    mut kb: ResMut<Input<KeyCode>>,
+    mut app_state: ResMut<State<GameState>>,
+    query: Query<(&Interaction, &OnlineMenuItem), Changed<Interaction>>,
+) {
+    if kb.clear_just_pressed(KeyCode::Escape) {
+        app_state
+            .set(GameState::InMenu)
+            .expect("Something went wrong!");
+        return;
+    }
+
+    for (interaction, item) in query.iter() {
+        if interaction == &Interaction::Clicked {
+            let next = match item {
+                OnlineMenuItem::JoinServer => GameState::Connecting,
+                OnlineMenuItem::BrowseRooms => GameState::Browse,
+                OnlineMenuItem::HostPeer => {
+                    commands.insert_resource(PeerRole::Host);
+                    GameState::PeerConnecting
+                }
+                OnlineMenuItem::JoinPeer => {
+                    commands.insert_resource(PeerRole::Join);
+                    GameState::PeerConnecting
+                }
+                OnlineMenuItem::Back => GameState::InMenu,
+            };
+            app_state.set(next).expect("Something went wrong!");
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::ui::{spawn_button, spawn_screen, spawn_text};
+use crate::GameState;
+use bevy::prelude::*;
+
+pub struct Pause;
+
+// Marks entities of the pause screen, so they can be removed without touching the match.
+#[derive(Component)]
+struct PauseScreen;
+
+#[derive(Component)]
+enum PauseItem {
+    Resume,
+    Restart,
+    Settings,
+    QuitToMenu,
+}
+
+impl Plugin for Pause {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(GameState::InGame).with_system(pause_system))
+            .add_system_set(SystemSet::on_enter(GameState::Paused).with_system(init_pause_system))
+            .add_system_set(SystemSet::on_resume(GameState::Paused).with_system(init_pause_system))
+            .add_system_set(
+                SystemSet::on_update(GameState::Paused)
+                    .with_system(resume_system)
+                    .with_system(handle_buttons),
+            )
+            .add_system_set(SystemSet::on_pause(GameState::Paused).with_system(despawn_pause))
+            .add_system_set(SystemSet::on_exit(GameState::Paused).with_system(despawn_pause));
+    }
+}
+
+// Pauses the match when Escape is pressed.
+// The match stays in the state stack, so it's still rendered and its physics is frozen.
+fn pause_system(mut kb: ResMut<Input<KeyCode>>, mut app_state: ResMut<State<GameState>>) {
+    if kb.clear_just_pressed(KeyCode::Escape) {
+        app_state
+            .push(GameState::Paused)
+            .expect("Something went wrong!");
+    }
+}
+
+// Resumes the match when Escape is pressed again.
+fn resume_system(mut kb: ResMut<Input<KeyCode>>, mut app_state: ResMut<State<GameState>>) {
+    if kb.clear_just_pressed(KeyCode::Escape) {
+        app_state.pop().expect("Something went wrong!");
+    }
+}
+
+// Do the action given by the pressed button.
+fn handle_buttons(
+    mut app_state: ResMut<State<GameState>>,
+    query: Query<(&Interaction, &PauseItem), Changed<Interaction>>,
+) {
+    for (interaction, item) in query.iter() {
+        if interaction == &Interaction::Clicked {
+            let result = match item {
+                PauseItem::Resume => app_state.pop(),
+                // Leaving the match and entering it again starts it from scratch.
+                PauseItem::Restart => app_state.replace(GameState::InGame),
+                PauseItem::Settings => app_state.push(GameState::Settings),
+                PauseItem::QuitToMenu => app_state.replace(GameState::InMenu),
+            };
+            result.expect("Something went wrong!");
+        }
+    }
+}
+
+// Removes the pause screen, leaving the match untouched.
+fn despawn_pause(mut commands: Commands, query: Query<Entity, With<PauseScreen>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
+
+// Shows the pause screen on top of the match.
+fn init_pause_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(UiCameraBundle::default())
+        .insert(PauseScreen);
+
+    spawn_screen(&mut commands, Color::rgba(0.0, 0.0, 0.0, 0.6))
+        .insert(PauseScreen)
+        .with_children(|parent| {
+            spawn_text(parent, &asset_server, "Paused", 80.0);
+            spawn_button(parent, &asset_server, "Resume", PauseItem::Resume);
+            spawn_button(parent, &asset_server, "Restart Match", PauseItem::Restart);
+            spawn_button(parent, &asset_server, "Settings", PauseItem::Settings);
+            spawn_button(parent, &asset_server, "Quit to Menu", PauseItem::QuitToMenu);
+        });
+}
@@ -0,0 +1,321 @@
+use crate::controls::ControlScheme;
+use crate::input::InputDevices;
+use crate::link::Link;
+use crate::match_over::team_name;
+use crate::online::{connecting_cancelled, first_local_input, playable, show_connecting_status};
+use crate::online::{show_match, show_status, spawn_connecting_screen, spawn_online_match};
+use crate::online::{ConnectingItem, ConnectingText, NetworkSettings, StatusText};
+use crate::online::{CONNECT_TIMEOUT, HELP, JOIN_INTERVAL, PEER_TIMEOUT};
+use crate::stadium::{StadiumAsset, Stadiums};
+use crate::{clock_system, despawn_all_system, interpolation_system, kick_ring_system};
+use crate::{score_system, DiscId, GameState, PreviousPosition};
+use bevy::prelude::*;
+use rustball_core::protocol::{PeerMessage, PROTOCOL_VERSION};
+use rustball_core::{MatchRules, MatchState, RollbackSession, Stadium, PEER_TEAMS};
+use std::time::Instant;
+
+// Plays 1v1 matches directly with another player, without a server, see `RollbackSession`.
+pub struct PeerToPeer;
+
+// Whether the local player hosts the peer-to-peer match or joins it, chosen in the online menu.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PeerRole {
+    // Waits for the other player, plays red on the stadium and with the rules chosen in the menu.
+    Host,
+    Join,
+}
+
+// Waiting for the other peer, shown on the connecting screen.
+struct PeerConnecting {
+    // `None` if the connection couldn't be opened or joining failed.
+    link: Option<Link>,
+    // What's going on, or why joining failed.
+    status: String,
+    // Stadium and rules of the match the hosting peer offers.
+    offer: Option<(Stadium, MatchRules)>,
+    next_join: Instant,
+    deadline: Instant,
+}
+
+// Peer-to-peer match the local player plays.
+pub struct PeerMatch {
+    session: RollbackSession,
+    // Start of the match, sent again by the hosting peer if the first one was lost.
+    start: Option<PeerMessage>,
+    // Whether the other peer left the match.
+    left: bool,
+    desync_reported: bool,
+}
+
+impl Plugin for PeerToPeer {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(GameState::PeerConnecting).with_system(init_connecting_system),
+        )
+        .add_system_set(SystemSet::on_update(GameState::PeerConnecting).with_system(join_system))
+        .add_system_set(
+            SystemSet::on_exit(GameState::PeerConnecting).with_system(exit_connecting_system),
+        )
+        .add_system_set(SystemSet::on_enter(GameState::PeerMatch).with_system(init_match_system))
+        .add_system_set(
+            SystemSet::on_update(GameState::PeerMatch)
+                .with_system(leave_system)
+                .with_system(status_system)
+                .with_system(interpolation_system)
+                .with_system(kick_ring_system)
+                .with_system(score_system)
+                .with_system(clock_system),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::PeerMatch)
+                .with_system(exit_match_system)
+                .with_system(despawn_all_system),
+        );
+    }
+}
+
+// Opens the connection and shows the connecting screen.
+fn init_connecting_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<NetworkSettings>,
+    role: Res<PeerRole>,
+    stadiums: Res<Stadiums>,
+    stadium_assets: Res<Assets<StadiumAsset>>,
+    rules: Res<MatchRules>,
+) {
+    let (link, status, offer) = match *role {
+        PeerRole::Host => {
+            let offer = (stadiums.selected(&stadium_assets), *rules);
+            match Link::listen(settings.host_port, &settings) {
+                Ok(link) => (
+                    Some(link),
+                    format!("Waiting for an opponent on port {}...", settings.host_port),
+                    Some(offer),
+                ),
+                Err(err) => (
+                    None,
+                    format!("Can't host on port {}: {}", settings.host_port, err),
+                    None,
+                ),
+            }
+        }
+        PeerRole::Join => match Link::connect(&settings.peer, &settings) {
+            Ok(link) => (
+                Some(link),
+                format!("Connecting to {}...", settings.peer),
+                None,
+            ),
+            Err(err) => (
+                None,
+                format!("Can't connect to {}: {}", settings.peer, err),
+                None,
+            ),
+        },
+    };
+
+    spawn_connecting_screen(&mut commands, &asset_server, &status);
+    let now = Instant::now();
+    commands.insert_resource(PeerConnecting {
+        link,
+        status,
+        offer,
+        next_join: now,
+        deadline: now + CONNECT_TIMEOUT,
+    });
+}
+
+// The hosting peer waits for a join, the joining one asks until the match starts.
+// Cancel or Escape go back to the online menu.
+#[allow(clippy::too_many_arguments)]
+fn join_system(
+    mut commands: Commands,
+    mut kb: ResMut<Input<KeyCode>>,
+    mut app_state: ResMut<State<GameState>>,
+    settings: Res<NetworkSettings>,
+    role: Res<PeerRole>,
+    mut connecting: ResMut<PeerConnecting>,
+    buttons: Query<(&Interaction, &ConnectingItem), Changed<Interaction>>,
+    mut query: Query<&mut Text, With<ConnectingText>>,
+) {
+    if connecting_cancelled(&mut kb, &buttons) {
+        app_state
+            .set(GameState::OnlineMenu)
+            .expect("Something went wrong!");
+        return;
+    }
+
+    let connecting = &mut *connecting;
+    if let Some(link) = &mut connecting.link {
+        let now = Instant::now();
+        for (address, message) in link.poll() {
+            let peer_match = match (*role, message) {
+                (PeerRole::Host, PeerMessage::Join { version }) if version != PROTOCOL_VERSION => {
+                    let reason = format!(
+                        "The host speaks protocol {}, the peer {}",
+                        PROTOCOL_VERSION, version
+                    );
+                    link.send_to(address, &PeerMessage::Rejected(reason));
+                    continue;
+                }
+                (PeerRole::Host, PeerMessage::Join { .. }) => {
+                    let (stadium, rules) = connecting.offer.take().unwrap();
+                    let session = RollbackSession::new(0, stadium.clone(), rules);
+                    let start = PeerMessage::Start {
+                        stadium: Box::new(stadium),
+                        rules,
+                    };
+                    link.set_peer(address);
+                    link.send(&start);
+                    PeerMatch {
+                        session,
+                        start: Some(start),
+                        left: false,
+                        desync_reported: false,
+                    }
+                }
+                (PeerRole::Join, PeerMessage::Start { stadium, .. }) if !playable(&stadium) => {
+                    link.send_now(&PeerMessage::Leave);
+                    connecting.status = "The host offered an invalid stadium".to_string();
+                    connecting.link = None;
+                    break;
+                }
+                (PeerRole::Join, PeerMessage::Start { stadium, rules }) => PeerMatch {
+                    session: RollbackSession::new(1, *stadium, rules),
+                    start: None,
+                    left: false,
+                    desync_reported: false,
+                },
+                (PeerRole::Join, PeerMessage::Rejected(reason)) => {
+                    connecting.status = format!("The host refused: {}", reason);
+                    connecting.link = None;
+                    break;
+                }
+                _ => continue,
+            };
+            commands.insert_resource(peer_match);
+            commands.insert_resource(connecting.link.take().unwrap());
+            app_state
+                .set(GameState::PeerMatch)
+                .expect("Something went wrong!");
+            return;
+        }
+        if let (PeerRole::Join, Some(link)) = (*role, &mut connecting.link) {
+            if now >= connecting.deadline {
+                connecting.status = format!("No answer from {}", settings.peer);
+                connecting.link = None;
+            } else if now >= connecting.next_join {
+                link.send(&PeerMessage::Join {
+                    version: PROTOCOL_VERSION,
+                });
+                connecting.next_join = now + JOIN_INTERVAL;
+            }
+        }
+    }
+
+    show_connecting_status(&connecting.status, &mut query);
+}
+
+fn exit_connecting_system(
+    mut commands: Commands,
+    connecting: Res<PeerConnecting>,
+    query: Query<Entity>,
+) {
+    // The host may have started the match already.
+    if let Some(link) = &connecting.link {
+        link.send_now(&PeerMessage::Leave);
+    }
+    commands.remove_resource::<PeerConnecting>();
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn init_match_system(mut commands: Commands, asset_server: Res<AssetServer>, peer: Res<PeerMatch>) {
+    spawn_online_match(&mut commands, &asset_server, peer.session.state().clone());
+}
+
+// Takes the inputs of the other peer, simulates the tick with the input of the first local player
+// and sends the inputs the other peer may be missing, then shows the predicted match.
+pub fn peer_tick_system(
+    devices: InputDevices,
+    scheme: Res<ControlScheme>,
+    mut link: ResMut<Link>,
+    mut peer: ResMut<PeerMatch>,
+    mut match_state: ResMut<MatchState>,
+    mut positions: Query<(&DiscId, &mut PreviousPosition)>,
+) {
+    for (_, message) in link.poll() {
+        match message {
+            // The start was lost, the joining peer asks again.
+            PeerMessage::Join { .. } => {
+                if let Some(start) = &peer.start {
+                    link.send(start);
+                }
+            }
+            PeerMessage::Inputs { .. } => peer.session.receive(&message),
+            PeerMessage::Leave => peer.left = true,
+            PeerMessage::Start { .. } | PeerMessage::Rejected(_) => {}
+        }
+    }
+
+    peer.session.advance(first_local_input(&devices, &scheme));
+    link.send(&peer.session.message());
+    show_match(
+        &mut match_state,
+        peer.session.state().clone(),
+        &mut positions,
+    );
+
+    if let Some(tick) = peer.session.desync() {
+        if !peer.desync_reported {
+            warn!("The peers' matches differ after tick {}", tick);
+            peer.desync_reported = true;
+        }
+    }
+}
+
+// Escape leaves the match, so does losing or leaving of the other peer.
+fn leave_system(
+    mut kb: ResMut<Input<KeyCode>>,
+    mut app_state: ResMut<State<GameState>>,
+    link: Res<Link>,
+    peer: Res<PeerMatch>,
+) {
+    let timed_out = link.last_heard().elapsed() > PEER_TIMEOUT;
+    if timed_out {
+        warn!("Lost the connection to the other player");
+    }
+    if kb.clear_just_pressed(KeyCode::Escape) || timed_out || peer.left {
+        app_state
+            .set(GameState::OnlineMenu)
+            .expect("Something went wrong!");
+    }
+}
+
+fn exit_match_system(mut commands: Commands, link: Res<Link>) {
+    link.send_now(&PeerMessage::Leave);
+    commands.remove_resource::<Link>();
+    commands.remove_resource::<PeerMatch>();
+}
+
+// Shows the team of the local player, the rollbacks, the desync and the winner of the match.
+fn status_system(peer: Res<PeerMatch>, mut query: Query<&mut Text, With<StatusText>>) {
+    let session = &peer.session;
+    let mut status = format!(
+        "You play {}  Rollbacks: {}  {}",
+        team_name(PEER_TEAMS[session.local()]),
+        session.rollbacks(),
+        HELP
+    );
+    if session.is_waiting() {
+        status.push_str("\nWaiting for the opponent...");
+    }
+    if let Some(tick) = session.desync() {
+        status.push_str(&format!("\nOut of sync since tick {}", tick));
+    }
+    if let Some(winner) = session.confirmed().winner {
+        status.push_str(&format!("\n{} team wins!", team_name(winner)));
+    }
+    show_status(&status, &mut query);
+}
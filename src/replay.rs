@@ -0,0 +1,330 @@
+use crate::ui::TEXT_COLOR;
+use crate::{clock_system, despawn_all_system, kick_ring_system, score_system};
+use crate::{config, move_sprites, remember_positions, spawn_match, Controller};
+use crate::{DiscId, GameState, PreviousPosition, FONT};
+use bevy::prelude::*;
+use rustball_core::{MatchEvent, MatchRules, MatchState, Replay, ReplayPlayer, Stadium};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Records every match and plays the recorded ones in the replay viewer.
+pub struct Replays;
+
+// Directory in the user's configuration directory the replays are saved to.
+const REPLAYS_DIR: &str = "replays";
+const REPLAY_EXTENSION: &str = "rbreplay";
+// Playback speeds the viewer can switch between.
+const SPEEDS: [f64; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+const NORMAL_SPEED: usize = 2;
+// How far the arrow keys jump.
+const SEEK_SECONDS: usize = 5;
+const TICKS_PER_SECOND: usize = rustball_core::TICKS_PER_SECOND as usize;
+const HELP: &str =
+    "Space: play/pause  Left/Right: seek  Up/Down: speed  ,/.: frame  PgUp/PgDn: replay  Esc: menu";
+
+// Replay of the match being played, saved when the match ends.
+pub struct Recording(pub Replay);
+
+// Replay shown in the viewer and how it's played.
+struct Viewer {
+    // Saved replays, from the oldest.
+    files: Vec<PathBuf>,
+    // Index of the shown replay.
+    index: usize,
+    // `None` if there's no replay or it can't be read.
+    player: Option<ReplayPlayer>,
+    paused: bool,
+    // Index of the playback speed.
+    speed: usize,
+    // Part of the next tick already played, used for interpolation.
+    progress: f64,
+}
+
+#[derive(Component)]
+struct StatusText;
+
+impl Plugin for Replays {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_exit(GameState::InGame).with_system(save_replay_system))
+            .add_system_set(SystemSet::on_enter(GameState::Replay).with_system(init_viewer_system))
+            .add_system_set(
+                SystemSet::on_update(GameState::Replay)
+                    .with_system(switch_replay_system)
+                    .with_system(viewer_controls_system)
+                    .with_system(playback_system)
+                    .with_system(status_system)
+                    .with_system(kick_ring_system)
+                    .with_system(score_system)
+                    .with_system(clock_system),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Replay).with_system(despawn_all_system));
+    }
+}
+
+fn replays_dir() -> Option<PathBuf> {
+    config::config_path(REPLAYS_DIR)
+}
+
+// Saves the replay of the match which just ended, named after the time it ended.
+fn save_replay_system(recording: Option<Res<Recording>>) {
+    let recording = match recording {
+        Some(recording) if !recording.0.is_empty() => recording,
+        _ => return,
+    };
+    let dir = match replays_dir() {
+        Some(dir) => dir,
+        None => {
+            warn!("Can't save replay: no configuration directory");
+            return;
+        }
+    };
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let path = dir.join(format!("{}.{}", seconds, REPLAY_EXTENSION));
+    if let Err(err) = write_replay(&path, &recording.0) {
+        warn!("Can't save replay {:?}: {}", path, err);
+    }
+}
+
+fn write_replay(path: &Path, replay: &Replay) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    replay.write_to(&mut BufWriter::new(File::create(path)?))?;
+    Ok(())
+}
+
+fn read_replay(path: &Path) -> anyhow::Result<Replay> {
+    Ok(Replay::read_from(&mut BufReader::new(File::open(path)?))?)
+}
+
+// Saved replays sorted by their names, so from the oldest.
+fn replay_files() -> Vec<PathBuf> {
+    let entries = match replays_dir().map(fs::read_dir) {
+        Some(Ok(entries)) => entries,
+        _ => return Vec::new(),
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == REPLAY_EXTENSION))
+        .collect();
+    files.sort();
+    files
+}
+
+// Opens the viewer with the latest replay.
+fn init_viewer_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let files = replay_files();
+    let mut viewer = Viewer {
+        index: files.len().saturating_sub(1),
+        files,
+        player: None,
+        paused: false,
+        speed: NORMAL_SPEED,
+        progress: 0.,
+    };
+    open_replay(&mut commands, &asset_server, &mut viewer);
+    commands.insert_resource(viewer);
+}
+
+// Reads the selected replay and spawns its match at the start.
+// Without a replay, the empty default stadium is shown.
+fn open_replay(commands: &mut Commands, asset_server: &Res<AssetServer>, viewer: &mut Viewer) {
+    viewer.player = viewer
+        .files
+        .get(viewer.index)
+        .and_then(|path| match read_replay(path) {
+            Ok(replay) => Some(ReplayPlayer::new(replay)),
+            Err(err) => {
+                warn!("Can't read replay {:?}: {}", path, err);
+                None
+            }
+        });
+    viewer.paused = false;
+    viewer.progress = 0.;
+
+    let match_state = match &viewer.player {
+        Some(player) => player.state().clone(),
+        None => MatchState::new(Stadium::default(), &[], MatchRules::default()),
+    };
+    let controllers = match_state
+        .players
+        .iter()
+        .map(|_| Controller::Nobody)
+        .collect();
+    spawn_match(commands, asset_server, &match_state, controllers);
+    commands.insert_resource(match_state);
+
+    commands.spawn_bundle(UiCameraBundle::default());
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text {
+                sections: [String::new(), format!("\n{}", HELP)]
+                    .into_iter()
+                    .map(|value| TextSection {
+                        value,
+                        style: TextStyle {
+                            font: asset_server.load(FONT),
+                            font_size: 24.0,
+                            color: TEXT_COLOR,
+                        },
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(StatusText);
+}
+
+// Shows the previous or the next replay, replacing everything shown.
+fn switch_replay_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    kb: Res<Input<KeyCode>>,
+    mut viewer: ResMut<Viewer>,
+    entities: Query<Entity>,
+) {
+    let index = if kb.just_pressed(KeyCode::PageUp) && viewer.index > 0 {
+        viewer.index - 1
+    } else if kb.just_pressed(KeyCode::PageDown) && viewer.index + 1 < viewer.files.len() {
+        viewer.index + 1
+    } else {
+        return;
+    };
+    for entity in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    viewer.index = index;
+    open_replay(&mut commands, &asset_server, &mut viewer);
+}
+
+// Handles the playback keys of the viewer, Escape goes back to the menu.
+fn viewer_controls_system(
+    mut kb: ResMut<Input<KeyCode>>,
+    mut app_state: ResMut<State<GameState>>,
+    mut viewer: ResMut<Viewer>,
+    mut match_state: ResMut<MatchState>,
+    mut positions: Query<(&DiscId, &mut PreviousPosition)>,
+) {
+    if kb.clear_just_pressed(KeyCode::Escape) {
+        app_state
+            .set(GameState::InMenu)
+            .expect("Something went wrong!");
+        return;
+    }
+
+    if kb.just_pressed(KeyCode::Up) {
+        viewer.speed = (viewer.speed + 1).min(SPEEDS.len() - 1);
+    }
+    if kb.just_pressed(KeyCode::Down) {
+        viewer.speed = viewer.speed.saturating_sub(1);
+    }
+
+    let viewer = &mut *viewer;
+    let player = match &mut viewer.player {
+        Some(player) => player,
+        None => return,
+    };
+    if kb.just_pressed(KeyCode::Space) {
+        viewer.paused = !viewer.paused;
+        // Playing a finished replay starts it again.
+        if !viewer.paused && player.is_finished() {
+            player.seek(0);
+        }
+    }
+
+    let tick = player.tick();
+    let seek_ticks = SEEK_SECONDS * TICKS_PER_SECOND;
+    let target = if kb.just_pressed(KeyCode::Left) {
+        Some(tick.saturating_sub(seek_ticks))
+    } else if kb.just_pressed(KeyCode::Right) {
+        Some(tick + seek_ticks)
+    } else if kb.just_pressed(KeyCode::Comma) {
+        viewer.paused = true;
+        Some(tick.saturating_sub(1))
+    } else if kb.just_pressed(KeyCode::Period) {
+        viewer.paused = true;
+        Some(tick + 1)
+    } else {
+        None
+    };
+    if let Some(target) = target {
+        player.seek(target);
+        viewer.progress = 0.;
+        *match_state = player.state().clone();
+        remember_positions(&match_state, &mut positions);
+    }
+}
+
+// Plays the replay at the chosen speed, moving the sprites smoothly between the ticks.
+fn playback_system(
+    time: Res<Time>,
+    mut viewer: ResMut<Viewer>,
+    mut match_state: ResMut<MatchState>,
+    mut positions: Query<(&DiscId, &mut PreviousPosition)>,
+    mut sprites: Query<(&DiscId, &PreviousPosition, &mut Transform)>,
+) {
+    let viewer = &mut *viewer;
+    if let Some(player) = viewer.player.as_mut().filter(|_| !viewer.paused) {
+        viewer.progress +=
+            time.delta_seconds_f64() * TICKS_PER_SECOND as f64 * SPEEDS[viewer.speed];
+        while viewer.progress >= 1. && !player.is_finished() {
+            viewer.progress -= 1.;
+            remember_positions(&match_state, &mut positions);
+            let events = player.step();
+            *match_state = player.state().clone();
+            // Discs are teleported to the kickoff positions, don't interpolate their way back.
+            if events
+                .iter()
+                .any(|event| matches!(event, MatchEvent::Kickoff(_)))
+            {
+                remember_positions(&match_state, &mut positions);
+            }
+        }
+        if player.is_finished() {
+            viewer.paused = true;
+            viewer.progress = 1.;
+        }
+    }
+    move_sprites(&match_state, viewer.progress.min(1.) as f32, &mut sprites);
+}
+
+// Shows which replay is played, where and how fast.
+fn status_system(viewer: Res<Viewer>, mut query: Query<&mut Text, With<StatusText>>) {
+    let clock = |ticks: usize| {
+        let seconds = ticks / TICKS_PER_SECOND;
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    };
+    let status = match (&viewer.player, viewer.files.get(viewer.index)) {
+        (Some(player), Some(path)) => format!(
+            "{} ({}/{})  {} / {}  {}x{}",
+            path.file_stem().unwrap_or_default().to_string_lossy(),
+            viewer.index + 1,
+            viewer.files.len(),
+            clock(player.tick()),
+            clock(player.replay().len()),
+            SPEEDS[viewer.speed],
+            if viewer.paused { "  Paused" } else { "" },
+        ),
+        (None, Some(path)) => format!("Can't read {}", path.display()),
+        (_, None) => "No replays yet, play a match first".to_string(),
+    };
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value = status.clone();
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crate::menu::next_choice;
+use crate::online::NetworkSettings;
+use crate::ui::{spawn_button, spawn_screen, spawn_text};
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::window::{PresentMode, WindowMode};
+
+pub struct Settings;
+
+// Simulated network conditions to choose from, for testing online play on localhost.
+const LATENCIES: [u32; 5] = [0, 25, 50, 100, 200];
+const PACKET_LOSSES: [u32; 5] = [0, 1, 5, 10, 20];
+
+// Options of the window, changed in the settings screen.
+pub struct DisplaySettings {
+    pub fullscreen: bool,
+    pub vsync: bool,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        DisplaySettings {
+            fullscreen: false,
+            vsync: true,
+        }
+    }
+}
+
+// Marks entities of the settings screen.
+#[derive(Component)]
+struct SettingsScreen;
+
+#[derive(Component)]
+enum SettingsItem {
+    Fullscreen,
+    VSync,
+    SimulatedLag,
+    PacketLoss,
+    Back,
+}
+
+impl Plugin for Settings {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DisplaySettings>()
+            .add_system(apply_display_settings)
+            .add_system_set(
+                SystemSet::on_enter(GameState::Settings).with_system(init_settings_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Settings)
+                    .with_system(handle_buttons)
+                    .with_system(update_labels),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Settings).with_system(despawn_settings));
+    }
+}
+
+// Text shown on the button.
+fn button_label(
+    item: &SettingsItem,
+    settings: &DisplaySettings,
+    network: &NetworkSettings,
+) -> String {
+    let on_off = |value| if value { "On" } else { "Off" };
+    match item {
+        SettingsItem::Fullscreen => format!("Fullscreen: {}", on_off(settings.fullscreen)),
+        SettingsItem::VSync => format!("VSync: {}", on_off(settings.vsync)),
+        SettingsItem::SimulatedLag => match network.latency {
+            0 => "Simulated Lag: Off".to_string(),
+            latency => format!("Simulated Lag: {} ms", latency),
+        },
+        SettingsItem::PacketLoss => match network.packet_loss {
+            0 => "Packet Loss: Off".to_string(),
+            loss => format!("Packet Loss: {}%", loss),
+        },
+        SettingsItem::Back => "Back".to_string(),
+    }
+}
+
+// Do the action given by the pressed button, Escape goes back as well.
+fn handle_buttons(
+    mut kb: ResMut<Input<KeyCode>>,
+    mut app_state: ResMut<State<GameState>>,
+    mut settings: ResMut<DisplaySettings>,
+    mut network: ResMut<NetworkSettings>,
+    query: Query<(&Interaction, &SettingsItem), Changed<Interaction>>,
+) {
+    if kb.clear_just_pressed(KeyCode::Escape) {
+        app_state.pop().expect("Something went wrong!");
+        return;
+    }
+
+    for (interaction, item) in query.iter() {
+        if interaction == &Interaction::Clicked {
+            match item {
+                SettingsItem::Fullscreen => settings.fullscreen = !settings.fullscreen,
+                SettingsItem::VSync => settings.vsync = !settings.vsync,
+                SettingsItem::SimulatedLag => {
+                    network.latency = next_choice(&LATENCIES, network.latency);
+                    network.save();
+                }
+                SettingsItem::PacketLoss => {
+                    network.packet_loss = next_choice(&PACKET_LOSSES, network.packet_loss);
+                    network.save();
+                }
+                SettingsItem::Back => app_state.pop().expect("Something went wrong!"),
+            }
+        }
+    }
+}
+
+// Updates labels of the buttons after the settings were changed.
+fn update_labels(
+    settings: Res<DisplaySettings>,
+    network: Res<NetworkSettings>,
+    query: Query<(&SettingsItem, &Children)>,
+    mut query_text: Query<&mut Text>,
+) {
+    if !settings.is_changed() && !network.is_changed() {
+        return;
+    }
+    for (item, children) in query.iter() {
+        for &child in children.iter() {
+            if let Ok(mut text) = query_text.get_mut(child) {
+                text.sections[0].value = button_label(item, &settings, &network);
+            }
+        }
+    }
+}
+
+// Changes the window after the settings were changed.
+fn apply_display_settings(settings: Res<DisplaySettings>, mut windows: ResMut<Windows>) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_mode(if settings.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        });
+        window.set_present_mode(if settings.vsync {
+            PresentMode::Fifo
+        } else {
+            PresentMode::Immediate
+        });
+    }
+}
+
+fn despawn_settings(mut commands: Commands, query: Query<Entity, With<SettingsScreen>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
+
+// Shows the settings screen on top of the previous one.
+fn init_settings_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<DisplaySettings>,
+    network: Res<NetworkSettings>,
+) {
+    commands
+        .spawn_bundle(UiCameraBundle::default())
+        .insert(SettingsScreen);
+
+    spawn_screen(&mut commands, Color::rgba(0.0, 0.0, 0.0, 0.6))
+        .insert(SettingsScreen)
+        .with_children(|parent| {
+            spawn_text(parent, &asset_server, "Settings", 80.0);
+            for item in [
+                SettingsItem::Fullscreen,
+                SettingsItem::VSync,
+                SettingsItem::SimulatedLag,
+                SettingsItem::PacketLoss,
+                SettingsItem::Back,
+            ] {
+                let label = button_label(&item, &settings, &network);
+                spawn_button(parent, &asset_server, label, item);
+            }
+        });
+}
@@ -0,0 +1,173 @@
+use bevy::asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset};
+use bevy::math::{vec3, Mat2};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use rustball_core::{Background, Segment, Stadium};
+
+// Folder of the assets with the stadiums to choose from.
+const STADIUMS_FOLDER: &str = "stadiums";
+const WALL_THICKNESS: f32 = 4.0;
+// Length of the straight pieces curved walls are drawn with.
+const WALL_PIECE_LENGTH: f32 = 10.0;
+const WALL_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+
+pub struct StadiumAssets;
+
+// Stadium read from a `.stadium.ron` file or imported from a HaxBall `.hbs` file.
+#[derive(TypeUuid)]
+#[uuid = "6f1d9b3e-2c47-4b8a-9e55-0d3a7c1f4e21"]
+pub struct StadiumAsset(pub Stadium);
+
+#[derive(Default)]
+struct StadiumLoader;
+
+impl AssetLoader for StadiumLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let source = std::str::from_utf8(bytes)?;
+            let is_haxball = load_context
+                .path()
+                .extension()
+                .is_some_and(|extension| extension == "hbs");
+            let stadium = if is_haxball {
+                Stadium::from_hbs(source)?
+            } else {
+                Stadium::from_ron(source)?
+            };
+            load_context.set_default_asset(LoadedAsset::new(StadiumAsset(stadium)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["stadium.ron", "hbs"]
+    }
+}
+
+// All the stadiums found in the assets, sorted by their file names.
+pub struct Stadiums {
+    handles: Vec<Handle<StadiumAsset>>,
+    // Index of the stadium the next match is played on.
+    selected: usize,
+}
+
+impl Stadiums {
+    // The selected stadium, or the default one if it couldn't be loaded.
+    pub fn selected(&self, assets: &Assets<StadiumAsset>) -> Stadium {
+        self.handles
+            .get(self.selected)
+            .and_then(|handle| assets.get(handle))
+            .map_or_else(Stadium::default, |asset| asset.0.clone())
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.handles.is_empty() {
+            self.selected = (self.selected + 1) % self.handles.len();
+        }
+    }
+}
+
+impl Plugin for StadiumAssets {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<StadiumAsset>()
+            .init_asset_loader::<StadiumLoader>()
+            .add_startup_system(load_stadiums_system);
+    }
+}
+
+fn load_stadiums_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let mut handles: Vec<Handle<StadiumAsset>> = match asset_server.load_folder(STADIUMS_FOLDER) {
+        Ok(handles) => handles.into_iter().map(|handle| handle.typed()).collect(),
+        Err(err) => {
+            warn!("Can't load stadiums: {:?}", err);
+            Vec::new()
+        }
+    };
+    handles.sort_by_key(|handle| {
+        asset_server
+            .get_handle_path(handle)
+            .map(|path| path.path().to_owned())
+    });
+
+    commands.insert_resource(Stadiums {
+        handles,
+        selected: 0,
+    });
+}
+
+// Texture and its tint drawing the background of the stadium.
+pub fn background_texture(
+    background: &Background,
+    asset_server: &AssetServer,
+) -> (Handle<Image>, Color) {
+    match background {
+        Background::Image(path) => (asset_server.load(path.as_str()), Color::WHITE),
+        Background::Color(r, g, b) => (Default::default(), Color::rgb(*r, *g, *b)),
+    }
+}
+
+// Spawns sprites of the pitch and the walls of the stadium.
+pub fn spawn_stadium(commands: &mut Commands, asset_server: &AssetServer, stadium: &Stadium) {
+    let (texture, color) = background_texture(&stadium.background, asset_server);
+    commands.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            color,
+            custom_size: Some(Vec2::new(stadium.width, stadium.height)),
+            ..Default::default()
+        },
+        texture,
+        transform: Transform::from_translation(vec3(0.0, 0.0, 1.0)),
+        ..Default::default()
+    });
+
+    for wall in stadium.walls.iter().filter(|wall| wall.visible) {
+        let points = wall_points(wall);
+        for piece in points.windows(2) {
+            commands.spawn_bundle(line_sprite(
+                piece[0],
+                piece[1],
+                WALL_THICKNESS,
+                WALL_COLOR,
+                2.0,
+            ));
+        }
+    }
+}
+
+// Straight line between the given points, drawn as a thin rotated sprite.
+pub fn line_sprite(a: Vec2, b: Vec2, thickness: f32, color: Color, z: f32) -> SpriteBundle {
+    let delta = b - a;
+    SpriteBundle {
+        sprite: Sprite {
+            color,
+            custom_size: Some(Vec2::new(delta.length(), thickness)),
+            ..Default::default()
+        },
+        transform: Transform {
+            translation: ((a + b) / 2.).extend(z),
+            rotation: Quat::from_rotation_z(delta.y.atan2(delta.x)),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+// Points along the wall, curved walls are split into short straight pieces.
+fn wall_points(wall: &Segment) -> Vec<Vec2> {
+    let arc = match wall.arc() {
+        Some(arc) => arc,
+        None => return vec![wall.a, wall.b],
+    };
+    let pieces = ((arc.angle * arc.radius / WALL_PIECE_LENGTH).ceil() as usize).max(1);
+    let start = arc.start - arc.center;
+    (0..=pieces)
+        .map(|i| {
+            let angle = arc.angle * i as f32 / pieces as f32;
+            arc.center + Mat2::from_angle(angle) * start
+        })
+        .collect()
+}
@@ -0,0 +1,156 @@
+use crate::FONT;
+use bevy::ecs::system::EntityCommands;
+use bevy::{prelude::*, ui::FocusPolicy};
+
+// Color of the texts on the screens.
+pub const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+
+// Spawns a line of text as the child of the given parent.
+pub fn spawn_text(
+    parent: &mut ChildBuilder,
+    asset_server: &Res<AssetServer>,
+    value: impl Into<String>,
+    font_size: f32,
+) {
+    parent.spawn_bundle(TextBundle {
+        style: Style {
+            align_self: AlignSelf::Center,
+            margin: Rect::all(Val::Px(5.0)),
+            ..Default::default()
+        },
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: asset_server.load(FONT),
+                font_size,
+                color: TEXT_COLOR,
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    });
+}
+
+// Spawns new button with the given label as the child of the given parent.
+// The item is inserted into the button, so clicks can be matched to actions.
+pub fn spawn_button<T: Component>(
+    parent: &mut ChildBuilder,
+    asset_server: &Res<AssetServer>,
+    label: impl Into<String>,
+    item: T,
+) {
+    spawn_sized_button(
+        parent,
+        asset_server,
+        label,
+        item,
+        Vec2::new(300.0, 70.0),
+        40.0,
+    );
+}
+
+// Same as `spawn_button`, but fits more buttons side by side.
+pub fn spawn_small_button<T: Component>(
+    parent: &mut ChildBuilder,
+    asset_server: &Res<AssetServer>,
+    label: impl Into<String>,
+    item: T,
+) {
+    spawn_sized_button(
+        parent,
+        asset_server,
+        label,
+        item,
+        Vec2::new(230.0, 50.0),
+        28.0,
+    );
+}
+
+// Same as `spawn_button`, but fits a whole line of a list.
+pub fn spawn_wide_button<T: Component>(
+    parent: &mut ChildBuilder,
+    asset_server: &Res<AssetServer>,
+    label: impl Into<String>,
+    item: T,
+) {
+    spawn_sized_button(
+        parent,
+        asset_server,
+        label,
+        item,
+        Vec2::new(900.0, 40.0),
+        26.0,
+    );
+}
+
+fn spawn_sized_button<T: Component>(
+    parent: &mut ChildBuilder,
+    asset_server: &Res<AssetServer>,
+    label: impl Into<String>,
+    item: T,
+    size: Vec2,
+    font_size: f32,
+) {
+    parent
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                align_self: AlignSelf::Center,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                size: Size::new(Val::Px(size.x), Val::Px(size.y)),
+                margin: Rect::all(Val::Px(10.0)),
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    label,
+                    TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size,
+                        color: TEXT_COLOR,
+                    },
+                    Default::default(),
+                ),
+                focus_policy: FocusPolicy::Pass,
+                ..Default::default()
+            });
+        })
+        .insert(item);
+}
+
+// Spawns a full screen column the screen's texts and buttons are placed in.
+// Translucent background lets the paused match be seen underneath.
+pub fn spawn_screen<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    background: Color,
+) -> EntityCommands<'w, 's, 'a> {
+    commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+            flex_direction: FlexDirection::ColumnReverse,
+            justify_content: JustifyContent::Center,
+            ..Default::default()
+        },
+        color: background.into(),
+        ..Default::default()
+    })
+}
+
+// Spawns a row of buttons side by side as the child of the given parent.
+pub fn spawn_row<'w, 's, 'a>(
+    parent: &'a mut ChildBuilder<'w, 's, '_>,
+) -> EntityCommands<'w, 's, 'a> {
+    parent.spawn_bundle(NodeBundle {
+        style: Style {
+            align_self: AlignSelf::Center,
+            justify_content: JustifyContent::Center,
+            ..Default::default()
+        },
+        color: Color::NONE.into(),
+        ..Default::default()
+    })
+}